@@ -0,0 +1,73 @@
+// The hardware envelope always runs in 32 steps; the AY-3-8910 only has
+// 16 distinct levels, which its volume table accounts for.
+const LAST_STEP: u8 = 31;
+
+const CONTINUE: u8 = 0x08;
+const ATTACK: u8 = 0x04;
+const ALTERNATE: u8 = 0x02;
+const HOLD: u8 = 0x01;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Envelope {
+    period: u16,
+    counter: u16,
+    shape: u8,
+    step: u8,
+    attack: bool,
+    holding: bool,
+}
+
+impl Envelope {
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => self.period = (self.period & 0xFF00) | value as u16,
+            1 => self.period = (self.period & 0x00FF) | ((value as u16) << 8),
+            _ => self.set_shape(value),
+        }
+    }
+
+    // Writing the shape register restarts the envelope.
+    fn set_shape(&mut self, value: u8) {
+        self.shape = value & 0x0F;
+        self.counter = 0;
+        self.step = 0;
+        self.attack = self.shape & ATTACK != 0;
+        self.holding = false;
+    }
+
+    pub fn clock(&mut self) {
+        if self.holding {
+            return;
+        }
+        self.counter += 1;
+        if self.counter < self.period.max(1) {
+            return;
+        }
+        self.counter = 0;
+        if self.step < LAST_STEP {
+            self.step += 1;
+            return;
+        }
+        if self.shape & CONTINUE == 0 {
+            self.holding = true;
+            self.attack = false;
+        } else if self.shape & HOLD != 0 {
+            self.holding = true;
+            if self.shape & ALTERNATE != 0 {
+                self.attack = !self.attack;
+            }
+        } else {
+            if self.shape & ALTERNATE != 0 {
+                self.attack = !self.attack;
+            }
+            self.step = 0;
+        }
+    }
+
+    pub fn level(&self) -> u8 {
+        if self.attack {
+            return self.step;
+        }
+        return LAST_STEP - self.step;
+    }
+}
@@ -0,0 +1,392 @@
+use builder_derive_macro::Setters;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::clocks::ZX_SPECTRUM_AY_CLOCK;
+use crate::time::milliseconds_to_samples;
+use crate::utils::build::Build;
+use crate::waves::traits::has_tone::parse_note;
+
+use super::{
+    DcBlocker, InvalidApu, InvalidApuKind, RegisterSchedule, ScheduleNotes, milliseconds_to_cycles,
+};
+
+mod envelope;
+mod noise;
+mod tests;
+mod tone;
+use envelope::Envelope;
+use noise::NoiseChannel;
+use tone::ToneChannel;
+
+// The counters of the chip are clocked once every 8 input clocks.
+const CLOCKS_PER_STEP: u64 = 8;
+const OUTPUT_HIGH_PASS_FREQUENCY: f64 = 20.0;
+
+// Measured DAC levels of both chips, indexed by envelope step. Fixed
+// volumes use every odd entry.
+const AY_3_8910_VOLUMES: [f64; 32] = [
+    0.0,
+    0.0,
+    0.00999465934234,
+    0.00999465934234,
+    0.0144502937362,
+    0.0144502937362,
+    0.0210574502174,
+    0.0210574502174,
+    0.0307011520562,
+    0.0307011520562,
+    0.0455481803616,
+    0.0455481803616,
+    0.0644998855573,
+    0.0644998855573,
+    0.107362478065,
+    0.107362478065,
+    0.126588845655,
+    0.126588845655,
+    0.20498970016,
+    0.20498970016,
+    0.292210269322,
+    0.292210269322,
+    0.372838941024,
+    0.372838941024,
+    0.492530708782,
+    0.492530708782,
+    0.635324635691,
+    0.635324635691,
+    0.805584802014,
+    0.805584802014,
+    1.0,
+    1.0,
+];
+const YM2149_VOLUMES: [f64; 32] = [
+    0.0,
+    0.0,
+    0.00465400167849,
+    0.00772106507973,
+    0.0109559777218,
+    0.0139620050355,
+    0.0169985503929,
+    0.0200198367285,
+    0.024368657969,
+    0.029694056611,
+    0.0350652323186,
+    0.0403906309606,
+    0.0485389486534,
+    0.0583352407111,
+    0.0680552376593,
+    0.0777752346075,
+    0.0925154497597,
+    0.111085679408,
+    0.129747463188,
+    0.148485542077,
+    0.17666895552,
+    0.211551079576,
+    0.246387426566,
+    0.281101701381,
+    0.333730067903,
+    0.400427252613,
+    0.467383840696,
+    0.53443198291,
+    0.635172045472,
+    0.75800717174,
+    0.879926756695,
+    1.0,
+];
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum AyModel {
+    #[default]
+    Ay38910,
+    Ym2149,
+}
+
+impl AyModel {
+    pub fn volumes(&self) -> &'static [f64; 32] {
+        match self {
+            Self::Ay38910 => &AY_3_8910_VOLUMES,
+            Self::Ym2149 => &YM2149_VOLUMES,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AyChannel {
+    A,
+    B,
+    C,
+}
+
+impl AyChannel {
+    fn index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+// With the envelope flag set, the channel ignores its volume for the envelope.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct AyVoice {
+    volume: u8,
+    tone: bool,
+    noise: bool,
+    noise_period: u8,
+    envelope: bool,
+    envelope_shape: u8,
+    envelope_period: u16,
+}
+
+impl Default for AyVoice {
+    fn default() -> Self {
+        return Self {
+            volume: 15,
+            tone: true,
+            noise: false,
+            noise_period: 0,
+            envelope: false,
+            envelope_shape: 0,
+            envelope_period: 0,
+        };
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct Ay8910Builder {
+    clock: f64,
+    model: AyModel,
+    sampling_frequency: f64,
+}
+
+impl Default for Ay8910Builder {
+    fn default() -> Self {
+        return Self {
+            clock: ZX_SPECTRUM_AY_CLOCK,
+            model: AyModel::default(),
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl Build for Ay8910Builder {
+    type Output = Ay8910;
+    type Error = InvalidApu;
+
+    fn validate(&self) -> Result<(), Vec<InvalidApu>> {
+        let mut possible_errors: Vec<InvalidApu> = vec![];
+        if self.sampling_frequency <= 0.0 {
+            possible_errors.push(InvalidApu::new(
+                InvalidApuKind::NonPositiveSamplingFrequency,
+            ));
+        }
+        if self.clock <= 0.0 {
+            possible_errors.push(InvalidApu::new(InvalidApuKind::NonPositiveClock));
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    fn finalize(self) -> Result<Ay8910, InvalidApu> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        return Ok(Ay8910 {
+            tones: [
+                ToneChannel::default(),
+                ToneChannel::default(),
+                ToneChannel::default(),
+            ],
+            noise: NoiseChannel::default(),
+            envelope: Envelope::default(),
+            mixer: 0xFF,
+            amplitudes: [0; 3],
+            volumes: self.model.volumes(),
+            step: 0,
+            clock: self.clock,
+            sampling_frequency: self.sampling_frequency,
+            step_phase: 0.0,
+            scheduled_writes: RegisterSchedule::default(),
+            voices: vec![AyVoice::default(); 3],
+            dc_blocker: DcBlocker::new(OUTPUT_HIGH_PASS_FREQUENCY, self.sampling_frequency),
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ay8910 {
+    tones: [ToneChannel; 3],
+    noise: NoiseChannel,
+    envelope: Envelope,
+    mixer: u8,
+    amplitudes: [u8; 3],
+    volumes: &'static [f64; 32],
+    step: u64,
+    clock: f64,
+    sampling_frequency: f64,
+    step_phase: f64,
+    scheduled_writes: RegisterSchedule,
+    voices: Vec<AyVoice>,
+    dc_blocker: DcBlocker,
+}
+
+#[allow(dead_code)]
+impl Ay8910 {
+    // Registers are numbered 0 to 15 as on the chip; the last two drive its
+    // I/O ports and are ignored.
+    pub fn write_register(&mut self, address: u16, value: u8) -> Result<(), InvalidApu> {
+        match address {
+            0..=5 => self.tones[(address / 2) as usize].write(address % 2, value),
+            6 => self.noise.write(value),
+            7 => self.mixer = value,
+            8..=10 => self.amplitudes[(address - 8) as usize] = value & 0x1F,
+            11..=13 => self.envelope.write(address - 11, value),
+            14 | 15 => (),
+            _ => return Err(InvalidApu::new(InvalidApuKind::UnknownRegister)),
+        }
+        return Ok(());
+    }
+
+    pub fn schedule_register_write(
+        &mut self,
+        time_ms: f64,
+        address: u16,
+        value: u8,
+    ) -> Result<(), InvalidApu> {
+        if time_ms < 0.0 {
+            return Err(InvalidApu::new(InvalidApuKind::NegativeTime));
+        }
+        if address > 15 {
+            return Err(InvalidApu::new(InvalidApuKind::UnknownRegister));
+        }
+        let cycle = milliseconds_to_cycles(self.clock, time_ms);
+        self.scheduled_writes.schedule(cycle, address, value);
+        return Ok(());
+    }
+
+    pub fn get_voice(&self, channel: AyChannel) -> &AyVoice {
+        return &self.voices[channel.index()];
+    }
+
+    pub fn set_voice(&mut self, channel: AyChannel, voice: AyVoice) {
+        self.voices[channel.index()] = voice;
+    }
+
+    pub fn render_ms(&mut self, duration_ms: f64) -> Audio {
+        let number_of_samples = milliseconds_to_samples(self.sampling_frequency, duration_ms);
+        let steps_per_sample = self.clock / (CLOCKS_PER_STEP as f64 * self.sampling_frequency);
+        let mut samples = Vec::with_capacity(number_of_samples);
+        for _ in 0..number_of_samples {
+            self.step_phase += steps_per_sample;
+            let steps = self.step_phase.floor() as usize;
+            self.step_phase -= steps as f64;
+            let mut accumulated = 0.0;
+            for _ in 0..steps {
+                self.step();
+                accumulated += self.mix();
+            }
+            let sample = accumulated / steps.max(1) as f64;
+            samples.push(self.dc_blocker.filter(sample));
+        }
+        let builder = AudioBuilder::new(samples, self.sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+
+    // A set bit disables the tone or the noise of a channel.
+    fn voice_mixer(&self) -> u8 {
+        let mut mixer = 0x00;
+        for (index, voice) in self.voices.iter().enumerate() {
+            if !voice.tone {
+                mixer |= 0x01 << index;
+            }
+            if !voice.noise {
+                mixer |= 0x08 << index;
+            }
+        }
+        return mixer;
+    }
+
+    fn step(&mut self) {
+        let cycle = self.step * CLOCKS_PER_STEP;
+        while let Some(write) = self.scheduled_writes.pop_due(cycle) {
+            let _ = self.write_register(write.address, write.value);
+        }
+        for tone in self.tones.iter_mut() {
+            tone.clock();
+        }
+        self.noise.clock();
+        self.envelope.clock();
+        self.step += 1;
+    }
+
+    // A channel sounds while both its tone and its noise inputs are high,
+    // and a disabled input counts as always high.
+    fn mix(&self) -> f64 {
+        let mut accumulated = 0.0;
+        for (index, tone) in self.tones.iter().enumerate() {
+            let tone_on = tone.output() || self.mixer & (0x01 << index) != 0;
+            let noise_on = self.noise.output() || self.mixer & (0x08 << index) != 0;
+            if !tone_on || !noise_on {
+                continue;
+            }
+            let amplitude = self.amplitudes[index];
+            let level = if amplitude & 0x10 != 0 {
+                self.envelope.level()
+            } else {
+                2 * (amplitude & 0x0F) + 1
+            };
+            accumulated += self.volumes[level as usize];
+        }
+        return accumulated / 3.0;
+    }
+}
+
+impl ScheduleNotes for Ay8910 {
+    type Channel = AyChannel;
+
+    // The mixer and the noise period are shared by the three channels, so
+    // every note rewrites the mixer from the voices of all of them.
+    fn schedule_note(
+        &mut self,
+        channel: AyChannel,
+        start_ms: f64,
+        duration_ms: f64,
+        note: &str,
+    ) -> Result<(), InvalidApu> {
+        let end_ms = start_ms + duration_ms.max(0.0);
+        let index = channel.index() as u16;
+        let voice = self.voices[channel.index()].clone();
+        let frequency = parse_note(note).ok_or(InvalidApu::new(InvalidApuKind::UnknownNote))?;
+        let period = (self.clock / (16.0 * frequency)).round();
+        if !(1.0..=4095.0).contains(&period) {
+            return Err(InvalidApu::new(InvalidApuKind::NoteOutOfRange));
+        }
+        let period = period as u16;
+        let amplitude = if voice.envelope {
+            0x10
+        } else {
+            voice.volume.min(15)
+        };
+        let mut writes: Vec<(f64, u16, u8)> = vec![
+            (start_ms, 2 * index, (period & 0xFF) as u8),
+            (start_ms, 2 * index + 1, (period >> 8) as u8),
+            (start_ms, 7, self.voice_mixer()),
+            (start_ms, 8 + index, amplitude),
+        ];
+        if voice.noise {
+            writes.push((start_ms, 6, voice.noise_period & 0x1F));
+        }
+        if voice.envelope {
+            writes.push((start_ms, 11, (voice.envelope_period & 0xFF) as u8));
+            writes.push((start_ms, 12, (voice.envelope_period >> 8) as u8));
+            writes.push((start_ms, 13, voice.envelope_shape & 0x0F));
+        }
+        writes.push((end_ms, 8 + index, 0x00));
+        for (time_ms, address, value) in writes {
+            self.schedule_register_write(time_ms, address, value)?;
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,37 @@
+use crate::waves::Lfsr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseChannel {
+    period: u8,
+    counter: u16,
+    lfsr: Lfsr,
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        return Self {
+            period: 0,
+            counter: 0,
+            lfsr: Lfsr::new(17, vec![0, 3]).expect("a valid register"),
+        };
+    }
+}
+
+impl NoiseChannel {
+    pub fn write(&mut self, value: u8) {
+        self.period = value & 0x1F;
+    }
+
+    // The noise runs at half the rate of a tone with the same period.
+    pub fn clock(&mut self) {
+        self.counter += 1;
+        if self.counter >= 2 * self.period.max(1) as u16 {
+            self.counter = 0;
+            self.lfsr.step();
+        }
+    }
+
+    pub fn output(&self) -> bool {
+        return self.lfsr.output();
+    }
+}
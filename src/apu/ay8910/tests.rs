@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::utils::testing::{assert_note_errors, assert_note_pitch};
+
+    #[test]
+    fn test_note_pitch() {
+        for model in [AyModel::Ay38910, AyModel::Ym2149] {
+            assert_note_pitch(
+                &[AyChannel::B],
+                || {
+                    Ay8910Builder::default()
+                        .with_model(model.clone())
+                        .finalize()
+                        .unwrap()
+                },
+                |psg, duration_ms| psg.render_ms(duration_ms).get_samples(),
+                4,
+            );
+        }
+    }
+
+    #[test]
+    fn test_envelope_decay() {
+        let mut psg = Ay8910Builder::default().finalize().unwrap();
+        let voice = AyVoice::default()
+            .with_envelope(true)
+            .with_envelope_shape(0x00)
+            .with_envelope_period(100);
+        psg.set_voice(AyChannel::A, voice);
+        psg.schedule_note(AyChannel::A, 0.0, 1000.0, "a4").unwrap();
+        let samples = psg.render_ms(300.0).get_samples();
+        assert!(samples[..441].iter().any(|sample| sample.abs() > 0.1));
+        assert!(samples[8820..].iter().all(|sample| sample.abs() < 0.001));
+    }
+
+    #[test]
+    fn test_invalid_commands() {
+        let mut psg = Ay8910Builder::default().finalize().unwrap();
+        assert_eq!(
+            psg.write_register(16, 0x00),
+            Err(InvalidApu::new(InvalidApuKind::UnknownRegister))
+        );
+        assert_note_errors(
+            &mut psg,
+            &[
+                (AyChannel::C, 0.0, "c0", InvalidApuKind::NoteOutOfRange),
+                (AyChannel::C, -1.0, "c4", InvalidApuKind::NegativeTime),
+            ],
+        );
+    }
+}
@@ -0,0 +1,28 @@
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ToneChannel {
+    period: u16,
+    counter: u16,
+    output: bool,
+}
+
+impl ToneChannel {
+    pub fn write(&mut self, register: u16, value: u8) {
+        if register == 0 {
+            self.period = (self.period & 0xF00) | value as u16;
+        } else {
+            self.period = (self.period & 0x0FF) | (((value & 0x0F) as u16) << 8);
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period.max(1) {
+            self.counter = 0;
+            self.output = !self.output;
+        }
+    }
+
+    pub fn output(&self) -> bool {
+        return self.output;
+    }
+}
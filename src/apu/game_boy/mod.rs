@@ -0,0 +1,415 @@
+use builder_derive_macro::Setters;
+
+use crate::audio::stereo::StereoAudio;
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::clocks::GAME_BOY_CPU_CLOCK;
+use crate::time::milliseconds_to_samples;
+use crate::utils::build::Build;
+use crate::waves::traits::has_tone::parse_note;
+
+use super::{
+    DcBlocker, InvalidApu, InvalidApuKind, RegisterSchedule, ScheduleNotes, milliseconds_to_cycles,
+};
+
+mod noise;
+mod square;
+mod tests;
+mod units;
+mod wave;
+use noise::NoiseChannel;
+use square::SquareChannel;
+use wave::{WAVE_TABLE_BYTES, WaveChannel};
+
+// The whole APU is stepped every other CPU cycle, the finest resolution
+// any of its timers need.
+const CYCLES_PER_STEP: u64 = 2;
+// The frame sequencer runs at 512 Hz.
+const FRAME_SEQUENCER_PERIOD: u64 = 8192 / CYCLES_PER_STEP;
+const OUTPUT_HIGH_PASS_FREQUENCY: f64 = 20.0;
+const DEFAULT_WAVE_TABLE: [u8; 2 * WAVE_TABLE_BYTES] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4,
+    3, 2, 1, 0,
+];
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameBoyChannel {
+    Square1,
+    Square2,
+    Wave,
+    Noise,
+}
+
+impl GameBoyChannel {
+    fn index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+// An envelope period of zero keeps the volume constant.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct GameBoyVoice {
+    duty: u8,
+    volume: u8,
+    envelope_period: u8,
+    envelope_increase: bool,
+    short_noise: bool,
+}
+
+impl Default for GameBoyVoice {
+    fn default() -> Self {
+        return Self {
+            duty: 2,
+            volume: 15,
+            envelope_period: 0,
+            envelope_increase: false,
+            short_noise: false,
+        };
+    }
+}
+
+impl GameBoyVoice {
+    fn envelope_register(&self) -> u8 {
+        let direction = if self.envelope_increase { 0x08 } else { 0x00 };
+        return (self.volume.min(15) << 4) | direction | self.envelope_period.min(7);
+    }
+
+    // The wave channel only has four volume levels: mute, 25%, 50% and 100%.
+    fn wave_volume_register(&self) -> u8 {
+        let code = match self.volume {
+            0 => 0,
+            1..=4 => 3,
+            5..=10 => 2,
+            _ => 1,
+        };
+        return code << 5;
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct GameBoyApuBuilder {
+    cpu_clock: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for GameBoyApuBuilder {
+    fn default() -> Self {
+        return Self {
+            cpu_clock: GAME_BOY_CPU_CLOCK,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl Build for GameBoyApuBuilder {
+    type Output = GameBoyApu;
+    type Error = InvalidApu;
+
+    fn validate(&self) -> Result<(), Vec<InvalidApu>> {
+        let mut possible_errors: Vec<InvalidApu> = vec![];
+        if self.sampling_frequency <= 0.0 {
+            possible_errors.push(InvalidApu::new(
+                InvalidApuKind::NonPositiveSamplingFrequency,
+            ));
+        }
+        if self.cpu_clock <= 0.0 {
+            possible_errors.push(InvalidApu::new(InvalidApuKind::NonPositiveClock));
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    fn finalize(self) -> Result<GameBoyApu, InvalidApu> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        let high_pass = DcBlocker::new(OUTPUT_HIGH_PASS_FREQUENCY, self.sampling_frequency);
+        let mut apu = GameBoyApu {
+            square_1: SquareChannel::new(true),
+            square_2: SquareChannel::new(false),
+            wave: WaveChannel::default(),
+            noise: NoiseChannel::default(),
+            powered: false,
+            left_volume: 0,
+            right_volume: 0,
+            panning: 0,
+            frame_step: 0,
+            step: 0,
+            cpu_clock: self.cpu_clock,
+            sampling_frequency: self.sampling_frequency,
+            step_phase: 0.0,
+            scheduled_writes: RegisterSchedule::default(),
+            voices: vec![GameBoyVoice::default(); 4],
+            high_pass: [high_pass.clone(), high_pass],
+        };
+        apu.write_register(0xFF26, 0x80)?;
+        apu.write_register(0xFF24, 0x77)?;
+        apu.write_register(0xFF25, 0xFF)?;
+        apu.set_wave_table(&DEFAULT_WAVE_TABLE);
+        return Ok(apu);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameBoyApu {
+    square_1: SquareChannel,
+    square_2: SquareChannel,
+    wave: WaveChannel,
+    noise: NoiseChannel,
+    powered: bool,
+    left_volume: u8,
+    right_volume: u8,
+    panning: u8,
+    frame_step: u8,
+    step: u64,
+    cpu_clock: f64,
+    sampling_frequency: f64,
+    step_phase: f64,
+    scheduled_writes: RegisterSchedule,
+    voices: Vec<GameBoyVoice>,
+    high_pass: [DcBlocker; 2],
+}
+
+#[allow(dead_code)]
+impl GameBoyApu {
+    pub fn write_register(&mut self, address: u16, value: u8) -> Result<(), InvalidApu> {
+        let is_wave_table = (0xFF30..=0xFF3F).contains(&address);
+        if !self.powered && address != 0xFF26 && !is_wave_table {
+            return Ok(());
+        }
+        match address {
+            0xFF10..=0xFF14 => self.square_1.write(address - 0xFF10, value),
+            0xFF15..=0xFF19 => self.square_2.write(address - 0xFF15, value),
+            0xFF1A..=0xFF1E => self.wave.write(address - 0xFF1A, value),
+            0xFF1F..=0xFF23 => self.noise.write(address - 0xFF1F, value),
+            0xFF24 => {
+                self.left_volume = (value >> 4) & 0x07;
+                self.right_volume = value & 0x07;
+            }
+            0xFF25 => self.panning = value,
+            0xFF26 => {
+                self.powered = value & 0x80 != 0;
+                if !self.powered {
+                    self.power_off();
+                }
+            }
+            0xFF30..=0xFF3F => self.wave.write_table((address - 0xFF30) as usize, value),
+            _ => return Err(InvalidApu::new(InvalidApuKind::UnknownRegister)),
+        }
+        return Ok(());
+    }
+
+    pub fn schedule_register_write(
+        &mut self,
+        time_ms: f64,
+        address: u16,
+        value: u8,
+    ) -> Result<(), InvalidApu> {
+        if time_ms < 0.0 {
+            return Err(InvalidApu::new(InvalidApuKind::NegativeTime));
+        }
+        if !matches!(address, 0xFF10..=0xFF26 | 0xFF30..=0xFF3F) {
+            return Err(InvalidApu::new(InvalidApuKind::UnknownRegister));
+        }
+        let cycle = milliseconds_to_cycles(self.cpu_clock, time_ms);
+        self.scheduled_writes.schedule(cycle, address, value);
+        return Ok(());
+    }
+
+    // Writes the 32 4-bit samples of the wave channel, two per byte.
+    pub fn set_wave_table(&mut self, samples: &[u8; 2 * WAVE_TABLE_BYTES]) {
+        for (index, pair) in samples.chunks(2).enumerate() {
+            let byte = ((pair[0] & 0x0F) << 4) | (pair[1] & 0x0F);
+            self.wave.write_table(index, byte);
+        }
+    }
+
+    pub fn get_voice(&self, channel: GameBoyChannel) -> &GameBoyVoice {
+        return &self.voices[channel.index()];
+    }
+
+    pub fn set_voice(&mut self, channel: GameBoyChannel, voice: GameBoyVoice) {
+        self.voices[channel.index()] = voice;
+    }
+
+    pub fn render_ms(&mut self, duration_ms: f64) -> StereoAudio {
+        let number_of_samples = milliseconds_to_samples(self.sampling_frequency, duration_ms);
+        let steps_per_sample = self.cpu_clock / (CYCLES_PER_STEP as f64 * self.sampling_frequency);
+        let mut left_samples = Vec::with_capacity(number_of_samples);
+        let mut right_samples = Vec::with_capacity(number_of_samples);
+        for _ in 0..number_of_samples {
+            self.step_phase += steps_per_sample;
+            let steps = self.step_phase.floor() as usize;
+            self.step_phase -= steps as f64;
+            let (mut left, mut right) = (0.0, 0.0);
+            for _ in 0..steps {
+                self.step();
+                let (left_output, right_output) = self.mix();
+                left += left_output;
+                right += right_output;
+            }
+            let steps = steps.max(1) as f64;
+            left_samples.push(self.high_pass[0].filter(left / steps));
+            right_samples.push(self.high_pass[1].filter(right / steps));
+        }
+        let left: Audio = AudioBuilder::new(left_samples, self.sampling_frequency)
+            .finalize()
+            .expect("TODO");
+        let right: Audio = AudioBuilder::new(right_samples, self.sampling_frequency)
+            .finalize()
+            .expect("TODO");
+        return StereoAudio::new(left, right);
+    }
+
+    fn power_off(&mut self) {
+        self.square_1 = SquareChannel::new(true);
+        self.square_2 = SquareChannel::new(false);
+        let mut wave = WaveChannel::default();
+        std::mem::swap(&mut wave, &mut self.wave);
+        for (index, byte) in wave.table().iter().enumerate() {
+            self.wave.write_table(index, *byte);
+        }
+        self.noise = NoiseChannel::default();
+        self.left_volume = 0;
+        self.right_volume = 0;
+        self.panning = 0;
+    }
+
+    fn step(&mut self) {
+        let cycle = self.step * CYCLES_PER_STEP;
+        while let Some(write) = self.scheduled_writes.pop_due(cycle) {
+            let _ = self.write_register(write.address, write.value);
+        }
+        if self.step.is_multiple_of(FRAME_SEQUENCER_PERIOD) {
+            self.clock_frame_sequencer();
+        }
+        self.square_1.clock_timer();
+        self.square_2.clock_timer();
+        self.wave.clock_timer();
+        self.noise.clock_timer();
+        self.step += 1;
+    }
+
+    fn clock_frame_sequencer(&mut self) {
+        if self.frame_step.is_multiple_of(2) {
+            self.square_1.clock_length();
+            self.square_2.clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+        if self.frame_step == 2 || self.frame_step == 6 {
+            self.square_1.clock_sweep();
+        }
+        if self.frame_step == 7 {
+            self.square_1.clock_envelope();
+            self.square_2.clock_envelope();
+            self.noise.clock_envelope();
+        }
+        self.frame_step = (self.frame_step + 1) % 8;
+    }
+
+    // Every DAC maps its 4-bit input to -1.0..1.0, the four channels are
+    // panned through NR51 and each side is scaled by its NR50 volume.
+    fn mix(&self) -> (f64, f64) {
+        if !self.powered {
+            return (0.0, 0.0);
+        }
+        let outputs = [
+            self.square_1.output(),
+            self.square_2.output(),
+            self.wave.output(),
+            self.noise.output(),
+        ];
+        let (mut left, mut right) = (0.0, 0.0);
+        for (index, output) in outputs.iter().enumerate() {
+            let analog = match output {
+                Some(value) => *value as f64 / 7.5 - 1.0,
+                None => 0.0,
+            };
+            if self.panning & (0x10 << index) != 0 {
+                left += analog;
+            }
+            if self.panning & (0x01 << index) != 0 {
+                right += analog;
+            }
+        }
+        let left_gain = (self.left_volume + 1) as f64 / 8.0;
+        let right_gain = (self.right_volume + 1) as f64 / 8.0;
+        return (left_gain * left / 4.0, right_gain * right / 4.0);
+    }
+}
+
+impl ScheduleNotes for GameBoyApu {
+    type Channel = GameBoyChannel;
+
+    // The notes of the noise channel are NR43 values ("0" to "255") instead
+    // of pitches.
+    fn schedule_note(
+        &mut self,
+        channel: GameBoyChannel,
+        start_ms: f64,
+        duration_ms: f64,
+        note: &str,
+    ) -> Result<(), InvalidApu> {
+        let end_ms = start_ms + duration_ms.max(0.0);
+        let voice = self.voices[channel.index()].clone();
+        let writes: Vec<(f64, u16, u8)> = match channel {
+            GameBoyChannel::Square1 | GameBoyChannel::Square2 => {
+                let base = if channel == GameBoyChannel::Square1 {
+                    0xFF10
+                } else {
+                    0xFF15
+                };
+                let frequency = frequency_register(note, 131072.0)?;
+                vec![
+                    (start_ms, base, 0x00),
+                    (start_ms, base + 1, voice.duty.min(3) << 6),
+                    (start_ms, base + 2, voice.envelope_register()),
+                    (start_ms, base + 3, (frequency & 0xFF) as u8),
+                    (start_ms, base + 4, 0x80 | (frequency >> 8) as u8),
+                    (end_ms, base + 2, 0x00),
+                ]
+            }
+            GameBoyChannel::Wave => {
+                let frequency = frequency_register(note, 65536.0)?;
+                vec![
+                    (start_ms, 0xFF1A, 0x80),
+                    (start_ms, 0xFF1C, voice.wave_volume_register()),
+                    (start_ms, 0xFF1D, (frequency & 0xFF) as u8),
+                    (start_ms, 0xFF1E, 0x80 | (frequency >> 8) as u8),
+                    (end_ms, 0xFF1A, 0x00),
+                ]
+            }
+            GameBoyChannel::Noise => {
+                let polynomial = note
+                    .trim()
+                    .parse::<u8>()
+                    .map_err(|_error| InvalidApu::new(InvalidApuKind::UnknownNote))?;
+                let width = if voice.short_noise { 0x08 } else { 0x00 };
+                vec![
+                    (start_ms, 0xFF21, voice.envelope_register()),
+                    (start_ms, 0xFF22, (polynomial & !0x08) | width),
+                    (start_ms, 0xFF23, 0x80),
+                    (end_ms, 0xFF21, 0x00),
+                ]
+            }
+        };
+        for (time_ms, address, value) in writes {
+            self.schedule_register_write(time_ms, address, value)?;
+        }
+        return Ok(());
+    }
+}
+
+fn frequency_register(note: &str, base_frequency: f64) -> Result<u16, InvalidApu> {
+    let frequency = parse_note(note).ok_or(InvalidApu::new(InvalidApuKind::UnknownNote))?;
+    let register = (2048.0 - base_frequency / frequency).round();
+    if !(0.0..=2047.0).contains(&register) {
+        return Err(InvalidApu::new(InvalidApuKind::NoteOutOfRange));
+    }
+    return Ok(register as u16);
+}
@@ -0,0 +1,92 @@
+use crate::waves::{Lfsr, LfsrMode};
+
+use super::units::{LengthTimer, VolumeEnvelope};
+
+const NOISE_DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseChannel {
+    enabled: bool,
+    lfsr: Lfsr,
+    period: u32,
+    timer: u32,
+    envelope: VolumeEnvelope,
+    length: LengthTimer,
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        return Self {
+            enabled: false,
+            lfsr: Lfsr::from_mode(&LfsrMode::Long),
+            period: NOISE_DIVISORS[0] / 2,
+            timer: 0,
+            envelope: VolumeEnvelope::default(),
+            length: LengthTimer::new(64),
+        };
+    }
+}
+
+impl NoiseChannel {
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => (),
+            1 => self.length.load(value & 0x3F),
+            2 => {
+                self.envelope.write(value);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => {
+                let mode = if value & 0x08 != 0 {
+                    LfsrMode::GameBoyShort
+                } else {
+                    LfsrMode::Long
+                };
+                self.lfsr.set_mode(&mode);
+                let shift = (value >> 4) as u32;
+                self.period = (NOISE_DIVISORS[(value & 0x07) as usize] << shift) / 2;
+            }
+            _ => {
+                self.length.set_enabled(value & 0x40 != 0);
+                if value & 0x80 != 0 {
+                    self.enabled = self.envelope.dac_enabled();
+                    self.lfsr.fill();
+                    self.length.trigger();
+                    self.envelope.trigger();
+                    self.timer = self.period;
+                }
+            }
+        }
+    }
+
+    pub fn clock_timer(&mut self) {
+        if self.timer <= 1 {
+            self.timer = self.period;
+            self.lfsr.step();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn output(&self) -> Option<u8> {
+        if !self.envelope.dac_enabled() {
+            return None;
+        }
+        if !self.enabled || self.lfsr.output() {
+            return Some(0);
+        }
+        return Some(self.envelope.volume());
+    }
+}
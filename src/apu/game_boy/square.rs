@@ -0,0 +1,124 @@
+use super::units::{LengthTimer, Sweep, VolumeEnvelope};
+
+const DUTY_WAVEFORMS: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0],
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SquareChannel {
+    enabled: bool,
+    duty: u8,
+    step: u8,
+    frequency: u16,
+    timer: u16,
+    sweep: Option<Sweep>,
+    envelope: VolumeEnvelope,
+    length: LengthTimer,
+}
+
+impl SquareChannel {
+    pub fn new(with_sweep: bool) -> Self {
+        return Self {
+            enabled: false,
+            duty: 0,
+            step: 0,
+            frequency: 0,
+            timer: 0,
+            sweep: if with_sweep {
+                Some(Sweep::default())
+            } else {
+                None
+            },
+            envelope: VolumeEnvelope::default(),
+            length: LengthTimer::new(64),
+        };
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                if let Some(sweep) = &mut self.sweep {
+                    sweep.write(value);
+                }
+            }
+            1 => {
+                self.duty = value >> 6;
+                self.length.load(value & 0x3F);
+            }
+            2 => {
+                self.envelope.write(value);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.frequency = (self.frequency & 0x0700) | value as u16,
+            _ => {
+                self.frequency = (self.frequency & 0x00FF) | (((value & 0x07) as u16) << 8);
+                self.length.set_enabled(value & 0x40 != 0);
+                if value & 0x80 != 0 {
+                    self.trigger();
+                }
+            }
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.timer = self.period();
+        self.envelope.trigger();
+        if let Some(sweep) = &mut self.sweep
+            && sweep.trigger(self.frequency)
+        {
+            self.enabled = false;
+        }
+    }
+
+    // In units of two CPU cycles, the rate the whole APU is stepped at.
+    fn period(&self) -> u16 {
+        return (2048 - self.frequency) * 2;
+    }
+
+    pub fn clock_timer(&mut self) {
+        if self.timer <= 1 {
+            self.timer = self.period();
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_sweep(&mut self) {
+        if let Some(sweep) = &mut self.sweep
+            && sweep.clock(&mut self.frequency)
+        {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    // None when the DAC is off, which disconnects the channel entirely
+    // instead of outputting a digital zero.
+    pub fn output(&self) -> Option<u8> {
+        if !self.envelope.dac_enabled() {
+            return None;
+        }
+        let high = DUTY_WAVEFORMS[self.duty as usize][self.step as usize] == 1;
+        if !self.enabled || !high {
+            return Some(0);
+        }
+        return Some(self.envelope.volume());
+    }
+}
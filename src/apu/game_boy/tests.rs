@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::utils::testing::{assert_note_errors, assert_note_pitch};
+
+    #[test]
+    fn test_note_pitch() {
+        assert_note_pitch(
+            &[
+                GameBoyChannel::Square1,
+                GameBoyChannel::Square2,
+                GameBoyChannel::Wave,
+            ],
+            || GameBoyApuBuilder::default().finalize().unwrap(),
+            |apu, duration_ms| apu.render_ms(duration_ms).get_left().clone().get_samples(),
+            4,
+        );
+    }
+
+    #[test]
+    fn test_panning() {
+        let mut apu = GameBoyApuBuilder::default().finalize().unwrap();
+        apu.write_register(0xFF25, 0x10).unwrap();
+        apu.schedule_note(GameBoyChannel::Square1, 0.0, 200.0, "c4")
+            .unwrap();
+        let (left, right) = apu.render_ms(200.0).into_channels();
+        assert!(left.get_samples().iter().any(|sample| sample.abs() > 0.1));
+        assert!(
+            right
+                .get_samples()
+                .iter()
+                .all(|sample| sample.abs() < 0.001)
+        );
+    }
+
+    #[test]
+    fn test_invalid_commands() {
+        let mut apu = GameBoyApuBuilder::default().finalize().unwrap();
+        assert_eq!(
+            apu.write_register(0xFF27, 0x00),
+            Err(InvalidApu::new(InvalidApuKind::UnknownRegister))
+        );
+        assert_note_errors(
+            &mut apu,
+            &[
+                (
+                    GameBoyChannel::Square1,
+                    0.0,
+                    "c1",
+                    InvalidApuKind::NoteOutOfRange,
+                ),
+                (
+                    GameBoyChannel::Noise,
+                    0.0,
+                    "c4",
+                    InvalidApuKind::UnknownNote,
+                ),
+            ],
+        );
+    }
+}
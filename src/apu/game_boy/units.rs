@@ -0,0 +1,149 @@
+#[derive(Clone, Debug, PartialEq)]
+pub struct LengthTimer {
+    maximum: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl LengthTimer {
+    pub fn new(maximum: u16) -> Self {
+        return Self {
+            maximum,
+            counter: 0,
+            enabled: false,
+        };
+    }
+
+    pub fn load(&mut self, value: u8) {
+        self.counter = self.maximum - (value as u16).min(self.maximum - 1);
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn trigger(&mut self) {
+        if self.counter == 0 {
+            self.counter = self.maximum;
+        }
+    }
+
+    // Returns whether the timer just ran out, which turns its channel off.
+    pub fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 {
+            return false;
+        }
+        self.counter -= 1;
+        return self.counter == 0;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VolumeEnvelope {
+    initial_volume: u8,
+    increase: bool,
+    period: u8,
+    volume: u8,
+    timer: u8,
+}
+
+impl VolumeEnvelope {
+    pub fn write(&mut self, value: u8) {
+        self.initial_volume = value >> 4;
+        self.increase = value & 0x08 != 0;
+        self.period = value & 0x07;
+    }
+
+    // The upper five bits of NRx2 also power the channel's DAC.
+    pub fn dac_enabled(&self) -> bool {
+        return self.initial_volume != 0 || self.increase;
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.initial_volume;
+        self.timer = self.period;
+    }
+
+    pub fn clock(&mut self) {
+        if self.period == 0 {
+            return;
+        }
+        if self.timer > 0 {
+            self.timer -= 1;
+        }
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.increase && self.volume < 15 {
+                self.volume += 1;
+            } else if !self.increase && self.volume > 0 {
+                self.volume -= 1;
+            }
+        }
+    }
+
+    pub fn volume(&self) -> u8 {
+        return self.volume;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sweep {
+    period: u8,
+    negate: bool,
+    shift: u8,
+    timer: u8,
+    enabled: bool,
+    shadow_frequency: u16,
+}
+
+impl Sweep {
+    pub fn write(&mut self, value: u8) {
+        self.period = (value >> 4) & 0x07;
+        self.negate = value & 0x08 != 0;
+        self.shift = value & 0x07;
+    }
+
+    fn reload_timer(&mut self) {
+        self.timer = if self.period == 0 { 8 } else { self.period };
+    }
+
+    fn next_frequency(&self) -> u32 {
+        let delta = (self.shadow_frequency >> self.shift) as u32;
+        if self.negate {
+            return (self.shadow_frequency as u32).saturating_sub(delta);
+        }
+        return self.shadow_frequency as u32 + delta;
+    }
+
+    // Both triggering and clocking return whether the frequency overflowed,
+    // which turns the first square channel off.
+    pub fn trigger(&mut self, frequency: u16) -> bool {
+        self.shadow_frequency = frequency;
+        self.reload_timer();
+        self.enabled = self.period != 0 || self.shift != 0;
+        return self.shift != 0 && self.next_frequency() > 0x7FF;
+    }
+
+    pub fn clock(&mut self, frequency: &mut u16) -> bool {
+        if self.timer > 0 {
+            self.timer -= 1;
+        }
+        if self.timer != 0 {
+            return false;
+        }
+        self.reload_timer();
+        if !self.enabled || self.period == 0 {
+            return false;
+        }
+        let next_frequency = self.next_frequency();
+        if next_frequency > 0x7FF {
+            return true;
+        }
+        if self.shift != 0 {
+            self.shadow_frequency = next_frequency as u16;
+            *frequency = next_frequency as u16;
+            return self.next_frequency() > 0x7FF;
+        }
+        return false;
+    }
+}
@@ -0,0 +1,102 @@
+use super::units::LengthTimer;
+
+pub const WAVE_TABLE_BYTES: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaveChannel {
+    dac_enabled: bool,
+    enabled: bool,
+    volume_code: u8,
+    frequency: u16,
+    timer: u16,
+    position: u8,
+    table: [u8; WAVE_TABLE_BYTES],
+    length: LengthTimer,
+}
+
+impl Default for WaveChannel {
+    fn default() -> Self {
+        return Self {
+            dac_enabled: false,
+            enabled: false,
+            volume_code: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            table: [0; WAVE_TABLE_BYTES],
+            length: LengthTimer::new(256),
+        };
+    }
+}
+
+impl WaveChannel {
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.dac_enabled = value & 0x80 != 0;
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            }
+            1 => self.length.load(value),
+            2 => self.volume_code = (value >> 5) & 0x03,
+            3 => self.frequency = (self.frequency & 0x0700) | value as u16,
+            _ => {
+                self.frequency = (self.frequency & 0x00FF) | (((value & 0x07) as u16) << 8);
+                self.length.set_enabled(value & 0x40 != 0);
+                if value & 0x80 != 0 {
+                    self.enabled = self.dac_enabled;
+                    self.length.trigger();
+                    self.timer = self.period();
+                    self.position = 0;
+                }
+            }
+        }
+    }
+
+    pub fn write_table(&mut self, index: usize, value: u8) {
+        self.table[index % WAVE_TABLE_BYTES] = value;
+    }
+
+    pub fn table(&self) -> &[u8; WAVE_TABLE_BYTES] {
+        return &self.table;
+    }
+
+    fn period(&self) -> u16 {
+        return 2048 - self.frequency;
+    }
+
+    pub fn clock_timer(&mut self) {
+        if self.timer <= 1 {
+            self.timer = self.period();
+            self.position = (self.position + 1) % (2 * WAVE_TABLE_BYTES as u8);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn output(&self) -> Option<u8> {
+        if !self.dac_enabled {
+            return None;
+        }
+        if !self.enabled {
+            return Some(0);
+        }
+        let byte = self.table[(self.position / 2) as usize];
+        let sample = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        };
+        return Some(match self.volume_code {
+            0 => 0,
+            code => sample >> (code - 1),
+        });
+    }
+}
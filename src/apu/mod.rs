@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+pub mod ay8910;
+pub mod game_boy;
+pub mod nes;
+pub mod sn76489;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidApuKind {
+    NonPositiveSamplingFrequency,
+    NonPositiveClock,
+    NegativeTime,
+    UnknownRegister,
+    UnknownNote,
+    NoteOutOfRange,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidApu {
+    kind: InvalidApuKind,
+}
+
+impl InvalidApu {
+    pub fn new(kind: InvalidApuKind) -> Self {
+        return Self { kind };
+    }
+}
+
+pub fn milliseconds_to_cycles(clock: f64, time_ms: f64) -> u64 {
+    return (time_ms / 1000.0 * clock).round() as u64;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterWrite {
+    pub cycle: u64,
+    pub address: u16,
+    pub value: u8,
+}
+
+// Register writes waiting for the chip to reach their cycle. Writes with
+// the same cycle keep the order they were scheduled in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegisterSchedule {
+    writes: VecDeque<RegisterWrite>,
+}
+
+impl RegisterSchedule {
+    pub fn schedule(&mut self, cycle: u64, address: u16, value: u8) {
+        let index = self.writes.partition_point(|write| write.cycle <= cycle);
+        self.writes.insert(
+            index,
+            RegisterWrite {
+                cycle,
+                address,
+                value,
+            },
+        );
+    }
+
+    pub fn pop_due(&mut self, cycle: u64) -> Option<RegisterWrite> {
+        if self.writes.front()?.cycle > cycle {
+            return None;
+        }
+        return self.writes.pop_front();
+    }
+}
+
+// Chips with a note API, which turns notes into register writes. Empty
+// notes are rests, and playing a list of notes returns the time the last
+// one ends, for the next list to start from.
+pub trait ScheduleNotes {
+    type Channel: Copy;
+
+    fn schedule_note(
+        &mut self,
+        channel: Self::Channel,
+        start_ms: f64,
+        duration_ms: f64,
+        note: &str,
+    ) -> Result<(), InvalidApu>;
+
+    fn schedule_notes(
+        &mut self,
+        channel: Self::Channel,
+        start_ms: f64,
+        notes: &[(f64, &str)],
+    ) -> Result<f64, InvalidApu> {
+        let mut time_ms = start_ms;
+        for (duration_ms, note) in notes {
+            if !note.is_empty() {
+                self.schedule_note(channel, time_ms, *duration_ms, note)?;
+            }
+            time_ms += duration_ms;
+        }
+        Ok(time_ms)
+    }
+}
+
+// First order high pass filter, the same kind of coupling capacitor every
+// console has between the sound chip and the audio output.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DcBlocker {
+    coefficient: f64,
+    last_input: f64,
+    last_output: f64,
+}
+
+#[allow(dead_code)]
+impl DcBlocker {
+    pub fn new(cutoff_frequency: f64, sampling_frequency: f64) -> Self {
+        return Self {
+            coefficient: f64::exp(-2.0 * PI * cutoff_frequency / sampling_frequency),
+            last_input: 0.0,
+            last_output: 0.0,
+        };
+    }
+
+    pub fn filter(&mut self, input: f64) -> f64 {
+        let output = input - self.last_input + self.coefficient * self.last_output;
+        self.last_input = input;
+        self.last_output = output;
+        return output;
+    }
+}
@@ -0,0 +1,122 @@
+use crate::waves::DMC_RATES;
+
+pub const DMC_MEMORY_START: u16 = 0xC000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DmcChannel {
+    memory: Vec<u8>,
+    looping: bool,
+    timer_period: u16,
+    timer: u16,
+    output_level: u8,
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+}
+
+impl DmcChannel {
+    // Samples can only be read from $C000 onwards, so the memory given here
+    // is mapped starting at that address.
+    pub fn new(memory: Vec<u8>) -> Self {
+        return Self {
+            memory,
+            looping: false,
+            timer_period: DMC_RATES[0],
+            timer: 0,
+            output_level: 0,
+            sample_address: DMC_MEMORY_START,
+            sample_length: 1,
+            current_address: DMC_MEMORY_START,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+        };
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.looping = value & 0x40 != 0;
+                self.timer_period = DMC_RATES[(value & 0x0F) as usize];
+            }
+            1 => self.output_level = value & 0x7F,
+            2 => self.sample_address = DMC_MEMORY_START + value as u16 * 64,
+            _ => self.sample_length = value as u16 * 16 + 1,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    fn fill_sample_buffer(&mut self) {
+        if self.sample_buffer.is_some() || self.bytes_remaining == 0 {
+            return;
+        }
+        let offset = self.current_address.wrapping_sub(DMC_MEMORY_START) as usize;
+        self.sample_buffer = Some(*self.memory.get(offset).unwrap_or(&0));
+        self.current_address = self.current_address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 && self.looping {
+            self.restart();
+        }
+    }
+
+    pub fn clock_timer(&mut self) {
+        self.fill_sample_buffer();
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+            self.clock_output();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_output(&mut self) {
+        if !self.silence {
+            if self.shift_register & 1 == 1 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+        self.shift_register >>= 1;
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(byte) => {
+                    self.silence = false;
+                    self.shift_register = byte;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn memory_length(&self) -> usize {
+        return self.memory.len();
+    }
+
+    pub fn output(&self) -> u8 {
+        return self.output_level;
+    }
+}
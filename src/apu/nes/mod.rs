@@ -0,0 +1,393 @@
+use builder_derive_macro::Setters;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::clocks::NES_NTSC_CPU_CLOCK;
+use crate::time::milliseconds_to_samples;
+use crate::utils::build::Build;
+use crate::waves::traits::has_tone::parse_note;
+
+use super::{
+    DcBlocker, InvalidApu, InvalidApuKind, RegisterSchedule, ScheduleNotes, milliseconds_to_cycles,
+};
+
+mod dmc;
+mod noise;
+mod pulse;
+mod tests;
+mod triangle;
+mod units;
+use dmc::DmcChannel;
+use noise::NoiseChannel;
+use pulse::PulseChannel;
+use triangle::TriangleChannel;
+
+// CPU cycles at which the frame counter clocks the envelopes and the
+// linear counter (quarter frames); the second and last entries also clock
+// the length counters and sweeps (half frames).
+const FOUR_STEP_SEQUENCE: [u32; 4] = [7457, 14913, 22371, 29829];
+const FIVE_STEP_SEQUENCE: [u32; 4] = [7457, 14913, 22371, 37281];
+const OUTPUT_HIGH_PASS_FREQUENCY: f64 = 90.0;
+const NOTE_LENGTH_INDEX: u8 = 1;
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NesChannel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+}
+
+impl NesChannel {
+    fn index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+// The duty is an index into the four hardware duty cycles.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct NesVoice {
+    duty: u8,
+    volume: u8,
+    short_noise: bool,
+    looping_sample: bool,
+}
+
+impl Default for NesVoice {
+    fn default() -> Self {
+        return Self {
+            duty: 2,
+            volume: 15,
+            short_noise: false,
+            looping_sample: false,
+        };
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct NesApuBuilder {
+    cpu_clock: f64,
+    sampling_frequency: f64,
+    dmc_memory: Vec<u8>,
+}
+
+impl Default for NesApuBuilder {
+    fn default() -> Self {
+        return Self {
+            cpu_clock: NES_NTSC_CPU_CLOCK,
+            sampling_frequency: 44100_f64,
+            dmc_memory: vec![],
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl Build for NesApuBuilder {
+    type Output = NesApu;
+    type Error = InvalidApu;
+
+    fn validate(&self) -> Result<(), Vec<InvalidApu>> {
+        let mut possible_errors: Vec<InvalidApu> = vec![];
+        if self.sampling_frequency <= 0.0 {
+            possible_errors.push(InvalidApu::new(
+                InvalidApuKind::NonPositiveSamplingFrequency,
+            ));
+        }
+        if self.cpu_clock <= 0.0 {
+            possible_errors.push(InvalidApu::new(InvalidApuKind::NonPositiveClock));
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    fn finalize(self) -> Result<NesApu, InvalidApu> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        let mut apu = NesApu {
+            pulse_1: PulseChannel::new(true),
+            pulse_2: PulseChannel::new(false),
+            triangle: TriangleChannel::default(),
+            noise: NoiseChannel::default(),
+            dmc: DmcChannel::new(self.dmc_memory),
+            five_step_mode: false,
+            frame_cycle: 0,
+            cpu_cycle: 0,
+            cpu_clock: self.cpu_clock,
+            sampling_frequency: self.sampling_frequency,
+            cycle_phase: 0.0,
+            scheduled_writes: RegisterSchedule::default(),
+            voices: vec![NesVoice::default(); 5],
+            dc_blocker: DcBlocker::new(OUTPUT_HIGH_PASS_FREQUENCY, self.sampling_frequency),
+        };
+        apu.write_register(0x4015, 0x0F)?;
+        return Ok(apu);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NesApu {
+    pulse_1: PulseChannel,
+    pulse_2: PulseChannel,
+    triangle: TriangleChannel,
+    noise: NoiseChannel,
+    dmc: DmcChannel,
+    five_step_mode: bool,
+    frame_cycle: u32,
+    cpu_cycle: u64,
+    cpu_clock: f64,
+    sampling_frequency: f64,
+    cycle_phase: f64,
+    scheduled_writes: RegisterSchedule,
+    voices: Vec<NesVoice>,
+    dc_blocker: DcBlocker,
+}
+
+#[allow(dead_code)]
+impl NesApu {
+    pub fn write_register(&mut self, address: u16, value: u8) -> Result<(), InvalidApu> {
+        match address {
+            0x4000..=0x4003 => self.pulse_1.write(address - 0x4000, value),
+            0x4004..=0x4007 => self.pulse_2.write(address - 0x4004, value),
+            0x4008..=0x400B => self.triangle.write(address - 0x4008, value),
+            0x400C..=0x400F => self.noise.write(address - 0x400C, value),
+            0x4010..=0x4013 => self.dmc.write(address - 0x4010, value),
+            0x4015 => {
+                self.pulse_1.length.set_enabled(value & 0x01 != 0);
+                self.pulse_2.length.set_enabled(value & 0x02 != 0);
+                self.triangle.length.set_enabled(value & 0x04 != 0);
+                self.noise.length.set_enabled(value & 0x08 != 0);
+                self.dmc.set_enabled(value & 0x10 != 0);
+            }
+            0x4017 => {
+                self.five_step_mode = value & 0x80 != 0;
+                self.frame_cycle = 0;
+                if self.five_step_mode {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+            }
+            _ => return Err(InvalidApu::new(InvalidApuKind::UnknownRegister)),
+        }
+        return Ok(());
+    }
+
+    pub fn schedule_register_write(
+        &mut self,
+        time_ms: f64,
+        address: u16,
+        value: u8,
+    ) -> Result<(), InvalidApu> {
+        if time_ms < 0.0 {
+            return Err(InvalidApu::new(InvalidApuKind::NegativeTime));
+        }
+        if !matches!(address, 0x4000..=0x4013 | 0x4015 | 0x4017) {
+            return Err(InvalidApu::new(InvalidApuKind::UnknownRegister));
+        }
+        let cycle = milliseconds_to_cycles(self.cpu_clock, time_ms);
+        self.scheduled_writes.schedule(cycle, address, value);
+        return Ok(());
+    }
+
+    pub fn get_voice(&self, channel: NesChannel) -> &NesVoice {
+        return &self.voices[channel.index()];
+    }
+
+    pub fn set_voice(&mut self, channel: NesChannel, voice: NesVoice) {
+        self.voices[channel.index()] = voice;
+    }
+
+    pub fn render_ms(&mut self, duration_ms: f64) -> Audio {
+        let number_of_samples = milliseconds_to_samples(self.sampling_frequency, duration_ms);
+        let cycles_per_sample = self.cpu_clock / self.sampling_frequency;
+        let mut samples = Vec::with_capacity(number_of_samples);
+        for _ in 0..number_of_samples {
+            self.cycle_phase += cycles_per_sample;
+            let cycles = self.cycle_phase.floor() as usize;
+            self.cycle_phase -= cycles as f64;
+            let mut accumulated = 0.0;
+            for _ in 0..cycles {
+                self.step_cpu_cycle();
+                accumulated += self.mix();
+            }
+            let sample = accumulated / cycles.max(1) as f64;
+            samples.push(self.dc_blocker.filter(sample));
+        }
+        let builder = AudioBuilder::new(samples, self.sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+
+    fn timer_period(&self, note: &str, divider: f64, minimum: u16) -> Result<u16, InvalidApu> {
+        let frequency = parse_note(note).ok_or(InvalidApu::new(InvalidApuKind::UnknownNote))?;
+        let period = (self.cpu_clock / (divider * frequency) - 1.0).round();
+        if period < minimum as f64 || period > 0x7FF as f64 {
+            return Err(InvalidApu::new(InvalidApuKind::NoteOutOfRange));
+        }
+        return Ok(period as u16);
+    }
+
+    fn step_cpu_cycle(&mut self) {
+        while let Some(write) = self.scheduled_writes.pop_due(self.cpu_cycle) {
+            let _ = self.write_register(write.address, write.value);
+        }
+        self.clock_frame_counter();
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if !self.cpu_cycle.is_multiple_of(2) {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+        self.cpu_cycle += 1;
+    }
+
+    fn clock_frame_counter(&mut self) {
+        self.frame_cycle += 1;
+        let sequence = if self.five_step_mode {
+            FIVE_STEP_SEQUENCE
+        } else {
+            FOUR_STEP_SEQUENCE
+        };
+        if let Some(step) = sequence.iter().position(|cycle| *cycle == self.frame_cycle) {
+            self.clock_quarter_frame();
+            if !step.is_multiple_of(2) {
+                self.clock_half_frame();
+            }
+        }
+        if self.frame_cycle > sequence[3] {
+            self.frame_cycle = 0;
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.envelope.clock();
+        self.pulse_2.envelope.clock();
+        self.noise.envelope.clock();
+        self.triangle.clock_linear_counter();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.length.clock();
+        self.pulse_2.length.clock();
+        self.triangle.length.clock();
+        self.noise.length.clock();
+        self.pulse_1.clock_sweep();
+        self.pulse_2.clock_sweep();
+    }
+
+    // The non-linear mixer of the 2A03, using the usual approximation from
+    // the NESdev wiki. Its output goes from 0.0 to about 1.0.
+    fn mix(&self) -> f64 {
+        let pulses = (self.pulse_1.output() + self.pulse_2.output()) as f64;
+        let pulse_out = if pulses == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulses + 100.0)
+        };
+        let triangle = self.triangle.output() as f64 / 8227.0;
+        let noise = self.noise.output() as f64 / 12241.0;
+        let dmc = self.dmc.output() as f64 / 22638.0;
+        let tnd_sum = triangle + noise + dmc;
+        let tnd_out = if tnd_sum == 0.0 {
+            0.0
+        } else {
+            159.79 / (1.0 / tnd_sum + 100.0)
+        };
+        return pulse_out + tnd_out;
+    }
+}
+
+impl ScheduleNotes for NesApu {
+    type Channel = NesChannel;
+
+    // Notes are pitches ("a4", "c#3") for the pulse and triangle channels,
+    // period indices ("0" to "15") for the noise channel and rate indices
+    // for the DMC, which plays the whole DMC memory.
+    fn schedule_note(
+        &mut self,
+        channel: NesChannel,
+        start_ms: f64,
+        duration_ms: f64,
+        note: &str,
+    ) -> Result<(), InvalidApu> {
+        let end_ms = start_ms + duration_ms.max(0.0);
+        let voice = self.voices[channel.index()].clone();
+        let volume = voice.volume.min(15);
+        let writes: Vec<(f64, u16, u8)> = match channel {
+            NesChannel::Pulse1 | NesChannel::Pulse2 => {
+                let base = if channel == NesChannel::Pulse1 {
+                    0x4000
+                } else {
+                    0x4004
+                };
+                let period = self.timer_period(note, 16.0, 8)?;
+                let control = (voice.duty.min(3) << 6) | 0x30;
+                vec![
+                    (start_ms, base, control | volume),
+                    (start_ms, base + 1, 0x08),
+                    (start_ms, base + 2, (period & 0xFF) as u8),
+                    (
+                        start_ms,
+                        base + 3,
+                        (NOTE_LENGTH_INDEX << 3) | (period >> 8) as u8,
+                    ),
+                    (end_ms, base, control),
+                ]
+            }
+            NesChannel::Triangle => {
+                let period = self.timer_period(note, 32.0, 2)?;
+                vec![
+                    (start_ms, 0x4008, 0xFF),
+                    (start_ms, 0x400A, (period & 0xFF) as u8),
+                    (
+                        start_ms,
+                        0x400B,
+                        (NOTE_LENGTH_INDEX << 3) | (period >> 8) as u8,
+                    ),
+                    (end_ms, 0x4008, 0x80),
+                ]
+            }
+            NesChannel::Noise => {
+                let index = parse_index(note)?;
+                let mode = if voice.short_noise { 0x80 } else { 0x00 };
+                vec![
+                    (start_ms, 0x400C, 0x30 | volume),
+                    (start_ms, 0x400E, mode | index),
+                    (start_ms, 0x400F, NOTE_LENGTH_INDEX << 3),
+                    (end_ms, 0x400C, 0x30),
+                ]
+            }
+            NesChannel::Dmc => {
+                let index = parse_index(note)?;
+                let looping = if voice.looping_sample { 0x40 } else { 0x00 };
+                let length = (self.dmc.memory_length().saturating_sub(1) / 16).min(0xFF) as u8;
+                vec![
+                    (start_ms, 0x4010, looping | index),
+                    (start_ms, 0x4012, 0x00),
+                    (start_ms, 0x4013, length),
+                    (start_ms, 0x4015, 0x1F),
+                    (end_ms, 0x4015, 0x0F),
+                ]
+            }
+        };
+        for (time_ms, address, value) in writes {
+            self.schedule_register_write(time_ms, address, value)?;
+        }
+        return Ok(());
+    }
+}
+
+fn parse_index(note: &str) -> Result<u8, InvalidApu> {
+    let index = note
+        .trim()
+        .parse::<u8>()
+        .map_err(|_error| InvalidApu::new(InvalidApuKind::UnknownNote))?;
+    if index > 0x0F {
+        return Err(InvalidApu::new(InvalidApuKind::NoteOutOfRange));
+    }
+    return Ok(index);
+}
@@ -0,0 +1,66 @@
+use crate::waves::{Lfsr, LfsrMode, NES_NOISE_PERIODS};
+
+use super::units::{LengthCounter, VolumeEnvelope};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseChannel {
+    lfsr: Lfsr,
+    timer_period: u16,
+    timer: u16,
+    pub envelope: VolumeEnvelope,
+    pub length: LengthCounter,
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        return Self {
+            lfsr: Lfsr::from_mode(&LfsrMode::Long),
+            timer_period: NES_NOISE_PERIODS[0],
+            timer: 0,
+            envelope: VolumeEnvelope::default(),
+            length: LengthCounter::default(),
+        };
+    }
+}
+
+impl NoiseChannel {
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.length.set_halted(value & 0x20 != 0);
+                self.envelope.write(value);
+            }
+            1 => (),
+            2 => {
+                let mode = if value & 0x80 != 0 {
+                    LfsrMode::Short
+                } else {
+                    LfsrMode::Long
+                };
+                self.lfsr.set_mode(&mode);
+                self.timer_period = NES_NOISE_PERIODS[(value & 0x0F) as usize];
+            }
+            _ => {
+                self.length.load(value >> 3);
+                self.envelope.restart();
+            }
+        }
+    }
+
+    // The period table is in CPU cycles, so this is clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+            self.lfsr.step();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.lfsr.output() || !self.length.is_active() {
+            return 0;
+        }
+        return self.envelope.volume();
+    }
+}
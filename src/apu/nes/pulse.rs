@@ -0,0 +1,73 @@
+use super::units::{LengthCounter, Sweep, VolumeEnvelope};
+
+const DUTY_SEQUENCES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PulseChannel {
+    duty: u8,
+    step: u8,
+    timer_period: u16,
+    timer: u16,
+    pub envelope: VolumeEnvelope,
+    pub sweep: Sweep,
+    pub length: LengthCounter,
+}
+
+impl PulseChannel {
+    pub fn new(ones_complement_sweep: bool) -> Self {
+        return Self {
+            duty: 0,
+            step: 0,
+            timer_period: 0,
+            timer: 0,
+            envelope: VolumeEnvelope::default(),
+            sweep: Sweep::new(ones_complement_sweep),
+            length: LengthCounter::default(),
+        };
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.duty = value >> 6;
+                self.length.set_halted(value & 0x20 != 0);
+                self.envelope.write(value);
+            }
+            1 => self.sweep.write(value),
+            2 => self.timer_period = (self.timer_period & 0x0700) | value as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((value & 0x07) as u16) << 8);
+                self.length.load(value >> 3);
+                self.step = 0;
+                self.envelope.restart();
+            }
+        }
+    }
+
+    // Clocked once every APU cycle, that is, every other CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_sweep(&mut self) {
+        self.sweep.clock(&mut self.timer_period);
+    }
+
+    pub fn output(&self) -> u8 {
+        let high = DUTY_SEQUENCES[self.duty as usize][self.step as usize] == 1;
+        if !high || !self.length.is_active() || self.sweep.is_muting(self.timer_period) {
+            return 0;
+        }
+        return self.envelope.volume();
+    }
+}
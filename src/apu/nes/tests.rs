@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::utils::testing::{assert_note_errors, assert_note_pitch};
+
+    #[test]
+    fn test_note_pitch() {
+        assert_note_pitch(
+            &[NesChannel::Pulse1, NesChannel::Pulse2, NesChannel::Triangle],
+            || NesApuBuilder::default().finalize().unwrap(),
+            |apu, duration_ms| apu.render_ms(duration_ms).get_samples(),
+            2,
+        );
+    }
+
+    #[test]
+    fn test_note_release() {
+        let mut apu = NesApuBuilder::default().finalize().unwrap();
+        apu.schedule_note(NesChannel::Pulse1, 0.0, 100.0, "c4")
+            .unwrap();
+        let samples = apu.render_ms(400.0).get_samples();
+        assert!(samples[..4410].iter().any(|sample| sample.abs() > 0.1));
+        assert!(samples[13230..].iter().all(|sample| sample.abs() < 0.001));
+    }
+
+    #[test]
+    fn test_invalid_commands() {
+        let mut apu = NesApuBuilder::default().finalize().unwrap();
+        assert_eq!(
+            apu.write_register(0x4014, 0x00),
+            Err(InvalidApu::new(InvalidApuKind::UnknownRegister))
+        );
+        assert_note_errors(
+            &mut apu,
+            &[
+                (NesChannel::Pulse1, 0.0, "x4", InvalidApuKind::UnknownNote),
+                (
+                    NesChannel::Pulse1,
+                    0.0,
+                    "c0",
+                    InvalidApuKind::NoteOutOfRange,
+                ),
+                (NesChannel::Noise, 0.0, "16", InvalidApuKind::NoteOutOfRange),
+            ],
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use super::units::LengthCounter;
+
+const TRIANGLE_SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TriangleChannel {
+    timer_period: u16,
+    timer: u16,
+    step: u8,
+    control: bool,
+    linear_counter: u8,
+    linear_reload_value: u8,
+    linear_reload: bool,
+    pub length: LengthCounter,
+}
+
+impl TriangleChannel {
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.control = value & 0x80 != 0;
+                self.length.set_halted(self.control);
+                self.linear_reload_value = value & 0x7F;
+            }
+            1 => (),
+            2 => self.timer_period = (self.timer_period & 0x0700) | value as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((value & 0x07) as u16) << 8);
+                self.length.load(value >> 3);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    // Clocked on every CPU cycle, which puts the triangle an octave below
+    // a pulse channel with the same period.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            if self.length.is_active() && self.linear_counter > 0 {
+                self.step = (self.step + 1) % 32;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_linear_counter(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    // A halted triangle holds its last step instead of going back to zero.
+    // Ultrasonic periods are left out, like most emulators do, since they
+    // only produce a pop and aliasing.
+    pub fn output(&self) -> u8 {
+        if self.timer_period < 2 {
+            return 7;
+        }
+        return TRIANGLE_SEQUENCE[self.step as usize];
+    }
+}
@@ -0,0 +1,143 @@
+pub const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LengthCounter {
+    counter: u8,
+    halted: bool,
+    enabled: bool,
+}
+
+impl LengthCounter {
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    pub fn set_halted(&mut self, halted: bool) {
+        self.halted = halted;
+    }
+
+    pub fn clock(&mut self) {
+        if !self.halted && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        return self.counter > 0;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VolumeEnvelope {
+    start: bool,
+    looping: bool,
+    constant: bool,
+    period: u8,
+    divider: u8,
+    decay_level: u8,
+}
+
+impl VolumeEnvelope {
+    pub fn write(&mut self, value: u8) {
+        self.looping = value & 0x20 != 0;
+        self.constant = value & 0x10 != 0;
+        self.period = value & 0x0F;
+    }
+
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay_level = 15;
+            self.divider = self.period;
+        } else if self.divider == 0 {
+            self.divider = self.period;
+            if self.decay_level > 0 {
+                self.decay_level -= 1;
+            } else if self.looping {
+                self.decay_level = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn volume(&self) -> u8 {
+        if self.constant {
+            return self.period;
+        }
+        return self.decay_level;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sweep {
+    enabled: bool,
+    period: u8,
+    negate: bool,
+    shift: u8,
+    divider: u8,
+    reload: bool,
+    ones_complement: bool,
+}
+
+impl Sweep {
+    // The first pulse channel negates with ones' complement, which makes
+    // its downward sweeps one step lower than the second channel's.
+    pub fn new(ones_complement: bool) -> Self {
+        return Self {
+            ones_complement,
+            ..Self::default()
+        };
+    }
+
+    pub fn write(&mut self, value: u8) {
+        self.enabled = value & 0x80 != 0;
+        self.period = (value >> 4) & 0x07;
+        self.negate = value & 0x08 != 0;
+        self.shift = value & 0x07;
+        self.reload = true;
+    }
+
+    pub fn target_period(&self, timer_period: u16) -> i32 {
+        let change = (timer_period >> self.shift) as i32;
+        if !self.negate {
+            return timer_period as i32 + change;
+        }
+        if self.ones_complement {
+            return timer_period as i32 - change - 1;
+        }
+        return timer_period as i32 - change;
+    }
+
+    pub fn is_muting(&self, timer_period: u16) -> bool {
+        return timer_period < 8 || self.target_period(timer_period) > 0x7FF;
+    }
+
+    pub fn clock(&mut self, timer_period: &mut u16) {
+        if self.divider == 0 && self.enabled && self.shift > 0 && !self.is_muting(*timer_period) {
+            *timer_period = self.target_period(*timer_period).max(0) as u16;
+        }
+        if self.divider == 0 || self.reload {
+            self.divider = self.period;
+            self.reload = false;
+        } else {
+            self.divider -= 1;
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use builder_derive_macro::Setters;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::clocks::SEGA_MASTER_SYSTEM_PSG_CLOCK;
+use crate::time::milliseconds_to_samples;
+use crate::utils::build::Build;
+use crate::waves::traits::has_tone::parse_note;
+
+use super::{
+    DcBlocker, InvalidApu, InvalidApuKind, RegisterSchedule, ScheduleNotes, milliseconds_to_cycles,
+};
+
+mod noise;
+mod tests;
+mod tone;
+use noise::NoiseChannel;
+use tone::ToneChannel;
+
+// The counters of the chip are clocked once every 16 input clocks.
+const CLOCKS_PER_STEP: u64 = 16;
+const OUTPUT_HIGH_PASS_FREQUENCY: f64 = 20.0;
+// The chip only has one port, so every scheduled write uses this address.
+const DATA_PORT: u16 = 0;
+
+// Every attenuation step is 2 dB, and the last one silences the channel.
+pub fn sn76489_volume(attenuation: u8) -> f64 {
+    if attenuation >= 15 {
+        return 0.0;
+    }
+    return f64::powf(10.0, -2.0 * attenuation as f64 / 20.0);
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sn76489Channel {
+    Tone1,
+    Tone2,
+    Tone3,
+    Noise,
+}
+
+impl Sn76489Channel {
+    fn index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+// The volume is the opposite of the attenuation register.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct Sn76489Voice {
+    volume: u8,
+    white_noise: bool,
+}
+
+impl Default for Sn76489Voice {
+    fn default() -> Self {
+        return Self {
+            volume: 15,
+            white_noise: true,
+        };
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct Sn76489Builder {
+    clock: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for Sn76489Builder {
+    fn default() -> Self {
+        return Self {
+            clock: SEGA_MASTER_SYSTEM_PSG_CLOCK,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl Build for Sn76489Builder {
+    type Output = Sn76489;
+    type Error = InvalidApu;
+
+    fn validate(&self) -> Result<(), Vec<InvalidApu>> {
+        let mut possible_errors: Vec<InvalidApu> = vec![];
+        if self.sampling_frequency <= 0.0 {
+            possible_errors.push(InvalidApu::new(
+                InvalidApuKind::NonPositiveSamplingFrequency,
+            ));
+        }
+        if self.clock <= 0.0 {
+            possible_errors.push(InvalidApu::new(InvalidApuKind::NonPositiveClock));
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    fn finalize(self) -> Result<Sn76489, InvalidApu> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        return Ok(Sn76489 {
+            tones: [
+                ToneChannel::default(),
+                ToneChannel::default(),
+                ToneChannel::default(),
+            ],
+            noise: NoiseChannel::default(),
+            attenuations: [15; 4],
+            latched_channel: 0,
+            latched_volume: false,
+            step: 0,
+            clock: self.clock,
+            sampling_frequency: self.sampling_frequency,
+            step_phase: 0.0,
+            scheduled_writes: RegisterSchedule::default(),
+            voices: vec![Sn76489Voice::default(); 4],
+            dc_blocker: DcBlocker::new(OUTPUT_HIGH_PASS_FREQUENCY, self.sampling_frequency),
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sn76489 {
+    tones: [ToneChannel; 3],
+    noise: NoiseChannel,
+    attenuations: [u8; 4],
+    latched_channel: usize,
+    latched_volume: bool,
+    step: u64,
+    clock: f64,
+    sampling_frequency: f64,
+    step_phase: f64,
+    scheduled_writes: RegisterSchedule,
+    voices: Vec<Sn76489Voice>,
+    dc_blocker: DcBlocker,
+}
+
+#[allow(dead_code)]
+impl Sn76489 {
+    // A byte with the top bit set latches a channel and a register and
+    // writes its low four bits; any other byte writes the upper six bits of
+    // a tone period, or the whole of the other registers.
+    pub fn write_register(&mut self, value: u8) {
+        if value & 0x80 != 0 {
+            self.latched_channel = ((value >> 5) & 0x03) as usize;
+            self.latched_volume = value & 0x10 != 0;
+        }
+        let is_latch = value & 0x80 != 0;
+        let channel = self.latched_channel;
+        if self.latched_volume {
+            self.attenuations[channel] = value & 0x0F;
+        } else if channel == 3 {
+            self.noise.write(value);
+        } else if is_latch {
+            self.tones[channel].write_low(value);
+        } else {
+            self.tones[channel].write_high(value);
+        }
+    }
+
+    pub fn schedule_register_write(&mut self, time_ms: f64, value: u8) -> Result<(), InvalidApu> {
+        if time_ms < 0.0 {
+            return Err(InvalidApu::new(InvalidApuKind::NegativeTime));
+        }
+        let cycle = milliseconds_to_cycles(self.clock, time_ms);
+        self.scheduled_writes.schedule(cycle, DATA_PORT, value);
+        return Ok(());
+    }
+
+    pub fn get_voice(&self, channel: Sn76489Channel) -> &Sn76489Voice {
+        return &self.voices[channel.index()];
+    }
+
+    pub fn set_voice(&mut self, channel: Sn76489Channel, voice: Sn76489Voice) {
+        self.voices[channel.index()] = voice;
+    }
+
+    pub fn render_ms(&mut self, duration_ms: f64) -> Audio {
+        let number_of_samples = milliseconds_to_samples(self.sampling_frequency, duration_ms);
+        let steps_per_sample = self.clock / (CLOCKS_PER_STEP as f64 * self.sampling_frequency);
+        let mut samples = Vec::with_capacity(number_of_samples);
+        for _ in 0..number_of_samples {
+            self.step_phase += steps_per_sample;
+            let steps = self.step_phase.floor() as usize;
+            self.step_phase -= steps as f64;
+            let mut accumulated = 0.0;
+            for _ in 0..steps {
+                self.step();
+                accumulated += self.mix();
+            }
+            let sample = accumulated / steps.max(1) as f64;
+            samples.push(self.dc_blocker.filter(sample));
+        }
+        let builder = AudioBuilder::new(samples, self.sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+
+    fn step(&mut self) {
+        let cycle = self.step * CLOCKS_PER_STEP;
+        while let Some(write) = self.scheduled_writes.pop_due(cycle) {
+            self.write_register(write.value);
+        }
+        self.tones[0].clock();
+        self.tones[1].clock();
+        let tone_3_flipped = self.tones[2].clock();
+        self.noise.clock(tone_3_flipped);
+        self.step += 1;
+    }
+
+    // The outputs are unipolar, so the DC they leave is removed afterwards
+    // by the output high pass.
+    fn mix(&self) -> f64 {
+        let outputs = [
+            self.tones[0].output(),
+            self.tones[1].output(),
+            self.tones[2].output(),
+            self.noise.output(),
+        ];
+        let mut accumulated = 0.0;
+        for (output, attenuation) in outputs.iter().zip(self.attenuations.iter()) {
+            if *output {
+                accumulated += sn76489_volume(*attenuation);
+            }
+        }
+        return accumulated / 4.0;
+    }
+}
+
+impl ScheduleNotes for Sn76489 {
+    type Channel = Sn76489Channel;
+
+    // The notes of the noise channel are shift rates ("0" to "3") instead
+    // of pitches.
+    fn schedule_note(
+        &mut self,
+        channel: Sn76489Channel,
+        start_ms: f64,
+        duration_ms: f64,
+        note: &str,
+    ) -> Result<(), InvalidApu> {
+        let end_ms = start_ms + duration_ms.max(0.0);
+        let voice = self.voices[channel.index()].clone();
+        let latch = 0x80 | ((channel.index() as u8) << 5);
+        let attenuation = 15 - voice.volume.min(15);
+        let mut writes: Vec<(f64, u8)> = match channel {
+            Sn76489Channel::Noise => {
+                let rate = note
+                    .trim()
+                    .parse::<u8>()
+                    .map_err(|_error| InvalidApu::new(InvalidApuKind::UnknownNote))?;
+                if rate > 3 {
+                    return Err(InvalidApu::new(InvalidApuKind::NoteOutOfRange));
+                }
+                let white = if voice.white_noise { 0x04 } else { 0x00 };
+                vec![(start_ms, latch | white | rate)]
+            }
+            _ => {
+                let frequency =
+                    parse_note(note).ok_or(InvalidApu::new(InvalidApuKind::UnknownNote))?;
+                let period = (self.clock / (32.0 * frequency)).round();
+                if !(1.0..=1023.0).contains(&period) {
+                    return Err(InvalidApu::new(InvalidApuKind::NoteOutOfRange));
+                }
+                let period = period as u16;
+                vec![
+                    (start_ms, latch | (period & 0x0F) as u8),
+                    (start_ms, ((period >> 4) & 0x3F) as u8),
+                ]
+            }
+        };
+        writes.push((start_ms, latch | 0x10 | attenuation));
+        writes.push((end_ms, latch | 0x1F));
+        for (time_ms, value) in writes {
+            self.schedule_register_write(time_ms, value)?;
+        }
+        return Ok(());
+    }
+}
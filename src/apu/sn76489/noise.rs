@@ -0,0 +1,63 @@
+use crate::waves::Lfsr;
+
+// Shift rates in steps of the chip; the fourth setting follows the period
+// of the third tone channel instead.
+const NOISE_PERIODS: [u16; 3] = [0x10, 0x20, 0x40];
+// Sega's variant of the chip: a 16 bit register tapping bits 0 and 3 for
+// white noise, and just bit 0 for periodic noise.
+const LFSR_WIDTH: u8 = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseChannel {
+    control: u8,
+    counter: u16,
+    flip_flop: bool,
+    lfsr: Lfsr,
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        return Self {
+            control: 0,
+            counter: 0,
+            flip_flop: false,
+            lfsr: Lfsr::new(LFSR_WIDTH, vec![0]).expect("a valid register"),
+        };
+    }
+}
+
+impl NoiseChannel {
+    // Any write to the noise register also resets the shift register.
+    pub fn write(&mut self, value: u8) {
+        self.control = value & 0x07;
+        let taps = if self.control & 0x04 != 0 {
+            vec![0, 3]
+        } else {
+            vec![0]
+        };
+        self.lfsr = Lfsr::new(LFSR_WIDTH, taps).expect("a valid register");
+    }
+
+    pub fn clock(&mut self, tone_3_flipped: bool) {
+        let rate = (self.control & 0x03) as usize;
+        let flipped = if rate == 3 {
+            tone_3_flipped
+        } else if self.counter <= 1 {
+            self.counter = NOISE_PERIODS[rate];
+            true
+        } else {
+            self.counter -= 1;
+            false
+        };
+        if flipped {
+            self.flip_flop = !self.flip_flop;
+            if self.flip_flop {
+                self.lfsr.step();
+            }
+        }
+    }
+
+    pub fn output(&self) -> bool {
+        return self.lfsr.output();
+    }
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::utils::testing::{assert_note_errors, assert_note_pitch};
+
+    #[test]
+    fn test_note_pitch() {
+        assert_note_pitch(
+            &[
+                Sn76489Channel::Tone1,
+                Sn76489Channel::Tone2,
+                Sn76489Channel::Tone3,
+            ],
+            || Sn76489Builder::default().finalize().unwrap(),
+            |psg, duration_ms| psg.render_ms(duration_ms).get_samples(),
+            4,
+        );
+    }
+
+    #[test]
+    fn test_volume_table() {
+        assert_eq!(sn76489_volume(0), 1.0);
+        assert!((sn76489_volume(3) - 0.5012).abs() < 0.001);
+        assert_eq!(sn76489_volume(15), 0.0);
+    }
+
+    #[test]
+    fn test_noise_release() {
+        let mut psg = Sn76489Builder::default().finalize().unwrap();
+        psg.schedule_note(Sn76489Channel::Noise, 0.0, 100.0, "0")
+            .unwrap();
+        let samples = psg.render_ms(400.0).get_samples();
+        assert!(samples[..4410].iter().any(|sample| sample.abs() > 0.05));
+        assert!(samples[13230..].iter().all(|sample| sample.abs() < 0.001));
+        assert_note_errors(
+            &mut psg,
+            &[(
+                Sn76489Channel::Noise,
+                0.0,
+                "4",
+                InvalidApuKind::NoteOutOfRange,
+            )],
+        );
+    }
+}
@@ -0,0 +1,34 @@
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ToneChannel {
+    period: u16,
+    counter: u16,
+    output: bool,
+}
+
+impl ToneChannel {
+    pub fn write_low(&mut self, value: u8) {
+        self.period = (self.period & 0x3F0) | (value & 0x0F) as u16;
+    }
+
+    pub fn write_high(&mut self, value: u8) {
+        self.period = (self.period & 0x00F) | (((value & 0x3F) as u16) << 4);
+    }
+
+    // Returns whether the output flipped, which the noise channel can use
+    // as its own clock.
+    pub fn clock(&mut self) -> bool {
+        if self.counter <= 1 {
+            self.counter = self.period.max(1);
+            self.output = !self.output;
+            return true;
+        }
+        self.counter -= 1;
+        return false;
+    }
+
+    // Periods of 0 and 1 hold the output high, which games use to play
+    // samples through the volume register.
+    pub fn output(&self) -> bool {
+        return self.output || self.period <= 1;
+    }
+}
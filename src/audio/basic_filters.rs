@@ -1,8 +1,12 @@
+use std::f64::consts::PI;
+
 use crate::time::has_sampling_frequency::HasSamplingFrequency;
 use crate::time::samples_to_milliseconds;
 
-use super::traits::FilterAudio;
 use super::Audio;
+use super::traits::FilterAudio;
+
+const MINIMUM_CUTOFF_FREQUENCY: f64 = 10.0;
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct BitCruncher(pub u32);
@@ -105,3 +109,123 @@ impl FilterAudio for Decay {
         filtered
     }
 }
+
+// Cutoffs sweep exponentially, in octaves per second.
+fn swept_cutoff(cutoff: f64, sweep: f64, sampling_frequency: f64) -> f64 {
+    (cutoff * 2.0_f64.powf(sweep / sampling_frequency)).max(MINIMUM_CUTOFF_FREQUENCY)
+}
+
+// A state variable low pass filter, as in the SID, with a resonance from
+// 0.0 to 1.0. It can also filter a wave as it is generated, one sample at
+// a time, and give its band and high pass outputs along the way. Its cutoff
+// stays under a sixth of the sampling frequency, where it is stable.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LowPass {
+    cutoff: f64,
+    resonance: f64,
+    sweep: f64,
+    low: f64,
+    band: f64,
+}
+
+#[allow(dead_code)]
+impl LowPass {
+    pub fn new(cutoff: f64, resonance: f64, sweep: f64) -> Self {
+        LowPass {
+            cutoff,
+            resonance,
+            sweep,
+            low: 0.0,
+            band: 0.0,
+        }
+    }
+
+    pub fn get_cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    pub fn next_sample(&mut self, input: f64, sampling_frequency: f64) -> f64 {
+        let (low, _band, _high) = self.next_outputs(input, sampling_frequency);
+        low
+    }
+
+    // The low, band and high pass outputs for the next sample.
+    pub fn next_outputs(&mut self, input: f64, sampling_frequency: f64) -> (f64, f64, f64) {
+        self.cutoff =
+            swept_cutoff(self.cutoff, self.sweep, sampling_frequency).min(sampling_frequency / 6.0);
+        let coefficient = 2.0 * f64::sin(PI * self.cutoff / sampling_frequency);
+        let damping = 1.0 / (0.707 + 1.5 * self.resonance);
+        self.low += coefficient * self.band;
+        let high = input - self.low - damping * self.band;
+        self.band += coefficient * high;
+        (self.low, self.band, high)
+    }
+}
+
+impl FilterAudio for LowPass {
+    fn filter(mut self, audio: Audio) -> Audio {
+        let sampling_frequency = audio.get_sampling_frequency();
+        let samples: Vec<_> = audio
+            .get_samples()
+            .into_iter()
+            .map(|sample| self.next_sample(sample, sampling_frequency))
+            .collect();
+        let mut filtered = Audio {
+            sampling_frequency: None,
+            samples,
+        };
+        filtered.set_sampling_frequency(sampling_frequency);
+        filtered
+    }
+}
+
+// A one pole high pass filter.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HighPass {
+    cutoff: f64,
+    sweep: f64,
+    input: f64,
+    output: f64,
+}
+
+#[allow(dead_code)]
+impl HighPass {
+    pub fn new(cutoff: f64, sweep: f64) -> Self {
+        HighPass {
+            cutoff,
+            sweep,
+            input: 0.0,
+            output: 0.0,
+        }
+    }
+
+    pub fn get_cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    pub fn next_sample(&mut self, input: f64, sampling_frequency: f64) -> f64 {
+        self.cutoff = swept_cutoff(self.cutoff, self.sweep, sampling_frequency);
+        let time_constant = 1.0 / (2.0 * PI * self.cutoff);
+        let smoothing = time_constant / (time_constant + 1.0 / sampling_frequency);
+        self.output = smoothing * (self.output + input - self.input);
+        self.input = input;
+        self.output
+    }
+}
+
+impl FilterAudio for HighPass {
+    fn filter(mut self, audio: Audio) -> Audio {
+        let sampling_frequency = audio.get_sampling_frequency();
+        let samples: Vec<_> = audio
+            .get_samples()
+            .into_iter()
+            .map(|sample| self.next_sample(sample, sampling_frequency))
+            .collect();
+        let mut filtered = Audio {
+            sampling_frequency: None,
+            samples,
+        };
+        filtered.set_sampling_frequency(sampling_frequency);
+        filtered
+    }
+}
@@ -0,0 +1,22 @@
+use super::Audio;
+use super::basic_filters::{BitCruncher, Decay};
+use super::envelope::Envelope;
+use super::traits::FilterAudio;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    BitCruncher(BitCruncher),
+    Decay(Decay),
+    Envelope(Envelope),
+}
+
+impl FilterAudio for Effect {
+    fn filter(self, audio: Audio) -> Audio {
+        match self {
+            Effect::BitCruncher(bit_cruncher) => bit_cruncher.filter(audio),
+            Effect::Decay(decay) => decay.filter(audio),
+            Effect::Envelope(envelope) => envelope.filter(audio),
+        }
+    }
+}
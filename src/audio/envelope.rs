@@ -0,0 +1,78 @@
+use builder_derive_macro::Setters;
+
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::samples_to_milliseconds;
+
+use super::traits::FilterAudio;
+use super::Audio;
+
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct Envelope {
+    attack_ms: f64,
+    decay_ms: f64,
+    sustain_level: f64,
+    release_ms: f64,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        return Self {
+            attack_ms: 0.0,
+            decay_ms: 0.0,
+            sustain_level: 1.0,
+            release_ms: 0.0,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl Envelope {
+    // The release happens inside the note, so that a note with an
+    // envelope lasts exactly as long as one without it.
+    pub fn level_at(&self, time_ms: f64, duration_ms: f64) -> f64 {
+        if time_ms < 0.0 || time_ms >= duration_ms {
+            return 0.0;
+        }
+        let release_start_ms = (duration_ms - self.release_ms).max(0.0);
+        if time_ms < release_start_ms {
+            return self.held_level_at(time_ms);
+        }
+        let release_level = self.held_level_at(release_start_ms);
+        let progress = (time_ms - release_start_ms) / self.release_ms;
+        return release_level * (1.0 - progress.clamp(0.0, 1.0));
+    }
+
+    fn held_level_at(&self, time_ms: f64) -> f64 {
+        if time_ms < self.attack_ms {
+            return time_ms / self.attack_ms;
+        }
+        let decay_time_ms = time_ms - self.attack_ms;
+        if decay_time_ms < self.decay_ms {
+            let progress = decay_time_ms / self.decay_ms;
+            return 1.0 - (1.0 - self.sustain_level) * progress;
+        }
+        return self.sustain_level;
+    }
+}
+
+impl FilterAudio for Envelope {
+    fn filter(self, audio: Audio) -> Audio {
+        let sampling_frequency = audio.get_sampling_frequency();
+        let duration_ms = audio.milliseconds_length();
+        let samples: Vec<_> = audio
+            .get_samples()
+            .into_iter()
+            .enumerate()
+            .map(|(index, sample)| {
+                let time = samples_to_milliseconds(sampling_frequency, index);
+                sample * self.level_at(time, duration_ms)
+            })
+            .collect();
+        let mut filtered = Audio {
+            sampling_frequency: None,
+            samples,
+        };
+        filtered.set_sampling_frequency(sampling_frequency);
+        filtered
+    }
+}
@@ -8,6 +8,9 @@ use crate::time::{milliseconds_to_samples, samples_to_milliseconds};
 use crate::utils::build::Build;
 
 pub mod basic_filters;
+pub mod effect;
+pub mod envelope;
+pub mod stereo;
 mod operations;
 mod tests;
 pub mod traits;
@@ -23,6 +26,7 @@ pub enum InvalidAudioKind {
     NegativeSamplingFrequency,
     MismatchedSamplingFrequency,
     MismatchedLength,
+    UnreadableWav,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -1,4 +1,5 @@
 use std::ops::Div;
+use std::ops::Mul;
 use std::ops::Sub;
 
 use crate::time::has_sampling_frequency::HasSamplingFrequency;
@@ -22,9 +23,18 @@ impl Div for Audio {
     }
 }
 
+impl Mul<f64> for Audio {
+    type Output = Self;
+
+    fn mul(mut self, gain: f64) -> Self::Output {
+        self.samples.iter_mut().for_each(|sample| *sample *= gain);
+        return self;
+    }
+}
+
 #[allow(dead_code)]
 impl Audio {
-    fn match_length(&mut self, other: &mut Self) {
+    pub fn match_length(&mut self, other: &mut Self) {
         self.match_sampling_frequencies(other);
         let len_self = self.samples.len();
         let len_other = other.samples.len();
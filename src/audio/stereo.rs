@@ -0,0 +1,84 @@
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+
+use super::Audio;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StereoAudio {
+    left: Audio,
+    right: Audio,
+}
+
+#[allow(dead_code)]
+impl StereoAudio {
+    pub fn new(mut left: Audio, mut right: Audio) -> Self {
+        left.match_length(&mut right);
+        return Self { left, right };
+    }
+
+    pub fn from_mono(audio: Audio) -> Self {
+        return Self::new(audio.clone(), audio);
+    }
+
+    pub fn get_left(&self) -> &Audio {
+        return &self.left;
+    }
+
+    pub fn get_right(&self) -> &Audio {
+        return &self.right;
+    }
+
+    pub fn into_channels(self) -> (Audio, Audio) {
+        return (self.left, self.right);
+    }
+
+    pub fn overlap(self, other: Self) -> Self {
+        return Self::new(self.left.overlap(other.left), self.right.overlap(other.right));
+    }
+
+    pub fn write_wav(self) {
+        let sample_rate = self.left.get_sampling_frequency() as u32;
+        let left = self.left.get_samples();
+        let right = self.right.get_samples();
+        let max = left
+            .iter()
+            .chain(right.iter())
+            .map(|sample| sample.abs())
+            .reduce(f64::max)
+            .unwrap_or(0.0);
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create("test.wav", spec).unwrap();
+        for (left_sample, right_sample) in left.into_iter().zip(right.into_iter()) {
+            writer
+                .write_sample((i16::MAX as f64 * left_sample / max) as i16)
+                .unwrap();
+            writer
+                .write_sample((i16::MAX as f64 * right_sample / max) as i16)
+                .unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+}
+
+impl Into<Audio> for StereoAudio {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.left.get_sampling_frequency();
+        let samples = self
+            .left
+            .samples
+            .into_iter()
+            .zip(self.right.samples.into_iter())
+            .map(|(left_sample, right_sample)| 0.5 * (left_sample + right_sample))
+            .collect();
+        let mut downmixed = Audio {
+            samples,
+            sampling_frequency: None,
+        };
+        downmixed.set_sampling_frequency(sampling_frequency);
+        return downmixed;
+    }
+}
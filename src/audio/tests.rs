@@ -1,5 +1,8 @@
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::PI;
+
+    use super::super::basic_filters::{HighPass, LowPass};
     use super::super::*;
 
     #[test]
@@ -87,4 +90,40 @@ mod tests {
         assert_eq!(x.samples.last().unwrap(), &1.0);
         assert_eq!(x.samples.len(), 44101);
     }
+
+    #[test]
+    fn test_low_and_high_pass() {
+        let tone = |frequency: f64| -> Audio {
+            let samples = (0..4410)
+                .map(|index| (2.0 * PI * frequency * index as f64 / 44100.0).sin())
+                .collect();
+            AudioBuilder::new(samples, 44100_f64).finalize().unwrap()
+        };
+        let peak = |audio: Audio| {
+            audio.get_samples()[2205..]
+                .iter()
+                .map(|sample| sample.abs())
+                .reduce(f64::max)
+                .unwrap()
+        };
+        let low_pass = LowPass::new(500.0, 0.0, 0.0);
+        assert!(peak(tone(50.0).filter_audio(low_pass.clone())) > 0.9);
+        assert!(peak(tone(5000.0).filter_audio(low_pass)) < 0.05);
+        let high_pass = HighPass::new(500.0, 0.0);
+        assert!(peak(tone(50.0).filter_audio(high_pass.clone())) < 0.15);
+        assert!(peak(tone(5000.0).filter_audio(high_pass)) > 0.9);
+        // Sweeping down an octave per second takes the cutoff to 250 Hz.
+        let mut swept = LowPass::new(500.0, 0.0, -1.0);
+        for _ in 0..44100 {
+            swept.next_sample(0.0, 44100_f64);
+        }
+        assert!((swept.get_cutoff() - 250.0).abs() < 1e-6);
+        // Only the low pass needs its cutoff kept low to stay stable.
+        let mut low_pass = LowPass::new(10000.0, 0.0, 0.0);
+        let mut high_pass = HighPass::new(10000.0, 0.0);
+        low_pass.next_sample(0.0, 44100_f64);
+        high_pass.next_sample(0.0, 44100_f64);
+        assert_eq!(low_pass.get_cutoff(), 44100.0 / 6.0);
+        assert_eq!(high_pass.get_cutoff(), 10000.0);
+    }
 }
@@ -1,4 +1,4 @@
-use super::Audio;
+use super::{Audio, InvalidAudio, InvalidAudioKind};
 
 use crate::time::has_sampling_frequency::HasSamplingFrequency;
 use crate::time::samples_to_milliseconds;
@@ -32,6 +32,39 @@ impl Audio {
         return new_vec;
     }
 
+    // Reads the first channel of a WAV file, with samples between -1.0 and
+    // 1.0 whatever the format of the file.
+    pub fn read_wav(path: &str) -> Result<Audio, InvalidAudio> {
+        let unreadable = |_error| InvalidAudio {
+            kind: InvalidAudioKind::UnreadableWav,
+        };
+        let mut reader = hound::WavReader::open(path).map_err(unreadable)?;
+        let spec = reader.spec();
+        let samples: Vec<f64> = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .samples::<f32>()
+                .map(|sample| sample.map(|sample| sample as f64))
+                .collect::<Result<_, _>>()
+                .map_err(unreadable)?,
+            hound::SampleFormat::Int => {
+                let full_scale = (1_i64 << (spec.bits_per_sample - 1)) as f64;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f64 / full_scale))
+                    .collect::<Result<_, _>>()
+                    .map_err(unreadable)?
+            }
+        };
+        let samples = samples
+            .into_iter()
+            .step_by(spec.channels.max(1) as usize)
+            .collect();
+        return Ok(Audio {
+            samples,
+            sampling_frequency: Some(spec.sample_rate as f64),
+        });
+    }
+
     pub fn write_wav(self) {
         let sample_rate = self.get_sampling_frequency() as u32;
         println!("{:?}", sample_rate);
@@ -0,0 +1,45 @@
+use builder_derive_macro::Setters;
+
+use crate::waves::traits::has_tone::Interval;
+
+use super::NTSC_FRAME_RATE;
+
+// The tracker 0xy effect: the tone cycles through the offsets, in
+// semitones from the root, switching rate times per second.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct Arpeggio {
+    offsets: Vec<i32>,
+    rate: f64,
+}
+
+impl Default for Arpeggio {
+    fn default() -> Self {
+        return Self {
+            offsets: vec![0],
+            rate: NTSC_FRAME_RATE,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl Arpeggio {
+    pub fn from_intervals(intervals: &[Interval], rate: f64) -> Self {
+        let offsets = intervals
+            .iter()
+            .map(|interval| interval.to_semitones() as i32)
+            .collect();
+        return Self::new(offsets, rate);
+    }
+
+    pub fn is_valid(&self) -> bool {
+        return !self.offsets.is_empty() && self.rate > 0.0;
+    }
+
+    pub fn step_at(&self, time: f64) -> usize {
+        return (time * self.rate) as usize % self.offsets.len();
+    }
+
+    pub fn offset(&self, step: usize) -> i32 {
+        return self.offsets[step % self.offsets.len()];
+    }
+}
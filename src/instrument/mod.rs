@@ -0,0 +1,322 @@
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::has_duration::HasDuration;
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::samples_to_seconds;
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_duty_cycle::HasDutyCycle;
+use crate::waves::traits::has_phase::{HasPhase, retune};
+use crate::waves::traits::has_tone::HasTone;
+use crate::waves::{Pulse, Sawtooth, Triangle};
+
+mod arpeggio;
+#[allow(unused_imports)]
+pub use arpeggio::*;
+mod sequence;
+#[allow(unused_imports)]
+pub use sequence::*;
+mod tests;
+
+pub const NTSC_FRAME_RATE: f64 = 60.0988;
+#[allow(dead_code)]
+pub const PAL_FRAME_RATE: f64 = 50.007;
+const MAX_VOLUME: f64 = 15.0;
+// The four duty cycles of the NES pulse channels, which duty macros index.
+const NES_DUTY_CYCLES: [f64; 4] = [0.125, 0.25, 0.5, 0.75];
+
+// Waves an instrument can drive. The duty macro only means something for
+// waves with a duty cycle, so it is ignored by default, like the triangle
+// channel ignores it in trackers.
+pub trait MacroTarget:
+    Iterator<Item = f64> + HasTone + HasAmplitude + HasPhase + HasDuration + HasSamplingFrequency
+{
+    fn apply_duty(&mut self, _duty: i32) {}
+}
+
+impl MacroTarget for Pulse {
+    fn apply_duty(&mut self, duty: i32) {
+        self.set_duty_cycle(NES_DUTY_CYCLES[duty.rem_euclid(4) as usize]);
+    }
+}
+
+impl MacroTarget for Triangle {}
+
+impl MacroTarget for Sawtooth {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidInstrumentKind {
+    NonPositiveFrameRate,
+    NegativeReleaseTime,
+    InvalidArpeggio,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidInstrument {
+    kind: InvalidInstrumentKind,
+}
+
+// Volume macros go from 0 to 15, arpeggio macros are absolute offsets in
+// semitones, pitch macros are relative offsets in cents that add up frame
+// after frame and duty macros are indices into the NES duty cycles. The
+// sequences are released at release_ms, counted from the start of the
+// sound; without it the note is held until its end. An arpeggio runs at
+// its own rate, on top of the arpeggio macro.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstrumentBuilder<W: MacroTarget> {
+    wave: W,
+    frame_rate: f64,
+    arpeggio: Option<Arpeggio>,
+    volume_macro: Option<Sequence>,
+    arpeggio_macro: Option<Sequence>,
+    pitch_macro: Option<Sequence>,
+    duty_macro: Option<Sequence>,
+    release_ms: Option<f64>,
+}
+
+#[allow(dead_code)]
+impl<W: MacroTarget> InstrumentBuilder<W> {
+    pub fn new(wave: W) -> Self {
+        return Self {
+            wave,
+            frame_rate: NTSC_FRAME_RATE,
+            arpeggio: None,
+            volume_macro: None,
+            arpeggio_macro: None,
+            pitch_macro: None,
+            duty_macro: None,
+            release_ms: None,
+        };
+    }
+
+    pub fn get_frame_rate(&self) -> f64 {
+        return self.frame_rate;
+    }
+
+    pub fn with_frame_rate(mut self, frame_rate: f64) -> Self {
+        self.frame_rate = frame_rate;
+        return self;
+    }
+
+    pub fn with_arpeggio(mut self, arpeggio: Arpeggio) -> Self {
+        self.arpeggio = Some(arpeggio);
+        return self;
+    }
+
+    pub fn with_volume_macro(mut self, sequence: Sequence) -> Self {
+        self.volume_macro = Some(sequence);
+        return self;
+    }
+
+    pub fn with_arpeggio_macro(mut self, sequence: Sequence) -> Self {
+        self.arpeggio_macro = Some(sequence);
+        return self;
+    }
+
+    pub fn with_pitch_macro(mut self, sequence: Sequence) -> Self {
+        self.pitch_macro = Some(sequence);
+        return self;
+    }
+
+    pub fn with_duty_macro(mut self, sequence: Sequence) -> Self {
+        self.duty_macro = Some(sequence);
+        return self;
+    }
+
+    pub fn with_release_ms(mut self, release_ms: f64) -> Self {
+        self.release_ms = Some(release_ms);
+        return self;
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<InvalidInstrument>> {
+        let mut possible_errors: Vec<InvalidInstrument> = vec![];
+        if self.frame_rate <= 0.0 || self.frame_rate.is_nan() {
+            possible_errors.push(InvalidInstrument {
+                kind: InvalidInstrumentKind::NonPositiveFrameRate,
+            });
+        }
+        if self.release_ms.is_some_and(|release_ms| release_ms < 0.0) {
+            possible_errors.push(InvalidInstrument {
+                kind: InvalidInstrumentKind::NegativeReleaseTime,
+            });
+        }
+        if self
+            .arpeggio
+            .as_ref()
+            .is_some_and(|arpeggio| !arpeggio.is_valid())
+        {
+            possible_errors.push(InvalidInstrument {
+                kind: InvalidInstrumentKind::InvalidArpeggio,
+            });
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    pub fn finalize(self) -> Result<Instrument<W>, InvalidInstrument> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        return Ok(Instrument {
+            tone: self.wave.get_tone(),
+            amplitude: self.wave.get_amplitude(),
+            wave: self.wave,
+            frame_rate: self.frame_rate,
+            arpeggio: self.arpeggio,
+            arpeggio_step: None,
+            macros: [
+                self.volume_macro,
+                self.arpeggio_macro,
+                self.pitch_macro,
+                self.duty_macro,
+            ],
+            cursors: Default::default(),
+            release_ms: self.release_ms,
+            pitch_offset_cents: 0.0,
+            frame: None,
+            sample_index: 0,
+        });
+    }
+}
+
+// Indices of the macros of an instrument.
+const VOLUME: usize = 0;
+const ARPEGGIO: usize = 1;
+const PITCH: usize = 2;
+const DUTY: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instrument<W: MacroTarget> {
+    tone: f64,
+    amplitude: f64,
+    wave: W,
+    frame_rate: f64,
+    arpeggio: Option<Arpeggio>,
+    arpeggio_step: Option<usize>,
+    macros: [Option<Sequence>; 4],
+    cursors: [SequenceCursor; 4],
+    release_ms: Option<f64>,
+    pitch_offset_cents: f64,
+    frame: Option<usize>,
+    sample_index: usize,
+}
+
+impl<W: MacroTarget> HasTone for Instrument<W> {
+    fn get_tone(&self) -> f64 {
+        self.tone
+    }
+
+    fn set_tone(&mut self, tone: f64) {
+        self.tone = tone;
+        self.wave.set_tone(tone);
+    }
+}
+
+impl<W: MacroTarget> HasAmplitude for Instrument<W> {
+    fn get_amplitude(&self) -> f64 {
+        self.amplitude
+    }
+
+    fn set_amplitude(&mut self, amplitude: f64) {
+        self.amplitude = amplitude;
+        self.wave.set_amplitude(amplitude);
+    }
+}
+
+impl<W: MacroTarget> HasDuration for Instrument<W> {
+    fn get_duration_ms(&self) -> f64 {
+        self.wave.get_duration_ms()
+    }
+
+    fn set_duration_ms(&mut self, duration_ms: f64) {
+        self.wave.set_duration_ms(duration_ms);
+    }
+}
+
+#[allow(dead_code)]
+impl<W: MacroTarget> Instrument<W> {
+    // Invalid arpeggios are ignored.
+    pub fn set_arpeggio(&mut self, arpeggio: Option<Arpeggio>) {
+        self.arpeggio = arpeggio.filter(|arpeggio| arpeggio.is_valid());
+    }
+
+    fn macro_value(&self, index: usize) -> Option<i32> {
+        let sequence = self.macros[index].as_ref()?;
+        return self.cursors[index].value(sequence);
+    }
+
+    fn advance_frame(&mut self, time_ms: f64) {
+        let released = self
+            .release_ms
+            .is_some_and(|release_ms| time_ms >= release_ms);
+        for (cursor, sequence) in self.cursors.iter_mut().zip(self.macros.iter()) {
+            if let Some(sequence) = sequence
+                && !(released && cursor.release(sequence))
+            {
+                cursor.step(sequence);
+            }
+        }
+    }
+
+    fn apply_macros(&mut self) {
+        if let Some(volume) = self.macro_value(VOLUME) {
+            let level = (volume as f64).clamp(0.0, MAX_VOLUME) / MAX_VOLUME;
+            self.wave.set_amplitude(self.amplitude * level);
+        }
+        if let Some(pitch) = self.macro_value(PITCH) {
+            self.pitch_offset_cents += pitch as f64;
+        }
+        if let Some(duty) = self.macro_value(DUTY) {
+            self.wave.apply_duty(duty);
+        }
+    }
+
+    fn update_tone(&mut self) {
+        let mut semitones = self.macro_value(ARPEGGIO).unwrap_or(0);
+        if let (Some(arpeggio), Some(step)) = (&self.arpeggio, self.arpeggio_step) {
+            semitones += arpeggio.offset(step);
+        }
+        let cents = 100.0 * semitones as f64 + self.pitch_offset_cents;
+        let tone = self.tone * 2.0_f64.powf(cents / 1200.0);
+        if tone != self.wave.get_tone() {
+            retune(&mut self.wave, self.sample_index, tone);
+        }
+    }
+}
+
+impl<W: MacroTarget> Iterator for Instrument<W> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let time = samples_to_seconds(self.wave.get_sampling_frequency(), self.sample_index);
+        let frame = (time * self.frame_rate) as usize;
+        let arpeggio_step = self
+            .arpeggio
+            .as_ref()
+            .map(|arpeggio| arpeggio.step_at(time));
+        let new_frame = self.frame != Some(frame);
+        if new_frame {
+            if self.frame.is_some() {
+                self.advance_frame(1000.0 * time);
+            }
+            self.frame = Some(frame);
+            self.apply_macros();
+        }
+        if new_frame || self.arpeggio_step != arpeggio_step {
+            self.arpeggio_step = arpeggio_step;
+            self.update_tone();
+        }
+        let sample = self.wave.next()?;
+        self.sample_index = self.sample_index + 1;
+        return Some(sample);
+    }
+}
+
+impl<W: MacroTarget> Into<Audio> for Instrument<W> {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.wave.get_sampling_frequency();
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
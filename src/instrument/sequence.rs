@@ -0,0 +1,72 @@
+use builder_derive_macro::Setters;
+
+// A tracker style macro: one value per frame. While the note is held the
+// sequence loops back to the loop point when it reaches its end, or when
+// it reaches the release point if the loop point comes before it. Once the
+// note is released it carries on after the release point, and only loops
+// again if the loop point comes after the release point.
+#[derive(Clone, Debug, Default, PartialEq, Setters)]
+pub struct Sequence {
+    values: Vec<i32>,
+    loop_point: Option<usize>,
+    release_point: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SequenceCursor {
+    position: usize,
+    released: bool,
+}
+
+#[allow(dead_code)]
+impl SequenceCursor {
+    pub fn value(&self, sequence: &Sequence) -> Option<i32> {
+        return sequence.values.get(self.position).copied();
+    }
+
+    pub fn step(&mut self, sequence: &Sequence) {
+        let length = sequence.values.len();
+        if !self.released
+            && let Some(release_point) = sequence.release_point
+            && self.position >= release_point
+        {
+            self.position = match sequence.loop_point {
+                Some(loop_point) if loop_point <= release_point => loop_point,
+                _ => release_point,
+            };
+            return;
+        }
+        if self.position + 1 < length {
+            self.position += 1;
+            return;
+        }
+        let can_loop = match (self.released, sequence.release_point) {
+            (true, Some(release_point)) => sequence
+                .loop_point
+                .is_some_and(|loop_point| loop_point > release_point),
+            _ => true,
+        };
+        if let Some(loop_point) = sequence.loop_point
+            && can_loop
+            && loop_point < length
+        {
+            self.position = loop_point;
+        }
+    }
+
+    // Returns whether the cursor jumped past the release point, in which
+    // case it already points at the value of the next frame.
+    pub fn release(&mut self, sequence: &Sequence) -> bool {
+        if self.released {
+            return false;
+        }
+        self.released = true;
+        if let Some(release_point) = sequence.release_point
+            && release_point + 1 < sequence.values.len()
+        {
+            self.position = release_point + 1;
+            return true;
+        }
+        return false;
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::waves::PulseBuilder;
+
+    fn positions(sequence: &Sequence, frames: usize, release_frame: usize) -> Vec<i32> {
+        let mut cursor = SequenceCursor::default();
+        let mut values = vec![cursor.value(sequence).unwrap()];
+        for frame in 1..frames {
+            if !(frame >= release_frame && cursor.release(sequence)) {
+                cursor.step(sequence);
+            }
+            values.push(cursor.value(sequence).unwrap());
+        }
+        return values;
+    }
+
+    #[test]
+    fn test_sequence_loop() {
+        let sequence = Sequence::new(vec![0, 1, 2, 3], Some(1), None);
+        assert_eq!(positions(&sequence, 8, 8), vec![0, 1, 2, 3, 1, 2, 3, 1]);
+        let sequence = Sequence::new(vec![0, 1, 2], None, None);
+        assert_eq!(positions(&sequence, 5, 5), vec![0, 1, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_sequence_release() {
+        let sequence = Sequence::new(vec![0, 1, 2, 3, 4], Some(1), Some(2));
+        assert_eq!(
+            positions(&sequence, 10, 6),
+            vec![0, 1, 2, 1, 2, 1, 3, 4, 4, 4]
+        );
+        let sequence = Sequence::new(vec![0, 1, 2, 3, 4], Some(4), Some(1));
+        assert_eq!(positions(&sequence, 6, 3), vec![0, 1, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_volume_macro() {
+        let pulse = PulseBuilder::default()
+            .with_tone(440.0)
+            .with_duration_ms(100.0)
+            .finalize()
+            .unwrap();
+        let instrument = InstrumentBuilder::new(pulse)
+            .with_frame_rate(100.0)
+            .with_volume_macro(Sequence::new(vec![15, 0], None, None))
+            .with_duty_macro(Sequence::new(vec![0, 1, 2, 3], None, None))
+            .finalize()
+            .unwrap();
+        let samples: Vec<f64> = instrument.collect();
+        assert!(samples[..441].iter().all(|sample| sample.abs() == 1.0));
+        assert!(samples[441..].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn test_arpeggio() {
+        let pulse = PulseBuilder::default()
+            .with_tone(440.0)
+            .with_duration_ms(200.0)
+            .finalize()
+            .unwrap();
+        let instrument = InstrumentBuilder::new(pulse)
+            .with_arpeggio(Arpeggio::new(vec![0, 12], 10.0))
+            .finalize()
+            .unwrap();
+        let samples: Vec<f64> = instrument.collect();
+        let rising_edges = |samples: &[f64]| {
+            samples
+                .windows(2)
+                .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+                .count()
+        };
+        assert!((43..=45).contains(&rising_edges(&samples[..4410])));
+        assert!((87..=89).contains(&rising_edges(&samples[4410..])));
+    }
+}
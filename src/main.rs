@@ -1,5 +1,12 @@
+mod apu;
 mod audio;
+mod instrument;
+mod midi;
+mod mml;
 mod rythm;
+mod sfx;
+mod song;
+mod tracker;
 use rythm::{Rythm, RythmBuilder};
 mod utils;
 use utils::build::Build;
@@ -25,206 +32,63 @@ fn main() {
     let mut harmony_4 = harmony_1.clone();
     let mut harmony_5 = harmony_1.clone();
     let mut melody_1 = harmony_1.clone();
-    bass.hits_with_frequency(
-        triangle.clone().with_amplitude(4.0).finalize().unwrap(),
-        &[(0.75, "g3"), (0.75, "d3")],
-    );
-    bass.bis(7);
-    bass.hits_with_frequency(
-        triangle.clone().with_amplitude(4.0).finalize().unwrap(),
-        &[
-            (0.75, "f#3"),
-            (0.75, "b2"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "d3"),
-            (0.75, "a2"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "e3"),
-            (0.75, "f#3"),
-            (0.75, "b2"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "a3"),
-            (0.75, "d3"),
-            (0.75, "g3"),
-            (0.75, "d3"),
-            (0.75, "g3"),
-            (0.75, "d3"),
-            (0.75, "g3"),
-            (0.75, "d3"),
-            (0.75, "g3"),
-            (0.75, "d3"),
-            (0.75, "g3"),
-            (0.75, "d3"),
-            (0.75, "g3"),
-            (0.75, "d3"),
-            (0.75, "g3"),
-            (0.75, "d3"),
-            (0.75, "g3"),
-            (0.75, "d3"),
-            (0.75, "f#3"),
-            (0.75, "b2"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "d3"),
-            (0.75, "a2"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "d3"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "e3"),
-            (0.75, "a3"),
-            (0.75, "d3"),
-        ],
-    );
-    harmony_1.hits_with_frequency(
-        pulse.clone().finalize().unwrap(),
-        &[(-0.25, ""), (0.5, "b4"), (-0.25, ""), (0.5, "a4")],
-    );
-    harmony_1.bis(8);
-    harmony_1.hits_with_frequency(
-        pulse.clone().finalize().unwrap(),
-        &[
-            (-0.25, ""),
-            (0.5, "g4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "f4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "g4"),
-            (-0.25, ""),
-            (0.5, "d4"),
-            (-0.25, ""),
-            (0.5, "c4"),
-            (-0.25, ""),
-            (0.5, "c4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "d4"),
-            (-0.25, ""),
-            (0.5, "c4"),
-            (-0.25, ""),
-            (0.5, "c4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "c#5"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.25, "b3"),
-            (0.25, "d4"),
-            (0.75, "g4"),
-            (0.75, "a3"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "g4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "f4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "g4"),
-            (-0.25, ""),
-            (0.5, "d4"),
-            (-0.25, ""),
-            (0.5, "c4"),
-            (-0.25, ""),
-            (0.5, "c4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "d4"),
-            (-0.25, ""),
-            (0.5, "c4"),
-            (-0.25, ""),
-            (0.5, "c4"),
-            (-0.25, ""),
-            (0.5, "b4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.5, "c5"),
-            (-0.25, ""),
-            (0.5, "a4"),
-            (-0.25, ""),
-            (0.25, "b3"),
-            (0.25, "d4"),
-            (0.75, "g4"),
-            (0.75, "a3"),
-        ],
-    );
+    let bass_sound = triangle.clone().with_amplitude(4.0).finalize().unwrap();
+    let mut bass_patterns = rythm::Arrangement::default();
+    let bass_motifs: &[(&str, &[&str])] = &[
+        ("vamp", &["g3", "d3"]),
+        ("turnaround", &["f#3", "b2", "e3", "e3"]),
+        (
+            "pedal",
+            &[
+                "d3", "a2", "d3", "d3", "d3", "d3", "d3", "d3", "d3", "d3", "d3",
+            ],
+        ),
+        ("pickup", &["e3"]),
+        ("hold", &["e3", "e3", "e3", "e3", "e3"]),
+        ("cadence", &["e3", "a3", "d3"]),
+    ];
+    for (name, notes) in bass_motifs {
+        let notes: Vec<_> = notes.iter().map(|note| (0.75, *note)).collect();
+        bass_patterns
+            .new_pattern(name)
+            .unwrap()
+            .hits_with_frequency(bass_sound.clone(), &notes);
+    }
+    let vamp = rythm::Order::repeat(vec![rythm::Order::pattern("vamp")], 8);
+    bass_patterns
+        .with_order(vamp.clone())
+        .with_order(rythm::Order::repeat_with_endings(
+            vec![
+                rythm::Order::pattern("turnaround"),
+                rythm::Order::pattern("pedal"),
+            ],
+            2,
+            vec![
+                vec![
+                    rythm::Order::pattern("pickup"),
+                    rythm::Order::pattern("turnaround"),
+                    rythm::Order::pattern("cadence"),
+                    vamp,
+                ],
+                vec![
+                    rythm::Order::pattern("hold"),
+                    rythm::Order::pattern("cadence"),
+                ],
+            ],
+        ))
+        .arrange_into(&mut bass)
+        .unwrap();
+    mml::parse_channel(
+        &mut harmony_1,
+        &pulse.clone().finalize().unwrap(),
+        "o4 l4 [r b2 r a2]9
+         r g2 r b2 r f2 r a2 r g2 r d2 r c2 r c2 r a2 r a2 r d2 r c2 r c2 r b2 r a2
+         r b2 r >c+2 r <a2 r <b >d g2. <a2.
+         r >b2 [r a2 r b2]7 r a2 r a2 r b2
+         r g2 r b2 r f2 r a2 r g2 r d2 r c2 r c2 r a2 r a2 r d2 r c2 r c2 r b2 r a2
+         r a2 r >c2 r <a2 r <b >d g2. <a2.",
+    )
+    .unwrap();
     harmony_2.hits_with_frequency(
         pulse.clone().finalize().unwrap(),
         &[(-0.25, ""), (0.5, "d5"), (-0.25, ""), (0.5, "c#5")],
@@ -666,20 +530,21 @@ fn main() {
         ],
     );
     println!("{}", bass.len() * 4.0 / 3.0);
-    let audio_1: audio::Audio = bass.into();
     println!("{}", harmony_1.len() * 4.0 / 3.0);
-    let audio_2: audio::Audio = harmony_1.into();
     println!("{}", harmony_2.len() * 4.0 / 3.0);
-    let audio_3: audio::Audio = harmony_2.into();
     println!("{}", harmony_3.len() * 4.0 / 3.0);
-    let audio_4: audio::Audio = harmony_3.into();
     println!("{}", harmony_4.len() * 4.0 / 3.0);
-    let audio_5: audio::Audio = harmony_4.into();
     println!("{}", harmony_5.len() * 4.0 / 3.0);
-    let audio_6: audio::Audio = harmony_5.into();
     println!("{}", melody_1.len() * 4.0 / 3.0);
-    let audio_7: audio::Audio = melody_1.into();
-    let audio = audio_1 / audio_2 / audio_3 / audio_4 / audio_5 / audio_6 / audio_7;
+    let mut song = song::Song::default();
+    song.add_track(song::Track::from_rythm("bass", bass)).unwrap();
+    let harmonies = [harmony_1, harmony_2, harmony_3, harmony_4, harmony_5];
+    for (index, harmony) in harmonies.into_iter().enumerate() {
+        let name = format!("harmony {}", index + 1);
+        song.add_track(song::Track::from_rythm(&name, harmony)).unwrap();
+    }
+    song.add_track(song::Track::from_rythm("melody", melody_1)).unwrap();
+    let audio: audio::Audio = song.into();
     // let audio = audio.filter_audio(audio::basic_filters::BitCruncher(12));
     audio.write_wav();
 }
@@ -0,0 +1,177 @@
+use crate::audio::Audio;
+use crate::rythm::{BarBeatTick, Rythm, TempoMap, TempoRamp};
+use crate::waves::traits::has_tone::{A_0_FREQUENCY, HasTone};
+
+use super::import::A_0_KEY;
+use super::{
+    InvalidMidi, InvalidMidiKind, MidiEvent, MidiMessage, PERCUSSION_CHANNEL, StandardMidiFile,
+};
+
+pub const DEFAULT_TICKS_PER_QUARTER: u16 = 480;
+const CHANNEL_COUNT: usize = 16;
+const MAXIMUM_KEY: f64 = 127.0;
+const MAXIMUM_VELOCITY: f64 = 127.0;
+const US_PER_MINUTE: f64 = 60_000_000.0;
+const PITCH_BEND_CENTER: u16 = 8192;
+const MAXIMUM_PITCH_BEND: f64 = 16383.0;
+// What most synthesizers bend over, either way, unless told otherwise.
+const PITCH_BEND_RANGE_SEMITONES: f64 = 2.0;
+// MIDI has no tempo ramps, so they are written as a step every sixteenth
+// note.
+const TEMPO_RAMP_STEP: f64 = 1.0 / 16.0;
+
+// The nearest key and how far the frequency is from it, in semitones.
+pub fn frequency_to_key(frequency: f64) -> (u8, f64) {
+    let semitones = 12.0 * (frequency / A_0_FREQUENCY).log2() + A_0_KEY as f64;
+    let key = semitones.round().clamp(0.0, MAXIMUM_KEY);
+    return (key as u8, semitones - key);
+}
+
+fn pitch_bend_value(semitones: f64) -> u16 {
+    let center = PITCH_BEND_CENTER as f64;
+    let value = center + semitones / PITCH_BEND_RANGE_SEMITONES * center;
+    return value.round().clamp(0.0, MAXIMUM_PITCH_BEND) as u16;
+}
+
+// Note offs go before anything else on the same tick, so a key can be hit
+// again right after it is released, and note ons after their pitch bend.
+fn event_order(event: &MidiEvent) -> (u64, u8) {
+    let rank = match event.message {
+        MidiMessage::NoteOff { .. } => 0,
+        MidiMessage::NoteOn { .. } => 2,
+        _ => 1,
+    };
+    return (event.tick, rank);
+}
+
+#[allow(dead_code)]
+impl StandardMidiFile {
+    // A format 1 file with nothing but an empty conductor track, which
+    // will hold the tempos and time signatures.
+    pub fn new(ticks_per_quarter: u16) -> Self {
+        return Self {
+            format: 1,
+            ticks_per_quarter,
+            tracks: vec![vec![]],
+        };
+    }
+
+    pub fn from_rythm<T>(rythm: &Rythm<T>) -> Self
+    where
+        T: Into<Audio> + HasTone,
+    {
+        let mut file = Self::new(DEFAULT_TICKS_PER_QUARTER);
+        file.add_rythm("", rythm)
+            .expect("the first rythm always has a channel");
+        return file;
+    }
+
+    pub fn position_to_ticks(&self, position: f64) -> u64 {
+        let ticks = position * 4.0 * self.ticks_per_quarter as f64;
+        return ticks.round().max(0.0) as u64;
+    }
+
+    fn conductor_events(&self, tempo_map: &TempoMap) -> Vec<MidiEvent> {
+        let tempo_event = |position: f64| {
+            let us_per_quarter = US_PER_MINUTE / tempo_map.quarter_note_tempo_at(position);
+            let message = MidiMessage::Tempo(us_per_quarter.round() as u32);
+            return MidiEvent::new(self.position_to_ticks(position), message);
+        };
+        let mut events = vec![];
+        let changes = tempo_map.tempo_changes();
+        for (index, (position, _)) in changes.iter().enumerate() {
+            events.push(tempo_event(*position));
+            if let Some((next_position, TempoRamp::Linear)) = changes.get(index + 1) {
+                let mut step = position + TEMPO_RAMP_STEP;
+                while step < *next_position {
+                    events.push(tempo_event(step));
+                    step += TEMPO_RAMP_STEP;
+                }
+            }
+        }
+        for (bar, time_signature) in tempo_map.time_signatures() {
+            let position = tempo_map
+                .bar_beat_tick_to_position(&BarBeatTick::new(*bar, 1, 0))
+                .expect("every bar has a first beat");
+            let message = MidiMessage::TimeSignature {
+                numerator: time_signature.beats_per_bar as u8,
+                denominator: time_signature.beat_value as u8,
+            };
+            events.push(MidiEvent::new(self.position_to_ticks(position), message));
+        }
+        events.sort_by_key(|event| event.tick);
+        return events;
+    }
+
+    // Each rythm gets a track and a channel of its own, skipping the one
+    // for percussion, and the first one gives the tempos and time
+    // signatures. Hits are written as scored, without the groove. Tones
+    // between keys are bent into tune, but as pitch bends are for the
+    // whole channel, a chord only gets the bend of its last note.
+    pub fn add_rythm<T>(&mut self, name: &str, rythm: &Rythm<T>) -> Result<(), InvalidMidi>
+    where
+        T: Into<Audio> + HasTone,
+    {
+        let track_index = self.tracks.len().max(1) - 1;
+        let channel = match track_index < PERCUSSION_CHANNEL as usize {
+            true => track_index,
+            false => track_index + 1,
+        };
+        if channel >= CHANNEL_COUNT {
+            return Err(InvalidMidi {
+                kind: InvalidMidiKind::TooManyChannels,
+            });
+        }
+        let channel = channel as u8;
+        if self.tracks.is_empty() {
+            self.tracks.push(vec![]);
+        }
+        if track_index == 0 {
+            self.tracks[0] = self.conductor_events(rythm.get_tempo_map());
+        }
+        let mut events = vec![];
+        if !name.is_empty() {
+            events.push(MidiEvent::new(0, MidiMessage::TrackName(name.to_string())));
+        }
+        let mut pitch_bend = PITCH_BEND_CENTER;
+        for hit in rythm.scheduled_hits() {
+            let tone = hit.wave.get_tone();
+            if tone <= 0.0 || tone.is_nan() {
+                continue;
+            }
+            let (key, offset) = frequency_to_key(tone);
+            let start = self.position_to_ticks(hit.position);
+            let end = self.position_to_ticks(hit.position + hit.held_duration);
+            let note_pitch_bend = pitch_bend_value(offset);
+            if note_pitch_bend != pitch_bend {
+                pitch_bend = note_pitch_bend;
+                let message = MidiMessage::PitchBend {
+                    channel,
+                    value: pitch_bend,
+                };
+                events.push(MidiEvent::new(start, message));
+            }
+            let velocity = (hit.velocity * MAXIMUM_VELOCITY)
+                .round()
+                .clamp(1.0, MAXIMUM_VELOCITY);
+            let note_on = MidiMessage::NoteOn {
+                channel,
+                key,
+                velocity: velocity as u8,
+            };
+            events.push(MidiEvent::new(start, note_on));
+            let note_off = MidiMessage::NoteOff {
+                channel,
+                key,
+                velocity: 0,
+            };
+            events.push(MidiEvent::new(end.max(start + 1), note_off));
+        }
+        // Trailing rests are kept by ending the track with the rythm.
+        let end = self.position_to_ticks(rythm.len());
+        events.push(MidiEvent::new(end, MidiMessage::EndOfTrack));
+        events.sort_by_key(event_order);
+        self.tracks.push(events);
+        return Ok(());
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::audio::Audio;
+use crate::rythm::{Articulation, Beat, Rythm, RythmBuilder, TempoMap, TempoRamp, TimeSignature};
+use crate::song::{Song, Track};
+use crate::time::has_duration::HasDuration;
+use crate::utils::build::Build;
+use crate::waves::traits::has_tone::{A_0_FREQUENCY, HasTone, SEMI_TONE_FACTOR};
+use crate::waves::{LfsrNoiseBuilder, PulseBuilder, SawtoothBuilder, TriangleBuilder};
+
+use super::{
+    DEFAULT_TEMPO_US_PER_QUARTER, InvalidMidi, InvalidMidiKind, MidiMessage, StandardMidiFile,
+};
+
+pub(super) const A_0_KEY: i32 = 21;
+const MAXIMUM_VELOCITY: f64 = 127.0;
+const US_PER_MINUTE: f64 = 60_000_000.0;
+// Channels count from 0, so this is channel 10 of General MIDI.
+pub const PERCUSSION_CHANNEL: u8 = 9;
+
+pub fn key_to_frequency(key: u8) -> f64 {
+    return A_0_FREQUENCY * SEMI_TONE_FACTOR.powi(key as i32 - A_0_KEY);
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum MidiVoice {
+    Pulse(PulseBuilder),
+    Triangle(TriangleBuilder),
+    Sawtooth(SawtoothBuilder),
+    // Unpitched: notes keep the clock frequency of the builder, whatever
+    // their key, as drum kits map keys to instruments rather than pitches.
+    Noise(LfsrNoiseBuilder),
+}
+
+// A voice for the channel comes first, then one for the first program the
+// channel asks for, then the default. Percussion plays noise by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiMapping {
+    channels: Vec<(u8, MidiVoice)>,
+    programs: Vec<(u8, MidiVoice)>,
+    default: MidiVoice,
+}
+
+impl Default for MidiMapping {
+    fn default() -> Self {
+        return Self {
+            channels: vec![(
+                PERCUSSION_CHANNEL,
+                MidiVoice::Noise(LfsrNoiseBuilder::default()),
+            )],
+            programs: vec![],
+            default: MidiVoice::Pulse(PulseBuilder::default()),
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl MidiMapping {
+    pub fn with_channel(mut self, channel: u8, voice: MidiVoice) -> Self {
+        self.channels.retain(|(mapped, _)| *mapped != channel);
+        self.channels.push((channel, voice));
+        return self;
+    }
+
+    pub fn with_program(mut self, program: u8, voice: MidiVoice) -> Self {
+        self.programs.retain(|(mapped, _)| *mapped != program);
+        self.programs.push((program, voice));
+        return self;
+    }
+
+    pub fn with_default(mut self, voice: MidiVoice) -> Self {
+        self.default = voice;
+        return self;
+    }
+
+    pub fn voice_for(&self, channel: u8, program: Option<u8>) -> &MidiVoice {
+        let by_channel = self.channels.iter().find(|(mapped, _)| *mapped == channel);
+        let by_program = || {
+            self.programs
+                .iter()
+                .find(|(mapped, _)| Some(*mapped) == program)
+        };
+        return by_channel
+            .or_else(by_program)
+            .map_or(&self.default, |(_, voice)| voice);
+    }
+}
+
+struct MidiNote {
+    start: u64,
+    end: u64,
+    key: u8,
+    velocity: u8,
+}
+
+#[allow(dead_code)]
+impl StandardMidiFile {
+    // Time signatures that don't start on a bar line start on the next one.
+    pub fn tempo_map(&self) -> Result<TempoMap, InvalidMidi> {
+        let invalid_timing = |_error| InvalidMidi {
+            kind: InvalidMidiKind::InvalidTiming,
+        };
+        let tempo_bpm = US_PER_MINUTE / DEFAULT_TEMPO_US_PER_QUARTER as f64;
+        let mut tempo_map = TempoMap::new(tempo_bpm, Beat::QuarterNote, TimeSignature::default());
+        for event in self.merged_events() {
+            let position = self.ticks_to_position(event.tick);
+            match event.message {
+                MidiMessage::Tempo(us_per_quarter) => {
+                    let tempo_bpm = US_PER_MINUTE / us_per_quarter as f64;
+                    tempo_map
+                        .set_tempo_at(position, tempo_bpm, TempoRamp::Instant)
+                        .map_err(invalid_timing)?;
+                }
+                MidiMessage::TimeSignature {
+                    numerator,
+                    denominator,
+                } => {
+                    let at = tempo_map.position_to_bar_beat_tick(position);
+                    let bar = match at.beat == 1 && at.tick == 0 {
+                        true => at.bar,
+                        false => at.bar + 1,
+                    };
+                    let time_signature = TimeSignature::new(numerator as u32, denominator as u32);
+                    tempo_map
+                        .set_time_signature_at(bar, time_signature)
+                        .map_err(invalid_timing)?;
+                }
+                _ => {}
+            }
+        }
+        return Ok(tempo_map);
+    }
+
+    // The channels that play at least one note.
+    pub fn channels(&self) -> Vec<u8> {
+        let mut channels: Vec<u8> = self
+            .merged_events()
+            .iter()
+            .filter_map(|event| match event.message {
+                MidiMessage::NoteOn { channel, .. } => Some(channel),
+                _ => None,
+            })
+            .collect();
+        channels.sort();
+        channels.dedup();
+        return channels;
+    }
+
+    pub fn program_of(&self, channel: u8) -> Option<u8> {
+        return self
+            .merged_events()
+            .iter()
+            .find_map(|event| match event.message {
+                MidiMessage::ProgramChange {
+                    channel: event_channel,
+                    program,
+                } if event_channel == channel => Some(program),
+                _ => None,
+            });
+    }
+
+    // Each note off ends the earliest note on of its key, and notes still
+    // on at the end of the file end with it.
+    fn notes_of(&self, channel: u8) -> Vec<MidiNote> {
+        let events = self.merged_events();
+        let last_tick = events.last().map_or(0, |event| event.tick);
+        let mut playing: HashMap<u8, VecDeque<(u64, u8)>> = HashMap::new();
+        let mut notes = vec![];
+        for event in events {
+            match event.message {
+                MidiMessage::NoteOn {
+                    channel: event_channel,
+                    key,
+                    velocity,
+                } if event_channel == channel => {
+                    playing
+                        .entry(key)
+                        .or_default()
+                        .push_back((event.tick, velocity));
+                }
+                MidiMessage::NoteOff {
+                    channel: event_channel,
+                    key,
+                    ..
+                } if event_channel == channel => {
+                    if let Some((start, velocity)) =
+                        playing.get_mut(&key).and_then(VecDeque::pop_front)
+                    {
+                        notes.push(MidiNote {
+                            start,
+                            end: event.tick,
+                            key,
+                            velocity,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (key, starts) in playing {
+            for (start, velocity) in starts {
+                notes.push(MidiNote {
+                    start,
+                    end: last_tick,
+                    key,
+                    velocity,
+                });
+            }
+        }
+        notes.sort_by_key(|note| (note.start, note.key));
+        return notes;
+    }
+
+    fn channel_rythm_with<T>(
+        &self,
+        channel: u8,
+        root_sound: &T,
+        pitched: bool,
+    ) -> Result<Rythm<T>, InvalidMidi>
+    where
+        T: Into<Audio> + HasTone + HasDuration + Clone,
+    {
+        let tempo_map = self.tempo_map()?;
+        let mut rythm = RythmBuilder::default().finalize().expect("TODO");
+        rythm.set_tempo_map(tempo_map.clone());
+        let notes = self.notes_of(channel);
+        let mut end_position = 0.0;
+        let mut index = 0;
+        // Notes starting on the same tick play together, until the next
+        // ones start or, for the last ones, until the longest one ends.
+        while index < notes.len() {
+            let start = notes[index].start;
+            let together = notes[index..]
+                .iter()
+                .take_while(|note| note.start == start)
+                .count();
+            let group = &notes[index..index + together];
+            index += together;
+            let next_start = match notes.get(index) {
+                Some(note) => note.start,
+                None => group.iter().map(|note| note.end).max().unwrap_or(start),
+            };
+            let start_position = self.ticks_to_position(start);
+            if start_position > end_position {
+                rythm.hit(-(start_position - end_position), root_sound.clone());
+            }
+            let sounds = group
+                .iter()
+                .map(|note| {
+                    let end_position = self.ticks_to_position(note.end);
+                    let mut sound = root_sound.clone();
+                    if pitched {
+                        sound.set_tone(key_to_frequency(note.key));
+                    }
+                    sound.set_duration_ms(
+                        tempo_map.position_to_ms(end_position)
+                            - tempo_map.position_to_ms(start_position),
+                    );
+                    let velocity = note.velocity as f64 / MAXIMUM_VELOCITY;
+                    let articulation = Articulation::default().with_velocity(velocity);
+                    (end_position - start_position, sound, articulation)
+                })
+                .collect();
+            let duration = self.ticks_to_position(next_start) - start_position;
+            rythm.hit_together_articulated(duration, sounds);
+            end_position = start_position + duration;
+        }
+        return Ok(rythm);
+    }
+
+    // Every note of the channel on the root sound, at the tempos and time
+    // signatures of the file.
+    pub fn channel_rythm<T>(&self, channel: u8, root_sound: &T) -> Result<Rythm<T>, InvalidMidi>
+    where
+        T: Into<Audio> + HasTone + HasDuration + Clone,
+    {
+        return self.channel_rythm_with(channel, root_sound, true);
+    }
+
+    pub fn channel_rythms<T>(&self, root_sound: &T) -> Result<Vec<(u8, Rythm<T>)>, InvalidMidi>
+    where
+        T: Into<Audio> + HasTone + HasDuration + Clone,
+    {
+        return self
+            .channels()
+            .into_iter()
+            .map(|channel| Ok((channel, self.channel_rythm(channel, root_sound)?)))
+            .collect();
+    }
+
+    // Each channel makes a track, named "channel 1" for channel 0 and so
+    // on, as sequencers number them.
+    pub fn to_song(&self, mapping: &MidiMapping) -> Result<Song, InvalidMidi> {
+        let invalid_voice = |_error| InvalidMidi {
+            kind: InvalidMidiKind::InvalidVoice,
+        };
+        let mut song = Song::default();
+        for channel in self.channels() {
+            let voice = mapping.voice_for(channel, self.program_of(channel));
+            let audio: Audio = match voice {
+                MidiVoice::Pulse(builder) => {
+                    let sound = builder.clone().finalize().map_err(invalid_voice)?;
+                    self.channel_rythm_with(channel, &sound, true)?.into()
+                }
+                MidiVoice::Triangle(builder) => {
+                    let sound = builder.clone().finalize().map_err(invalid_voice)?;
+                    self.channel_rythm_with(channel, &sound, true)?.into()
+                }
+                MidiVoice::Sawtooth(builder) => {
+                    let sound = builder.clone().finalize().map_err(invalid_voice)?;
+                    self.channel_rythm_with(channel, &sound, true)?.into()
+                }
+                MidiVoice::Noise(builder) => {
+                    let sound = builder.clone().finalize().map_err(invalid_voice)?;
+                    self.channel_rythm_with(channel, &sound, false)?.into()
+                }
+            };
+            let name = format!("channel {}", channel + 1);
+            song.add_track(Track::from_rythm(&name, audio))
+                .expect("channel names are unique");
+        }
+        return Ok(song);
+    }
+}
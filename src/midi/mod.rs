@@ -0,0 +1,30 @@
+mod export;
+#[allow(unused_imports)]
+pub use export::*;
+mod import;
+#[allow(unused_imports)]
+pub use import::*;
+mod smf;
+#[allow(unused_imports)]
+pub use smf::*;
+mod tests;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidMidiKind {
+    NotAMidiFile,
+    UnsupportedFormat,
+    UnsupportedTimeDivision,
+    TruncatedFile,
+    InvalidEvent,
+    UnreadableFile,
+    UnwritableFile,
+    TooManyChannels,
+    InvalidTiming,
+    InvalidVoice,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidMidi {
+    kind: InvalidMidiKind,
+}
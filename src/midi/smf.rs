@@ -0,0 +1,392 @@
+use super::{InvalidMidi, InvalidMidiKind};
+
+// 120 quarter notes per minute, what a file without tempo events plays at.
+pub const DEFAULT_TEMPO_US_PER_QUARTER: u32 = 500_000;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const PROGRAM_CHANGE: u8 = 0xc0;
+const CHANNEL_PRESSURE: u8 = 0xd0;
+const PITCH_BEND: u8 = 0xe0;
+const SYSTEM_EXCLUSIVE: u8 = 0xf0;
+const ESCAPE: u8 = 0xf7;
+const META: u8 = 0xff;
+const META_TRACK_NAME: u8 = 0x03;
+const META_END_OF_TRACK: u8 = 0x2f;
+const META_TEMPO: u8 = 0x51;
+const META_TIME_SIGNATURE: u8 = 0x58;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum MidiMessage {
+    NoteOff { channel: u8, key: u8, velocity: u8 },
+    NoteOn { channel: u8, key: u8, velocity: u8 },
+    ProgramChange { channel: u8, program: u8 },
+    // Fourteen bits, from 0 to 16383, with no bend at 8192.
+    PitchBend { channel: u8, value: u16 },
+    TrackName(String),
+    // In microseconds per quarter note.
+    Tempo(u32),
+    // The denominator is the note value, 8 for eighth notes, not its power
+    // of two as stored in the file.
+    TimeSignature { numerator: u8, denominator: u8 },
+    EndOfTrack,
+    // Every other event, kept so the ticks of the others stay right.
+    Other,
+}
+
+// Ticks are counted from the start of the track, not from the previous
+// event as in the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiEvent {
+    pub tick: u64,
+    pub message: MidiMessage,
+}
+
+#[allow(dead_code)]
+impl MidiEvent {
+    pub fn new(tick: u64, message: MidiMessage) -> Self {
+        return Self { tick, message };
+    }
+}
+
+// Only formats 0 and 1, with their ticks in fractions of a quarter note,
+// are supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StandardMidiFile {
+    pub format: u16,
+    pub ticks_per_quarter: u16,
+    pub tracks: Vec<Vec<MidiEvent>>,
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        return Self { bytes, index: 0 };
+    }
+
+    fn is_empty(&self) -> bool {
+        return self.index >= self.bytes.len();
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], InvalidMidi> {
+        let end = self.index + length;
+        if end > self.bytes.len() {
+            return Err(InvalidMidi {
+                kind: InvalidMidiKind::TruncatedFile,
+            });
+        }
+        let bytes = &self.bytes[self.index..end];
+        self.index = end;
+        return Ok(bytes);
+    }
+
+    fn peek_u8(&self) -> Result<u8, InvalidMidi> {
+        return self.bytes.get(self.index).copied().ok_or(InvalidMidi {
+            kind: InvalidMidiKind::TruncatedFile,
+        });
+    }
+
+    fn read_u8(&mut self) -> Result<u8, InvalidMidi> {
+        return Ok(self.read_bytes(1)?[0]);
+    }
+
+    fn read_u16(&mut self) -> Result<u16, InvalidMidi> {
+        let bytes = self.read_bytes(2)?;
+        return Ok(u16::from_be_bytes([bytes[0], bytes[1]]));
+    }
+
+    fn read_u32(&mut self) -> Result<u32, InvalidMidi> {
+        let bytes = self.read_bytes(4)?;
+        return Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+
+    // Seven bits per byte, most significant first, with the top bit set on
+    // every byte but the last. The standard allows up to four bytes.
+    fn read_variable_length(&mut self) -> Result<u32, InvalidMidi> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let byte = self.read_u8()?;
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        return Err(InvalidMidi {
+            kind: InvalidMidiKind::InvalidEvent,
+        });
+    }
+}
+
+fn parse_meta(meta_type: u8, data: &[u8]) -> MidiMessage {
+    match (meta_type, data) {
+        (META_TRACK_NAME, name) => MidiMessage::TrackName(String::from_utf8_lossy(name).into()),
+        (META_END_OF_TRACK, _) => MidiMessage::EndOfTrack,
+        (META_TEMPO, [high, middle, low]) => {
+            MidiMessage::Tempo(u32::from_be_bytes([0, *high, *middle, *low]))
+        }
+        (META_TIME_SIGNATURE, [numerator, denominator_power, ..]) if *denominator_power < 8 => {
+            MidiMessage::TimeSignature {
+                numerator: *numerator,
+                denominator: 1 << denominator_power,
+            }
+        }
+        _ => MidiMessage::Other,
+    }
+}
+
+// Channel messages can leave out their status byte when it is the same as
+// the previous one, which is called running status.
+fn parse_track(bytes: &[u8]) -> Result<Vec<MidiEvent>, InvalidMidi> {
+    let mut reader = ByteReader::new(bytes);
+    let mut events = vec![];
+    let mut tick = 0;
+    let mut running_status = None;
+    while !reader.is_empty() {
+        tick += reader.read_variable_length()? as u64;
+        let status = match reader.peek_u8()? {
+            byte if byte & 0x80 != 0 => {
+                reader.read_u8()?;
+                byte
+            }
+            _ => running_status.ok_or(InvalidMidi {
+                kind: InvalidMidiKind::InvalidEvent,
+            })?,
+        };
+        let message = match status {
+            NOTE_OFF..SYSTEM_EXCLUSIVE => {
+                running_status = Some(status);
+                let channel = status & 0x0f;
+                let data_length = match status & 0xf0 {
+                    PROGRAM_CHANGE | CHANNEL_PRESSURE => 1,
+                    _ => 2,
+                };
+                let data = reader.read_bytes(data_length)?;
+                match (status & 0xf0, data) {
+                    // A note on without velocity is how most files write
+                    // note offs, to make the most of running status.
+                    (NOTE_ON, [key, 0]) => MidiMessage::NoteOff {
+                        channel,
+                        key: *key,
+                        velocity: 0,
+                    },
+                    (NOTE_OFF, [key, velocity]) => MidiMessage::NoteOff {
+                        channel,
+                        key: *key,
+                        velocity: *velocity,
+                    },
+                    (NOTE_ON, [key, velocity]) => MidiMessage::NoteOn {
+                        channel,
+                        key: *key,
+                        velocity: *velocity,
+                    },
+                    (PROGRAM_CHANGE, [program]) => MidiMessage::ProgramChange {
+                        channel,
+                        program: *program,
+                    },
+                    (PITCH_BEND, [low, high]) => MidiMessage::PitchBend {
+                        channel,
+                        value: (*high as u16) << 7 | *low as u16,
+                    },
+                    _ => MidiMessage::Other,
+                }
+            }
+            SYSTEM_EXCLUSIVE | ESCAPE => {
+                running_status = None;
+                let length = reader.read_variable_length()?;
+                reader.read_bytes(length as usize)?;
+                MidiMessage::Other
+            }
+            META => {
+                running_status = None;
+                let meta_type = reader.read_u8()?;
+                let length = reader.read_variable_length()?;
+                parse_meta(meta_type, reader.read_bytes(length as usize)?)
+            }
+            _ => {
+                return Err(InvalidMidi {
+                    kind: InvalidMidiKind::InvalidEvent,
+                });
+            }
+        };
+        let is_end = message == MidiMessage::EndOfTrack;
+        events.push(MidiEvent::new(tick, message));
+        if is_end {
+            break;
+        }
+    }
+    return Ok(events);
+}
+
+fn write_variable_length(bytes: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        groups.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.extend(groups.iter().rev());
+}
+
+fn write_meta(bytes: &mut Vec<u8>, meta_type: u8, data: &[u8]) {
+    bytes.extend([META, meta_type]);
+    write_variable_length(bytes, data.len() as u32);
+    bytes.extend(data);
+}
+
+// Always writes status bytes, without running status. Events that were
+// read but not understood can't be written back, and are left out.
+fn write_message(bytes: &mut Vec<u8>, message: &MidiMessage) {
+    match message {
+        MidiMessage::NoteOff {
+            channel,
+            key,
+            velocity,
+        } => bytes.extend([NOTE_OFF | channel, *key, *velocity]),
+        MidiMessage::NoteOn {
+            channel,
+            key,
+            velocity,
+        } => bytes.extend([NOTE_ON | channel, *key, *velocity]),
+        MidiMessage::ProgramChange { channel, program } => {
+            bytes.extend([PROGRAM_CHANGE | channel, *program])
+        }
+        MidiMessage::PitchBend { channel, value } => bytes.extend([
+            PITCH_BEND | channel,
+            (value & 0x7f) as u8,
+            (value >> 7 & 0x7f) as u8,
+        ]),
+        MidiMessage::TrackName(name) => write_meta(bytes, META_TRACK_NAME, name.as_bytes()),
+        MidiMessage::Tempo(us_per_quarter) => {
+            write_meta(bytes, META_TEMPO, &us_per_quarter.to_be_bytes()[1..])
+        }
+        MidiMessage::TimeSignature {
+            numerator,
+            denominator,
+        } => {
+            let denominator_power = denominator.max(&1).ilog2() as u8;
+            // Also a metronome click per quarter note and eight thirty
+            // second notes per quarter note, as almost every file has.
+            write_meta(
+                bytes,
+                META_TIME_SIGNATURE,
+                &[*numerator, denominator_power, 24, 8],
+            );
+        }
+        MidiMessage::EndOfTrack => write_meta(bytes, META_END_OF_TRACK, &[]),
+        MidiMessage::Other => {}
+    }
+}
+
+// Events are written in the order of their ticks, and every track ends
+// with a single end of track event, at the latest of its own end of track
+// and its last event.
+fn write_track(bytes: &mut Vec<u8>, events: &[MidiEvent]) {
+    let end_tick = events
+        .iter()
+        .filter(|event| event.message == MidiMessage::EndOfTrack)
+        .map(|event| event.tick)
+        .max()
+        .unwrap_or(0);
+    let mut events: Vec<&MidiEvent> = events
+        .iter()
+        .filter(|event| !matches!(event.message, MidiMessage::EndOfTrack | MidiMessage::Other))
+        .collect();
+    events.sort_by_key(|event| event.tick);
+    let last_tick = events.last().map_or(0, |event| event.tick);
+    let end_of_track = MidiEvent::new(last_tick.max(end_tick), MidiMessage::EndOfTrack);
+    let mut track = vec![];
+    let mut tick = 0;
+    for event in events.into_iter().chain([&end_of_track]) {
+        write_variable_length(&mut track, (event.tick - tick) as u32);
+        write_message(&mut track, &event.message);
+        tick = event.tick;
+    }
+    bytes.extend(b"MTrk");
+    bytes.extend((track.len() as u32).to_be_bytes());
+    bytes.extend(track);
+}
+
+#[allow(dead_code)]
+impl StandardMidiFile {
+    pub fn parse(bytes: &[u8]) -> Result<Self, InvalidMidi> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.read_bytes(4).ok() != Some(b"MThd".as_slice()) {
+            return Err(InvalidMidi {
+                kind: InvalidMidiKind::NotAMidiFile,
+            });
+        }
+        let header_length = reader.read_u32()? as usize;
+        let mut header = ByteReader::new(reader.read_bytes(header_length)?);
+        let format = header.read_u16()?;
+        let track_count = header.read_u16()?;
+        let division = header.read_u16()?;
+        if format > 1 {
+            return Err(InvalidMidi {
+                kind: InvalidMidiKind::UnsupportedFormat,
+            });
+        }
+        // The top bit set means ticks are frames of SMPTE time code.
+        if division & 0x8000 != 0 || division == 0 {
+            return Err(InvalidMidi {
+                kind: InvalidMidiKind::UnsupportedTimeDivision,
+            });
+        }
+        let mut tracks = vec![];
+        while !reader.is_empty() && tracks.len() < track_count as usize {
+            let chunk_type = reader.read_bytes(4)?;
+            let length = reader.read_u32()? as usize;
+            let chunk = reader.read_bytes(length)?;
+            // Unknown chunks are to be skipped, as the standard says.
+            if chunk_type == b"MTrk" {
+                tracks.push(parse_track(chunk)?);
+            }
+        }
+        return Ok(Self {
+            format,
+            ticks_per_quarter: division,
+            tracks,
+        });
+    }
+
+    pub fn read(path: &str) -> Result<Self, InvalidMidi> {
+        let bytes = std::fs::read(path).map_err(|_error| InvalidMidi {
+            kind: InvalidMidiKind::UnreadableFile,
+        })?;
+        return Self::parse(&bytes);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = b"MThd".to_vec();
+        bytes.extend(6_u32.to_be_bytes());
+        bytes.extend(self.format.to_be_bytes());
+        bytes.extend((self.tracks.len() as u16).to_be_bytes());
+        bytes.extend(self.ticks_per_quarter.to_be_bytes());
+        for track in self.tracks.iter() {
+            write_track(&mut bytes, track);
+        }
+        return bytes;
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), InvalidMidi> {
+        return std::fs::write(path, self.to_bytes()).map_err(|_error| InvalidMidi {
+            kind: InvalidMidiKind::UnwritableFile,
+        });
+    }
+
+    // Every event of every track, in the order they are played.
+    pub fn merged_events(&self) -> Vec<&MidiEvent> {
+        let mut events: Vec<&MidiEvent> = self.tracks.iter().flatten().collect();
+        events.sort_by_key(|event| event.tick);
+        return events;
+    }
+
+    // Ticks to whole notes, the unit of positions in rythms.
+    pub fn ticks_to_position(&self, ticks: u64) -> f64 {
+        return ticks as f64 / (4.0 * self.ticks_per_quarter as f64);
+    }
+}
@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::audio::Audio;
+    use crate::rythm::{Articulation, BarBeatTick, RythmBuilder, TimeSignature};
+    use crate::utils::build::Build;
+    use crate::waves;
+    use crate::waves::traits::has_tone::HasTone;
+
+    // A format 0 file at 480 ticks per quarter note: a C major third on
+    // channel 1, written with running status, then a kick on channel 10.
+    fn file_bytes() -> Vec<u8> {
+        let track: Vec<u8> = vec![
+            0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20, // 120 bpm
+            0x00, 0xff, 0x58, 0x04, 0x03, 0x02, 0x18, 0x08, // 3/4
+            0x00, 0xc0, 0x01, // program 1
+            0x00, 0x90, 60, 127, // note on
+            0x00, 64, 64, // running status
+            0x83, 0x60, 60, 0, // note off at 480 ticks
+            0x00, 64, 0, // running status
+            0x83, 0x60, 0x99, 36, 100, // kick at 960 ticks
+            0x81, 0x70, 0x89, 36, 0, // note off at 1200 ticks
+            0x00, 0xff, 0x2f, 0x00,
+        ];
+        let mut bytes = b"MThd".to_vec();
+        bytes.extend([0, 0, 0, 6, 0, 0, 0, 1, 0x01, 0xe0]);
+        bytes.extend(b"MTrk");
+        bytes.extend((track.len() as u32).to_be_bytes());
+        bytes.extend(track);
+        return bytes;
+    }
+
+    #[test]
+    fn test_parse() {
+        let file = StandardMidiFile::parse(&file_bytes()).unwrap();
+        assert_eq!(file.ticks_per_quarter, 480);
+        assert_eq!(file.tracks[0].len(), 10);
+        assert_eq!(file.channels(), vec![0, 9]);
+        assert_eq!(file.program_of(0), Some(1));
+        let tempo_map = file.tempo_map().unwrap();
+        assert_eq!(tempo_map.tempo_at(0.0), 120.0);
+        assert_eq!(
+            tempo_map.time_signature_at_bar(1),
+            &TimeSignature::new(3, 4)
+        );
+        assert_eq!(
+            StandardMidiFile::parse(b"RIFF"),
+            Err(InvalidMidi {
+                kind: InvalidMidiKind::NotAMidiFile
+            })
+        );
+        let bytes = file_bytes();
+        assert_eq!(
+            StandardMidiFile::parse(&bytes[..bytes.len() - 3]),
+            Err(InvalidMidi {
+                kind: InvalidMidiKind::TruncatedFile
+            })
+        );
+    }
+
+    #[test]
+    fn test_import() {
+        let file = StandardMidiFile::parse(&file_bytes()).unwrap();
+        let pulse = waves::PulseBuilder::default().finalize().unwrap();
+        let rythm = file.channel_rythm(0, &pulse).unwrap();
+        assert_eq!(rythm.hit_positions(), vec![BarBeatTick::new(1, 1, 0)]);
+        let tone = rythm
+            .get_hit_at(&BarBeatTick::new(1, 1, 0))
+            .unwrap()
+            .get_tone();
+        assert!((tone - 261.6256).abs() < 1e-3);
+        let audio: Audio = rythm.into();
+        assert_eq!(audio.get_samples().len(), 22050);
+        let song = file.to_song(&MidiMapping::default()).unwrap();
+        let stems = song.render_stems();
+        assert_eq!(stems[1].0, "channel 10");
+        let (left, _) = stems[1].1.clone().into_channels();
+        assert_eq!(left.get_samples().len(), 55125);
+    }
+
+    #[test]
+    fn test_export() {
+        let pulse = waves::PulseBuilder::default().finalize().unwrap();
+        let mut rythm = RythmBuilder::default()
+            .with_tempo_bpm(90.0)
+            .finalize()
+            .unwrap();
+        let soft = Articulation::default().with_velocity(0.5);
+        rythm.hits_with_frequency(
+            pulse.clone(),
+            &[
+                (0.25, "a4", Articulation::default()),
+                (-0.25, "", Articulation::default()),
+                (0.5, "c5", soft),
+            ],
+        );
+        let mut out_of_tune = pulse.clone();
+        out_of_tune.set_tone(445.0);
+        rythm.hit(0.25, out_of_tune);
+        let bytes = StandardMidiFile::from_rythm(&rythm).to_bytes();
+        let file = StandardMidiFile::parse(&bytes).unwrap();
+        assert_eq!(file.tracks.len(), 2);
+        assert!(file.tracks[0].contains(&MidiEvent::new(0, MidiMessage::Tempo(666667))));
+        assert!(file.tracks[1].contains(&MidiEvent::new(
+            960,
+            MidiMessage::NoteOn {
+                channel: 0,
+                key: 72,
+                velocity: 64
+            }
+        )));
+        assert!(file.tracks[1].contains(&MidiEvent::new(
+            1920,
+            MidiMessage::PitchBend {
+                channel: 0,
+                value: 8993
+            }
+        )));
+        let imported = file.channel_rythm(0, &pulse).unwrap();
+        assert!((imported.get_tempo_map().tempo_at(0.0) - 90.0).abs() < 1e-3);
+        assert_eq!(
+            imported.hit_positions(),
+            vec![
+                BarBeatTick::new(1, 1, 0),
+                BarBeatTick::new(1, 3, 0),
+                BarBeatTick::new(2, 1, 0)
+            ]
+        );
+        // A trailing rest moves the end of the track past the last note.
+        let mut rested = rythm.clone();
+        rested.hit(-0.25, pulse.clone());
+        let bytes = StandardMidiFile::from_rythm(&rested).to_bytes();
+        let file = StandardMidiFile::parse(&bytes).unwrap();
+        assert_eq!(
+            file.tracks[1].last(),
+            Some(&MidiEvent::new(2880, MidiMessage::EndOfTrack))
+        );
+        let mut file = StandardMidiFile::new(DEFAULT_TICKS_PER_QUARTER);
+        for _ in 0..15 {
+            file.add_rythm("", &rythm).unwrap();
+        }
+        assert_eq!(
+            file.add_rythm("", &rythm),
+            Err(InvalidMidi {
+                kind: InvalidMidiKind::TooManyChannels
+            })
+        );
+    }
+}
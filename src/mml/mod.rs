@@ -0,0 +1,327 @@
+use crate::audio::Audio;
+use crate::rythm::{Articulation, Beat, Rythm, RythmBuilder};
+use crate::song::{Song, Track};
+use crate::utils::build::Build;
+use crate::waves::traits::has_tone::{HasTone, parse_note};
+
+mod tests;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidMmlKind {
+    UnexpectedCharacter,
+    InvalidLength,
+    InvalidNote,
+    InvalidTie,
+    InvalidTempo,
+    InvalidVolume,
+    InvalidGate,
+    UnclosedLoop,
+    UnopenedLoop,
+    InvalidRythm,
+}
+
+// The position is the index of the character, counted over the whole
+// text, where the error was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidMml {
+    kind: InvalidMmlKind,
+    position: usize,
+}
+
+const MAXIMUM_VOLUME: u32 = 15;
+const GATE_STEPS: u32 = 8;
+
+// Reads one channel. The octave, length, volume and gate carry over from
+// one command to the next, loops included, as in most MML dialects.
+struct MmlParser<'a, T> {
+    characters: Vec<char>,
+    offset: usize,
+    index: usize,
+    root_sound: &'a T,
+    octave: u32,
+    length: f64,
+    articulation: Articulation,
+}
+
+impl<'a, T: Into<Audio> + HasTone + Clone> MmlParser<'a, T> {
+    fn new(channel: &str, offset: usize, root_sound: &'a T) -> Self {
+        return Self {
+            characters: channel.chars().collect(),
+            offset,
+            index: 0,
+            root_sound,
+            octave: 4,
+            length: Beat::QuarterNote.duration_factor(),
+            articulation: Articulation::default(),
+        };
+    }
+
+    fn error(&self, kind: InvalidMmlKind) -> InvalidMml {
+        return InvalidMml {
+            kind,
+            position: self.offset + self.index,
+        };
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.index += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.characters.get(self.index).copied();
+    }
+
+    fn next_character(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.index += 1;
+        return Some(character.to_ascii_lowercase());
+    }
+
+    fn read_number(&mut self) -> Option<u32> {
+        let start = self.index;
+        while self
+            .peek()
+            .is_some_and(|character| character.is_ascii_digit())
+        {
+            self.index += 1;
+        }
+        let digits: String = self.characters[start..self.index].iter().collect();
+        return digits.parse().ok();
+    }
+
+    // A length in whole notes, like relative durations: a note length
+    // followed by dots, each adding half of what the previous one added.
+    fn read_length(&mut self) -> Result<f64, InvalidMml> {
+        let mut length = match self.read_number() {
+            Some(0) => return Err(self.error(InvalidMmlKind::InvalidLength)),
+            Some(note_length) => Beat::from_note_length(note_length).duration_factor(),
+            None => self.length,
+        };
+        let mut dot_length = length;
+        while self.peek() == Some('.') {
+            self.index += 1;
+            dot_length /= 2.0;
+            length += dot_length;
+        }
+        return Ok(length);
+    }
+
+    // Sharps are written + or # and flats -, which parse_note reads as b.
+    fn read_accidentals(&mut self) -> String {
+        let mut accidentals = String::new();
+        loop {
+            match self.peek() {
+                Some('+') | Some('#') => accidentals.push('#'),
+                Some('-') => accidentals.push('b'),
+                _ => return accidentals,
+            }
+            self.index += 1;
+        }
+    }
+
+    // Ties add up lengths, whether the note is repeated after the & or not.
+    // A repeated note has to be the same one, as ties do not slur.
+    fn read_tied_length(&mut self, letter: char, accidentals: &str) -> Result<f64, InvalidMml> {
+        let mut length = self.read_length()?;
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('&') {
+                return Ok(length);
+            }
+            self.index += 1;
+            self.skip_whitespace();
+            if self
+                .peek()
+                .is_some_and(|character| "abcdefgr".contains(character.to_ascii_lowercase()))
+            {
+                let start = self.index;
+                let tied_letter = self.next_character().expect("peeked");
+                if tied_letter != letter || self.read_accidentals() != accidentals {
+                    self.index = start;
+                    return Err(self.error(InvalidMmlKind::InvalidTie));
+                }
+            }
+            length += self.read_length()?;
+        }
+    }
+
+    fn note(&mut self, letter: char, rythm: &mut Rythm<T>) -> Result<(), InvalidMml> {
+        let accidentals = self.read_accidentals();
+        let note = format!("{}{}{}", letter, accidentals, self.octave);
+        let tone = parse_note(&note).ok_or_else(|| self.error(InvalidMmlKind::InvalidNote))?;
+        let length = self.read_tied_length(letter, &accidentals)?;
+        let mut sound = self.root_sound.clone();
+        sound.set_tone(tone);
+        rythm.hit_articulated(length, sound, self.articulation.clone());
+        return Ok(());
+    }
+
+    fn rest(&mut self, rythm: &mut Rythm<T>) -> Result<(), InvalidMml> {
+        let length = self.read_tied_length('r', "")?;
+        rythm.hit(-length, self.root_sound.clone());
+        return Ok(());
+    }
+
+    // The index of the ] closing the loop that starts at the current
+    // index, and of the : in it, if any.
+    fn find_loop_end(&self) -> Result<(usize, Option<usize>), InvalidMml> {
+        let mut depth = 0;
+        let mut last_pass_break = None;
+        for (index, character) in self.characters.iter().enumerate().skip(self.index) {
+            match character {
+                '[' => depth += 1,
+                ':' if depth == 0 => last_pass_break = Some(index),
+                ']' if depth == 0 => return Ok((index, last_pass_break)),
+                ']' => depth -= 1,
+                _ => {}
+            }
+        }
+        // Points at the [ rather than at the end of the channel.
+        return Err(InvalidMml {
+            kind: InvalidMmlKind::UnclosedLoop,
+            position: self.offset + self.index - 1,
+        });
+    }
+
+    // [body]n plays the body n times, twice without a count. Whatever
+    // follows a : in the body is left out of the last pass.
+    fn parse_loop(&mut self, rythm: &mut Rythm<T>) -> Result<(), InvalidMml> {
+        let body_start = self.index;
+        let (body_end, last_pass_break) = self.find_loop_end()?;
+        self.index = body_end + 1;
+        let count = self.read_number().unwrap_or(2);
+        let after_loop = self.index;
+        for pass in 0..count {
+            let end = match last_pass_break {
+                Some(last_pass_break) if pass + 1 == count => last_pass_break,
+                Some(_) | None => body_end,
+            };
+            self.index = body_start;
+            self.parse_until(end, rythm)?;
+        }
+        self.index = after_loop;
+        return Ok(());
+    }
+
+    fn parse_until(&mut self, end: usize, rythm: &mut Rythm<T>) -> Result<(), InvalidMml> {
+        while self.index < end {
+            let start = self.index;
+            let character = self.next_character().expect("before the end");
+            match character {
+                'a'..='g' => self.note(character, rythm)?,
+                'r' => self.rest(rythm)?,
+                'o' => {
+                    self.octave = self
+                        .read_number()
+                        .ok_or_else(|| self.error(InvalidMmlKind::InvalidNote))?
+                }
+                '>' => self.octave += 1,
+                '<' => {
+                    self.octave = self
+                        .octave
+                        .checked_sub(1)
+                        .ok_or_else(|| self.error(InvalidMmlKind::InvalidNote))?
+                }
+                'l' => self.length = self.read_length()?,
+                't' => {
+                    let tempo = self.read_number().unwrap_or(0);
+                    rythm
+                        .set_tempo(tempo as f64)
+                        .map_err(|_| self.error(InvalidMmlKind::InvalidTempo))?;
+                }
+                'v' => match self.read_number() {
+                    Some(volume) if volume <= MAXIMUM_VOLUME => {
+                        let velocity = volume as f64 / MAXIMUM_VOLUME as f64;
+                        self.articulation = self.articulation.clone().with_velocity(velocity);
+                    }
+                    _ => return Err(self.error(InvalidMmlKind::InvalidVolume)),
+                },
+                'q' => match self.read_number() {
+                    Some(gate) if gate > 0 && gate <= GATE_STEPS => {
+                        let gate = gate as f64 / GATE_STEPS as f64;
+                        self.articulation = self.articulation.clone().with_gate(gate);
+                    }
+                    _ => return Err(self.error(InvalidMmlKind::InvalidGate)),
+                },
+                '[' => self.parse_loop(rythm)?,
+                // Only marks where the last pass of a loop stops.
+                ':' => {}
+                ']' => {
+                    self.index = start;
+                    return Err(self.error(InvalidMmlKind::UnopenedLoop));
+                }
+                character if character.is_whitespace() => {}
+                _ => {
+                    self.index = start;
+                    return Err(self.error(InvalidMmlKind::UnexpectedCharacter));
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+// Appends a single channel of MML, like "t120 o4 l8 cdefg>c<", to the
+// rythm, with the root sound for every note.
+pub fn parse_channel<T>(
+    rythm: &mut Rythm<T>,
+    root_sound: &T,
+    channel: &str,
+) -> Result<(), InvalidMml>
+where
+    T: Into<Audio> + HasTone + Clone,
+{
+    let mut parser = MmlParser::new(channel, 0, root_sound);
+    let end = parser.characters.len();
+    return parser.parse_until(end, rythm);
+}
+
+// Channels are separated by ;, and each one gets a rythm of its own from
+// the builder. Tempos are in beats of the builder, quarter notes unless
+// it says otherwise.
+#[allow(dead_code)]
+pub fn parse<T>(
+    mml: &str,
+    root_sound: &T,
+    builder: RythmBuilder<T>,
+) -> Result<Vec<Rythm<T>>, InvalidMml>
+where
+    T: Into<Audio> + HasTone + Clone,
+{
+    let mut rythms = vec![];
+    let mut offset = 0;
+    for channel in mml.split(';') {
+        let mut rythm = builder.clone().finalize().map_err(|_| InvalidMml {
+            kind: InvalidMmlKind::InvalidRythm,
+            position: 0,
+        })?;
+        let mut parser = MmlParser::new(channel, offset, root_sound);
+        let end = parser.characters.len();
+        parser.parse_until(end, &mut rythm)?;
+        rythms.push(rythm);
+        offset += end + 1;
+    }
+    return Ok(rythms);
+}
+
+// Each channel makes a track, named "channel 1", "channel 2" and so on.
+#[allow(dead_code)]
+pub fn parse_song<T>(
+    mml: &str,
+    root_sound: &T,
+    builder: RythmBuilder<T>,
+) -> Result<Song, InvalidMml>
+where
+    T: Into<Audio> + HasTone + Clone,
+{
+    let mut song = Song::default();
+    for (index, rythm) in parse(mml, root_sound, builder)?.into_iter().enumerate() {
+        let name = format!("channel {}", index + 1);
+        song.add_track(Track::from_rythm(&name, rythm))
+            .expect("channel names are unique");
+    }
+    return Ok(song);
+}
@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::waves;
+
+    fn sine() -> waves::Sine {
+        return waves::SineBuilder::default().finalize().unwrap();
+    }
+
+    fn notes(mml: &str) -> Vec<(f64, f64)> {
+        let mut rythm = RythmBuilder::default().finalize().unwrap();
+        parse_channel(&mut rythm, &sine(), mml).unwrap();
+        let mut notes = vec![];
+        let mut position = 0.0;
+        for bar_beat_tick in rythm.hit_positions() {
+            let hit = rythm.get_hit_at(&bar_beat_tick).unwrap();
+            let start = rythm
+                .get_tempo_map()
+                .bar_beat_tick_to_position(&bar_beat_tick)
+                .unwrap();
+            assert!(start >= position);
+            position = start;
+            notes.push((start, hit.get_tone()));
+        }
+        return notes;
+    }
+
+    #[test]
+    fn test_notes_and_lengths() {
+        let notes = notes("o4 l8 c d4. r16 e-16 > c+ < a2&8");
+        let starts: Vec<f64> = notes.iter().map(|(start, _)| *start).collect();
+        assert_eq!(starts, vec![0.0, 0.125, 0.5625, 0.625, 0.75]);
+        let tones = [261.6256, 293.6648, 311.1270, 554.3653, 440.0];
+        for ((_, tone), expected) in notes.iter().zip(tones) {
+            assert!((tone - expected).abs() < 1e-3);
+        }
+        // A tied note can be repeated with either sharp.
+        let starts: Vec<f64> = self::notes("l4 c+&c#8 d")
+            .iter()
+            .map(|(start, _)| *start)
+            .collect();
+        assert_eq!(starts, vec![0.0, 0.375]);
+    }
+
+    #[test]
+    fn test_loops() {
+        assert_eq!(notes("l4 [c]3").len(), 3);
+        assert_eq!(notes("l4 [c [d]2 : e]3").len(), 3 * 3 + 2);
+        // Octave changes carry over from one pass to the next.
+        let tones: Vec<f64> = notes("o3 l4 [a>]2 a")
+            .iter()
+            .map(|(_, tone)| *tone)
+            .collect();
+        assert_eq!(tones, vec![220.0, 440.0, 880.0]);
+    }
+
+    #[test]
+    fn test_channels_and_errors() {
+        let builder = RythmBuilder::default();
+        let song = parse_song("t120 cdef; o2 c1", &sine(), builder.clone()).unwrap();
+        let audio: Audio = song.into();
+        assert!(!audio.get_samples().is_empty());
+        assert_eq!(
+            parse("c d; e [f", &sine(), builder.clone()),
+            Err(InvalidMml {
+                kind: InvalidMmlKind::UnclosedLoop,
+                position: 7,
+            })
+        );
+        assert_eq!(
+            parse("c v16", &sine(), builder.clone()).map(|rythms| rythms.len()),
+            Err(InvalidMml {
+                kind: InvalidMmlKind::InvalidVolume,
+                position: 5,
+            })
+        );
+        assert_eq!(
+            parse("c4&d8", &sine(), builder.clone()),
+            Err(InvalidMml {
+                kind: InvalidMmlKind::InvalidTie,
+                position: 3,
+            })
+        );
+        assert_eq!(
+            parse("c+4&c8", &sine(), builder),
+            Err(InvalidMml {
+                kind: InvalidMmlKind::InvalidTie,
+                position: 4,
+            })
+        );
+    }
+}
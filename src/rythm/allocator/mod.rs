@@ -0,0 +1,248 @@
+use builder_derive_macro::Setters;
+
+use crate::audio::Audio;
+use crate::utils::build::Build;
+
+use super::{Rythm, RythmBuilder};
+
+mod tests;
+
+// A note of a polyphonic arrangement. Times are in the same relative units
+// as the durations of Rythm hits, and higher priorities are kept first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteEvent<T: Into<Audio>> {
+    pub start: f64,
+    pub duration: f64,
+    pub priority: u8,
+    pub sound: T,
+}
+
+#[allow(dead_code)]
+impl<T: Into<Audio>> NoteEvent<T> {
+    pub fn new(start: f64, duration: f64, sound: T) -> Self {
+        return Self {
+            start,
+            duration,
+            priority: 0,
+            sound,
+        };
+    }
+
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        return self;
+    }
+}
+
+// Which playing note gives its voice up when every voice is busy. A note
+// can only be stolen by one with the same or a higher priority.
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum StealingPolicy {
+    NoStealing,
+    #[default]
+    Oldest,
+    LowestPriority,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum OverflowMode {
+    Fail,
+    #[default]
+    Drop,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidAllocationKind {
+    NoVoices,
+    NegativeTime,
+    VoiceOverflow(usize),
+    InvalidRythm,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidAllocation {
+    kind: InvalidAllocationKind,
+}
+
+// The per voice Rythms, together with the indices of the notes that were
+// dropped for lack of a voice and of the ones that were cut short.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoiceAllocation<T: Into<Audio>> {
+    rythms: Vec<Rythm<T>>,
+    dropped: Vec<usize>,
+    stolen: Vec<usize>,
+}
+
+#[allow(dead_code)]
+impl<T: Into<Audio>> VoiceAllocation<T> {
+    pub fn get_rythms(&self) -> &Vec<Rythm<T>> {
+        return &self.rythms;
+    }
+
+    pub fn into_rythms(self) -> Vec<Rythm<T>> {
+        return self.rythms;
+    }
+
+    pub fn get_dropped(&self) -> &Vec<usize> {
+        return &self.dropped;
+    }
+
+    pub fn get_stolen(&self) -> &Vec<usize> {
+        return &self.stolen;
+    }
+
+    pub fn fits(&self) -> bool {
+        return self.dropped.is_empty() && self.stolen.is_empty();
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct PlacedNote {
+    event: usize,
+    start: f64,
+    duration: f64,
+    priority: u8,
+}
+
+impl PlacedNote {
+    fn end(&self) -> f64 {
+        return self.start + self.duration;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct VoiceAllocator {
+    voices: usize,
+    stealing_policy: StealingPolicy,
+    overflow_mode: OverflowMode,
+}
+
+impl Default for VoiceAllocator {
+    fn default() -> Self {
+        return Self {
+            voices: 4,
+            stealing_policy: StealingPolicy::default(),
+            overflow_mode: OverflowMode::default(),
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl VoiceAllocator {
+    // Every voice gets a Rythm finalized from the given builder. Notes are
+    // placed in order of start time and, for notes starting together, of
+    // priority; a stolen note is cut where the new one starts.
+    pub fn allocate<T: Into<Audio> + Clone>(
+        &self,
+        events: Vec<NoteEvent<T>>,
+        rythm_builder: &RythmBuilder<T>,
+    ) -> Result<VoiceAllocation<T>, InvalidAllocation> {
+        if self.voices == 0 {
+            return Err(InvalidAllocation {
+                kind: InvalidAllocationKind::NoVoices,
+            });
+        }
+        if events.iter().any(|event| event.start < 0.0) {
+            return Err(InvalidAllocation {
+                kind: InvalidAllocationKind::NegativeTime,
+            });
+        }
+        let mut order: Vec<usize> = (0..events.len()).collect();
+        order.sort_by(|first, second| {
+            let (first, second) = (&events[*first], &events[*second]);
+            first
+                .start
+                .total_cmp(&second.start)
+                .then(second.priority.cmp(&first.priority))
+        });
+        let mut voices: Vec<Vec<PlacedNote>> = vec![vec![]; self.voices];
+        let mut dropped = vec![];
+        let mut stolen = vec![];
+        for index in order {
+            let event = &events[index];
+            let note = PlacedNote {
+                event: index,
+                start: event.start,
+                duration: event.duration.max(0.0),
+                priority: event.priority,
+            };
+            let free_voice = voices
+                .iter()
+                .position(|notes| notes.last().is_none_or(|last| last.end() <= event.start));
+            let voice = match free_voice {
+                Some(voice) => Some(voice),
+                None => self.victim(&voices, &note),
+            };
+            match voice {
+                Some(voice) => {
+                    if let Some(last) = voices[voice].last_mut()
+                        && last.end() > note.start
+                    {
+                        last.duration = note.start - last.start;
+                        stolen.push(last.event);
+                    }
+                    voices[voice].push(note);
+                }
+                None => {
+                    if self.overflow_mode == OverflowMode::Fail {
+                        return Err(InvalidAllocation {
+                            kind: InvalidAllocationKind::VoiceOverflow(index),
+                        });
+                    }
+                    dropped.push(index);
+                }
+            }
+        }
+        let mut rythms = vec![];
+        for notes in voices {
+            let mut rythm =
+                rythm_builder
+                    .clone()
+                    .finalize()
+                    .map_err(|_error| InvalidAllocation {
+                        kind: InvalidAllocationKind::InvalidRythm,
+                    })?;
+            let mut time = 0.0;
+            for note in notes {
+                if note.start > time {
+                    rythm.hit(time - note.start, events[note.event].sound.clone());
+                }
+                rythm.hit(note.duration, events[note.event].sound.clone());
+                time = note.end();
+            }
+            rythms.push(rythm);
+        }
+        return Ok(VoiceAllocation {
+            rythms,
+            dropped,
+            stolen,
+        });
+    }
+
+    // A note starting together with the new one would be cut to nothing, so
+    // only notes that already sounded can be stolen.
+    fn victim(&self, voices: &[Vec<PlacedNote>], note: &PlacedNote) -> Option<usize> {
+        let candidates = voices
+            .iter()
+            .enumerate()
+            .filter_map(|(voice, notes)| Some((voice, notes.last()?)))
+            .filter(|(_voice, playing)| {
+                playing.priority <= note.priority && playing.start < note.start
+            });
+        let victim = match self.stealing_policy {
+            StealingPolicy::NoStealing => None,
+            StealingPolicy::Oldest => {
+                candidates.min_by(|(_, first), (_, second)| first.start.total_cmp(&second.start))
+            }
+            StealingPolicy::LowestPriority => candidates.min_by(|(_, first), (_, second)| {
+                first
+                    .priority
+                    .cmp(&second.priority)
+                    .then(first.start.total_cmp(&second.start))
+            }),
+        };
+        return victim.map(|(voice, _playing)| voice);
+    }
+}
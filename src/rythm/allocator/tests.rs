@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::waves::{Pulse, PulseBuilder};
+
+    fn events(notes: &[(f64, f64, u8)]) -> Vec<NoteEvent<Pulse>> {
+        let pulse = PulseBuilder::default().finalize().unwrap();
+        return notes
+            .iter()
+            .map(|(start, duration, priority)| {
+                NoteEvent::new(*start, *duration, pulse.clone()).with_priority(*priority)
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_fitting_arrangement() {
+        let allocation = VoiceAllocator::default()
+            .with_voices(2)
+            .allocate(
+                events(&[(0.0, 1.0, 0), (0.0, 2.0, 0), (1.0, 1.0, 0)]),
+                &RythmBuilder::default(),
+            )
+            .unwrap();
+        assert!(allocation.fits());
+        let lengths: Vec<f64> = allocation.get_rythms().iter().map(Rythm::len).collect();
+        assert_eq!(lengths, vec![2.0, 2.0]);
+    }
+
+    #[test]
+    fn test_stealing() {
+        let notes = events(&[(0.0, 4.0, 1), (1.0, 4.0, 0), (2.0, 1.0, 0)]);
+        let allocation = VoiceAllocator::default()
+            .with_voices(2)
+            .with_stealing_policy(StealingPolicy::LowestPriority)
+            .allocate(notes.clone(), &RythmBuilder::default())
+            .unwrap();
+        assert_eq!(allocation.get_stolen(), &vec![1]);
+        assert_eq!(allocation.get_rythms()[1].len(), 3.0);
+        let allocation = VoiceAllocator::default()
+            .with_voices(2)
+            .with_stealing_policy(StealingPolicy::NoStealing)
+            .allocate(notes.clone(), &RythmBuilder::default())
+            .unwrap();
+        assert_eq!(allocation.get_dropped(), &vec![2]);
+        let allocation = VoiceAllocator::default()
+            .with_voices(2)
+            .with_stealing_policy(StealingPolicy::NoStealing)
+            .with_overflow_mode(OverflowMode::Fail)
+            .allocate(notes, &RythmBuilder::default());
+        assert_eq!(
+            allocation,
+            Err(InvalidAllocation {
+                kind: InvalidAllocationKind::VoiceOverflow(2)
+            })
+        );
+    }
+
+    #[test]
+    fn test_simultaneous_notes_are_not_stolen() {
+        let notes = events(&[(0.0, 2.0, 0), (0.0, 2.0, 0), (0.0, 2.0, 0)]);
+        for policy in [StealingPolicy::Oldest, StealingPolicy::LowestPriority] {
+            let allocation = VoiceAllocator::default()
+                .with_voices(2)
+                .with_stealing_policy(policy)
+                .allocate(notes.clone(), &RythmBuilder::default())
+                .unwrap();
+            assert!(allocation.get_stolen().is_empty());
+            assert_eq!(allocation.get_dropped(), &vec![2]);
+            let lengths: Vec<f64> = allocation.get_rythms().iter().map(Rythm::len).collect();
+            assert_eq!(lengths, vec![2.0, 2.0]);
+        }
+    }
+}
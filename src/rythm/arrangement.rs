@@ -0,0 +1,171 @@
+use crate::audio::Audio;
+use crate::utils::build::Build;
+use crate::waves::traits::has_tone::HasTone;
+
+use super::{Rythm, RythmBuilder};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidArrangementKind {
+    DuplicatePatternName,
+    UnknownPattern,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidArrangement {
+    kind: InvalidArrangementKind,
+}
+
+// An entry of the order list, as in trackers. A loop plays its body count
+// times, followed on each pass by the ending of that pass, so the second
+// ending follows the second pass. Passes past the last ending take it
+// again.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Order {
+    Pattern {
+        name: String,
+        semitones: i32,
+    },
+    Loop {
+        body: Vec<Order>,
+        count: usize,
+        endings: Vec<Vec<Order>>,
+    },
+}
+
+#[allow(dead_code)]
+impl Order {
+    pub fn pattern(name: &str) -> Self {
+        return Self::transposed(name, 0);
+    }
+
+    pub fn transposed(name: &str, semitones: i32) -> Self {
+        return Self::Pattern {
+            name: name.to_string(),
+            semitones,
+        };
+    }
+
+    pub fn repeat(body: Vec<Order>, count: usize) -> Self {
+        return Self::repeat_with_endings(body, count, vec![]);
+    }
+
+    pub fn repeat_with_endings(body: Vec<Order>, count: usize, endings: Vec<Vec<Order>>) -> Self {
+        return Self::Loop {
+            body,
+            count,
+            endings,
+        };
+    }
+}
+
+// Patterns are rythms of their own that an order list places one after
+// the other. Only their hits are used, so their tempo doesn't matter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arrangement<T: Into<Audio>> {
+    patterns: Vec<(String, Rythm<T>)>,
+    order: Vec<Order>,
+}
+
+impl<T: Into<Audio>> Default for Arrangement<T> {
+    fn default() -> Self {
+        return Self {
+            patterns: vec![],
+            order: vec![],
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl<T: Into<Audio> + Clone> Arrangement<T> {
+    pub fn add_pattern(&mut self, name: &str, pattern: Rythm<T>) -> Result<(), InvalidArrangement> {
+        if self.get_pattern(name).is_some() {
+            return Err(InvalidArrangement {
+                kind: InvalidArrangementKind::DuplicatePatternName,
+            });
+        }
+        self.patterns.push((name.to_string(), pattern));
+        return Ok(());
+    }
+
+    // Adds an empty pattern to fill in place.
+    pub fn new_pattern(&mut self, name: &str) -> Result<&mut Rythm<T>, InvalidArrangement> {
+        let pattern = RythmBuilder::default().finalize().expect("TODO");
+        self.add_pattern(name, pattern)?;
+        return Ok(&mut self.patterns.last_mut().expect("just added").1);
+    }
+
+    pub fn get_pattern(&self, name: &str) -> Option<&Rythm<T>> {
+        return self
+            .patterns
+            .iter()
+            .find(|(pattern_name, _)| pattern_name == name)
+            .map(|(_, pattern)| pattern);
+    }
+
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.order.push(order);
+        return self;
+    }
+
+    pub fn push_order(&mut self, order: Order) {
+        self.order.push(order);
+    }
+
+    fn validate_orders(&self, orders: &[Order]) -> Result<(), InvalidArrangement> {
+        for order in orders {
+            match order {
+                Order::Pattern { name, .. } => {
+                    if self.get_pattern(name).is_none() {
+                        return Err(InvalidArrangement {
+                            kind: InvalidArrangementKind::UnknownPattern,
+                        });
+                    }
+                }
+                Order::Loop { body, endings, .. } => {
+                    self.validate_orders(body)?;
+                    for ending in endings {
+                        self.validate_orders(ending)?;
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn play_orders(&self, orders: &[Order], rythm: &mut Rythm<T>)
+    where
+        T: HasTone,
+    {
+        for order in orders {
+            match order {
+                Order::Pattern { name, semitones } => {
+                    let pattern = self.get_pattern(name).expect("validated");
+                    rythm.play_pattern_transposed(pattern, *semitones);
+                }
+                Order::Loop {
+                    body,
+                    count,
+                    endings,
+                } => {
+                    for pass in 0..*count {
+                        self.play_orders(body, rythm);
+                        if let Some(ending) = endings.get(pass).or(endings.last()) {
+                            self.play_orders(ending, rythm);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Appends the whole order list to the rythm, which is left untouched
+    // when the list names a pattern that doesn't exist.
+    pub fn arrange_into(&self, rythm: &mut Rythm<T>) -> Result<(), InvalidArrangement>
+    where
+        T: HasTone,
+    {
+        self.validate_orders(&self.order)?;
+        self.play_orders(&self.order, rythm);
+        return Ok(());
+    }
+}
@@ -0,0 +1,28 @@
+// Semitones from the root of the chords named as in lead sheets, where
+// the empty name is the major triad.
+pub fn chord_semitones(name: &str) -> Option<Vec<i32>> {
+    let semitones: &[i32] = match name {
+        "" | "maj" | "M" => &[0, 4, 7],
+        "m" | "min" => &[0, 3, 7],
+        "dim" => &[0, 3, 6],
+        "aug" | "+" => &[0, 4, 8],
+        "sus2" => &[0, 2, 7],
+        "sus4" => &[0, 5, 7],
+        "5" => &[0, 7],
+        "6" => &[0, 4, 7, 9],
+        "m6" => &[0, 3, 7, 9],
+        "7" => &[0, 4, 7, 10],
+        "maj7" | "M7" => &[0, 4, 7, 11],
+        "m7" => &[0, 3, 7, 10],
+        "mM7" => &[0, 3, 7, 11],
+        "dim7" => &[0, 3, 6, 9],
+        "m7b5" => &[0, 3, 6, 10],
+        "aug7" => &[0, 4, 8, 10],
+        "add9" => &[0, 4, 7, 14],
+        "9" => &[0, 4, 7, 10, 14],
+        "maj9" => &[0, 4, 7, 11, 14],
+        "m9" => &[0, 3, 7, 10, 14],
+        _ => return None,
+    };
+    return Some(semitones.to_vec());
+}
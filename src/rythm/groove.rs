@@ -0,0 +1,160 @@
+use rand::RngCore;
+use rand::rngs::SmallRng;
+
+use super::POSITION_TOLERANCE;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SwingNote {
+    #[default]
+    EighthNote,
+    SixteenthNote,
+}
+
+impl SwingNote {
+    fn length(&self) -> f64 {
+        match self {
+            Self::EighthNote => 1.0 / 8.0,
+            Self::SixteenthNote => 1.0 / 16.0,
+        }
+    }
+}
+
+// The amount is the share of each pair of notes given to the first one:
+// 0.5 is straight, 2/3 a triplet feel and 0.75 a dotted one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Swing {
+    pub amount: f64,
+    pub note: SwingNote,
+}
+
+#[allow(dead_code)]
+impl Swing {
+    pub fn new(amount: f64, note: SwingNote) -> Self {
+        return Self { amount, note };
+    }
+
+    pub fn from_percentage(percentage: f64, note: SwingNote) -> Self {
+        return Self::new(percentage / 100.0, note);
+    }
+
+    pub fn is_valid(&self) -> bool {
+        return self.amount > 0.0 && self.amount < 1.0;
+    }
+
+    // Stretches the first half of each pair and squeezes the second, so
+    // positions on the beat stay where they are.
+    fn apply(&self, position: f64) -> f64 {
+        let length = self.note.length();
+        let pair_start = (position / (2.0 * length)).floor() * 2.0 * length;
+        let offset = position - pair_start;
+        let swung_length = 2.0 * length * self.amount;
+        if offset < length {
+            return pair_start + offset / length * swung_length;
+        }
+        let second_length = 2.0 * length - swung_length;
+        return pair_start + swung_length + (offset - length) / length * second_length;
+    }
+}
+
+// Offsets for each step of a repeating grid, as in drum machines: timing
+// offsets are fractions of a step and velocity offsets fractions of the
+// amplitude. Only hits that start on a step are moved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrooveTemplate {
+    pub step: f64,
+    pub timing_offsets: Vec<f64>,
+    pub velocity_offsets: Vec<f64>,
+}
+
+#[allow(dead_code)]
+impl GrooveTemplate {
+    pub fn new(step: f64, timing_offsets: Vec<f64>, velocity_offsets: Vec<f64>) -> Self {
+        return Self {
+            step,
+            timing_offsets,
+            velocity_offsets,
+        };
+    }
+
+    pub fn is_valid(&self) -> bool {
+        return self.step > 0.0;
+    }
+
+    // The timing offset, in whole notes, and the gain of a hit.
+    fn offsets_at(&self, position: f64) -> (f64, f64) {
+        let step = (position / self.step).round();
+        if (position - step * self.step).abs() > POSITION_TOLERANCE {
+            return (0.0, 1.0);
+        }
+        let offset_at = |offsets: &Vec<f64>| match offsets.len() {
+            0 => 0.0,
+            length => offsets[step as usize % length],
+        };
+        let timing_offset = offset_at(&self.timing_offsets) * self.step;
+        return (timing_offset, 1.0 + offset_at(&self.velocity_offsets));
+    }
+}
+
+// Hits move by up to timing_ms either way and their amplitude by up to
+// the given fraction, the same way for the same seed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Humanize {
+    pub timing_ms: f64,
+    pub amplitude: f64,
+    pub seed: u64,
+}
+
+#[allow(dead_code)]
+impl Humanize {
+    pub fn new(timing_ms: f64, amplitude: f64, seed: u64) -> Self {
+        return Self {
+            timing_ms,
+            amplitude,
+            seed,
+        };
+    }
+
+    pub fn is_valid(&self) -> bool {
+        return self.timing_ms >= 0.0 && (0.0..=1.0).contains(&self.amplitude);
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Groove {
+    pub swing: Option<Swing>,
+    pub template: Option<GrooveTemplate>,
+    pub humanize: Option<Humanize>,
+}
+
+impl Groove {
+    pub fn is_valid(&self) -> bool {
+        return self.swing.as_ref().is_none_or(Swing::is_valid)
+            && self.template.as_ref().is_none_or(GrooveTemplate::is_valid)
+            && self.humanize.as_ref().is_none_or(Humanize::is_valid);
+    }
+
+    // Where a position of the rythm is really played, in whole notes.
+    pub fn swing_position(&self, position: f64) -> f64 {
+        return match &self.swing {
+            Some(swing) => swing.apply(position),
+            None => position,
+        };
+    }
+
+    // The timing offset, in whole notes and in milliseconds, and the gain
+    // of a hit starting at the given position.
+    pub fn hit_offsets(&self, position: f64, rng: &mut SmallRng) -> (f64, f64, f64) {
+        let (timing_offset, mut gain) = match &self.template {
+            Some(template) => template.offsets_at(position),
+            None => (0.0, 1.0),
+        };
+        let mut timing_offset_ms = 0.0;
+        if let Some(humanize) = &self.humanize {
+            let mut bipolar = || 2.0 * (rng.next_u32() as f64 / u32::MAX as f64) - 1.0;
+            timing_offset_ms = humanize.timing_ms * bipolar();
+            gain *= 1.0 + humanize.amplitude * bipolar();
+        }
+        return (timing_offset, timing_offset_ms, gain);
+    }
+}
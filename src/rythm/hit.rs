@@ -1,10 +1,14 @@
+use builder_derive_macro::Setters;
+
 use crate::audio::Audio;
+use crate::audio::effect::Effect;
 
 #[allow(dead_code)]
 #[derive(Clone, Debug, PartialEq)]
 pub enum RythmElement<T: Into<Audio>> {
     Rest(Rest),
     Hit(Hit<T>),
+    Chord(Chord<T>),
 }
 
 impl<T: Into<Audio>> RythmElement<T> {
@@ -12,13 +16,41 @@ impl<T: Into<Audio>> RythmElement<T> {
         match self {
             Self::Rest(rest) => rest.relative_duration,
             Self::Hit(hit) => hit.relative_duration,
+            Self::Chord(chord) => chord.relative_duration,
         }
     }
 
-    pub fn wave(self) -> Option<T> {
+    pub fn first_wave(&self) -> Option<&T> {
         match self {
             Self::Rest(_rest) => None,
-            Self::Hit(hit) => Some(hit.wave),
+            Self::Hit(hit) => Some(&hit.wave),
+            Self::Chord(chord) => chord.notes.first().map(|note| &note.wave),
+        }
+    }
+
+    // Every hit of the element, which can sound longer or shorter than
+    // the element itself.
+    pub fn sounds(self) -> Vec<Hit<T>> {
+        match self {
+            Self::Rest(_rest) => vec![],
+            Self::Hit(hit) => vec![hit],
+            Self::Chord(chord) => chord.notes,
+        }
+    }
+
+    // The same element with every wave passed through the given function.
+    pub fn map_waves(self, mut map: impl FnMut(T) -> T) -> Self {
+        match self {
+            Self::Rest(rest) => Self::Rest(rest),
+            Self::Hit(hit) => Self::Hit(hit.map_wave(&mut map)),
+            Self::Chord(chord) => Self::Chord(Chord {
+                relative_duration: chord.relative_duration,
+                notes: chord
+                    .notes
+                    .into_iter()
+                    .map(|note| note.map_wave(&mut map))
+                    .collect(),
+            }),
         }
     }
 }
@@ -28,8 +60,92 @@ pub struct Rest {
     pub relative_duration: f64,
 }
 
+// How a hit is played. The velocity scales its amplitude, the gate how
+// long it is held, under 1.0 for staccato and over it for legato, and the
+// filter replaces the decay of the rythm over the time the hit is held.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct Articulation {
+    velocity: f64,
+    gate: f64,
+    filter: Option<Effect>,
+}
+
+impl Default for Articulation {
+    fn default() -> Self {
+        return Self {
+            velocity: 1.0,
+            gate: 1.0,
+            filter: None,
+        };
+    }
+}
+
+// Without a held duration, a hit sounds until the next element starts.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Hit<T: Into<Audio>> {
     pub relative_duration: f64,
+    pub held_duration: Option<f64>,
+    pub articulation: Articulation,
     pub wave: T,
 }
+
+impl<T: Into<Audio>> Hit<T> {
+    pub fn held_duration(&self) -> f64 {
+        let held_duration = self.held_duration.unwrap_or(self.relative_duration);
+        return held_duration * self.articulation.gate;
+    }
+
+    fn map_wave(self, map: &mut impl FnMut(T) -> T) -> Self {
+        return Self {
+            wave: map(self.wave),
+            ..self
+        };
+    }
+}
+
+// A hit as written, for exports: where it starts and how long it is held,
+// in whole notes from the start of the rythm, and how hard it is played.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledHit<'a, T> {
+    pub position: f64,
+    pub held_duration: f64,
+    pub velocity: f64,
+    pub wave: &'a T,
+}
+
+// What hits_with_frequency takes for each note: a duration and a note,
+// with an optional articulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteHit<'a> {
+    pub duration: f64,
+    pub note: &'a str,
+    pub articulation: Articulation,
+}
+
+impl<'a> From<(f64, &'a str)> for NoteHit<'a> {
+    fn from((duration, note): (f64, &'a str)) -> Self {
+        return Self {
+            duration,
+            note,
+            articulation: Articulation::default(),
+        };
+    }
+}
+
+impl<'a> From<(f64, &'a str, Articulation)> for NoteHit<'a> {
+    fn from((duration, note, articulation): (f64, &'a str, Articulation)) -> Self {
+        return Self {
+            duration,
+            note,
+            articulation,
+        };
+    }
+}
+
+// Notes that start together, each with its own hit for how long it
+// sounds, while the next element starts after relative_duration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chord<T: Into<Audio>> {
+    pub relative_duration: f64,
+    pub notes: Vec<Hit<T>>,
+}
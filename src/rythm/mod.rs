@@ -1,12 +1,34 @@
 use std::collections::VecDeque;
 
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
 use crate::audio::{basic_filters::Decay, Audio};
+use crate::instrument::{Arpeggio, Instrument, MacroTarget};
 use crate::time::has_duration::HasDuration;
 use crate::utils::build::Build;
-use crate::waves::traits::has_tone::HasTone;
+use crate::waves::traits::has_tone::{HasTone, Interval, SEMI_TONE_FACTOR};
 
+mod allocator;
+#[allow(unused_imports)]
+pub use allocator::*;
+mod arrangement;
+#[allow(unused_imports)]
+pub use arrangement::*;
+mod chord;
+#[allow(unused_imports)]
+pub use chord::*;
+mod groove;
+#[allow(unused_imports)]
+pub use groove::*;
 mod hit;
-use hit::{Hit, Rest, RythmElement};
+#[allow(unused_imports)]
+pub use hit::{Articulation, NoteHit, ScheduledHit};
+use hit::{Chord, Hit, Rest, RythmElement};
+mod tempo_map;
+use tempo_map::POSITION_TOLERANCE;
+#[allow(unused_imports)]
+pub use tempo_map::*;
 mod tests;
 
 #[allow(dead_code)]
@@ -24,7 +46,22 @@ pub enum Beat {
 }
 
 impl Beat {
-    fn duration_factor(&self) -> f64 {
+    // The beat of a note length as written in scores and MML, where 8 is
+    // an eighth note.
+    pub fn from_note_length(length: u32) -> Self {
+        match length {
+            1 => Self::WholeNote,
+            2 => Self::HalfNote,
+            4 => Self::QuarterNote,
+            8 => Self::EigthNote,
+            12 => Self::EightNoteTriplet,
+            16 => Self::SixteenthNote,
+            32 => Self::ThirtySecondNote,
+            length => Self::Custom(1.0 / length as f64),
+        }
+    }
+
+    pub fn duration_factor(&self) -> f64 {
         match self {
             Self::WholeNote => 1.0,
             Self::HalfNote => 0.5,
@@ -42,9 +79,11 @@ impl Beat {
 pub struct RythmBuilder<T: Into<Audio> + Clone> {
     tempo_bpm: f64,
     beat_type: Beat,
+    time_signature: TimeSignature,
     rythm: VecDeque<RythmElement<T>>,
     clamp: bool,
     decay: Option<Decay>,
+    groove: Groove,
 }
 
 #[allow(dead_code)]
@@ -67,6 +106,15 @@ impl<T: Into<Audio> + Clone> RythmBuilder<T> {
         return self;
     }
 
+    pub fn get_time_signature(&self) -> &TimeSignature {
+        return &self.time_signature;
+    }
+
+    pub fn with_time_signature(mut self, time_signature: TimeSignature) -> Self {
+        self.time_signature = time_signature;
+        return self;
+    }
+
     pub fn get_clamp(&self) -> bool {
         return self.clamp;
     }
@@ -80,6 +128,30 @@ impl<T: Into<Audio> + Clone> RythmBuilder<T> {
         self.decay = Some(decay);
         return self;
     }
+
+    pub fn get_groove(&self) -> &Groove {
+        return &self.groove;
+    }
+
+    pub fn with_groove(mut self, groove: Groove) -> Self {
+        self.groove = groove;
+        return self;
+    }
+
+    pub fn with_swing(mut self, swing: Swing) -> Self {
+        self.groove.swing = Some(swing);
+        return self;
+    }
+
+    pub fn with_groove_template(mut self, template: GrooveTemplate) -> Self {
+        self.groove.template = Some(template);
+        return self;
+    }
+
+    pub fn with_humanize(mut self, humanize: Humanize) -> Self {
+        self.groove.humanize = Some(humanize);
+        return self;
+    }
 }
 
 impl<T: Into<Audio> + Clone> Default for RythmBuilder<T> {
@@ -87,9 +159,11 @@ impl<T: Into<Audio> + Clone> Default for RythmBuilder<T> {
         return Self {
             tempo_bpm: 60.0,
             beat_type: Beat::default(),
+            time_signature: TimeSignature::default(),
             rythm: VecDeque::new(),
             clamp: false,
             decay: None,
+            groove: Groove::default(),
         };
     }
 }
@@ -104,6 +178,12 @@ impl<T: Into<Audio> + Clone> Build for RythmBuilder<T> {
         if tempo < 0.0 || tempo.is_nan() || tempo.is_infinite() {
             possible_errors.push(());
         }
+        if !self.time_signature.is_valid() {
+            possible_errors.push(());
+        }
+        if !self.groove.is_valid() {
+            possible_errors.push(());
+        }
         if !possible_errors.is_empty() {
             return Err(possible_errors);
         };
@@ -115,24 +195,31 @@ impl<T: Into<Audio> + Clone> Build for RythmBuilder<T> {
             return Err(error[0].clone());
         }
         return Ok(Rythm {
-            tempo_bpm: self.tempo_bpm,
-            beat_type: self.beat_type,
-            hit_start_time_ms: 0.0,
+            tempo_map: TempoMap::new(self.tempo_bpm, self.beat_type, self.time_signature),
+            hit_start_position: 0.0,
             rythm: VecDeque::new(),
             clamp: false,
             decay: self.decay,
+            rng: SmallRng::seed_from_u64(
+                self.groove
+                    .humanize
+                    .as_ref()
+                    .map_or(0, |humanize| humanize.seed),
+            ),
+            groove: self.groove,
         });
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Rythm<T: Into<Audio>> {
-    tempo_bpm: f64,
-    beat_type: Beat,
-    hit_start_time_ms: f64,
+    tempo_map: TempoMap,
+    hit_start_position: f64,
     rythm: VecDeque<RythmElement<T>>,
     clamp: bool,
     decay: Option<Decay>,
+    groove: Groove,
+    rng: SmallRng,
 }
 
 macro_rules! hit {
@@ -161,21 +248,155 @@ impl<T: Into<Audio>> Rythm<T> {
         } else if duration > 0.0 {
             self.rythm.push_back(RythmElement::Hit(Hit {
                 relative_duration: duration,
+                held_duration: None,
+                articulation: Articulation::default(),
                 wave: sound,
             }));
         }
     }
 
-    pub fn hits_with_frequency(&mut self, root_sound: T, notes: &[(f64, &str)])
+    // Legato: the sound goes on for held_duration even though the next
+    // hit starts after duration.
+    pub fn hit_held(&mut self, duration: f64, held_duration: f64, sound: T) {
+        if duration > 0.0 && held_duration > 0.0 {
+            self.rythm.push_back(RythmElement::Hit(Hit {
+                relative_duration: duration,
+                held_duration: Some(held_duration),
+                articulation: Articulation::default(),
+                wave: sound,
+            }));
+        }
+    }
+
+    pub fn hit_articulated(&mut self, duration: f64, sound: T, articulation: Articulation) {
+        if duration < 0.0 {
+            self.hit(duration, sound);
+        } else if duration > 0.0 {
+            self.rythm.push_back(RythmElement::Hit(Hit {
+                relative_duration: duration,
+                held_duration: None,
+                articulation,
+                wave: sound,
+            }));
+        }
+    }
+
+    // Tied durations make a single hit, as a tie between notes does.
+    pub fn hit_tied(&mut self, durations: &[f64], sound: T) {
+        let duration: f64 = durations.iter().map(|duration| duration.abs()).sum();
+        self.hit(duration, sound);
+    }
+
+    // Sounds that start together, each held for its own duration.
+    pub fn hit_together(&mut self, duration: f64, sounds: Vec<(f64, T)>) {
+        let sounds = sounds
+            .into_iter()
+            .map(|(held_duration, sound)| (held_duration, sound, Articulation::default()))
+            .collect();
+        self.hit_together_articulated(duration, sounds);
+    }
+
+    pub fn hit_together_articulated(
+        &mut self,
+        duration: f64,
+        sounds: Vec<(f64, T, Articulation)>,
+    ) {
+        let notes: Vec<_> = sounds
+            .into_iter()
+            .filter(|(held_duration, _, _)| *held_duration > 0.0)
+            .map(|(held_duration, sound, articulation)| Hit {
+                relative_duration: held_duration,
+                held_duration: None,
+                articulation,
+                wave: sound,
+            })
+            .collect();
+        if duration <= 0.0 {
+            return;
+        }
+        if notes.is_empty() {
+            self.rythm.push_back(RythmElement::Rest(Rest {
+                relative_duration: duration,
+            }));
+            return;
+        }
+        self.rythm.push_back(RythmElement::Chord(Chord {
+            relative_duration: duration,
+            notes,
+        }));
+    }
+
+    // Like hits_with_frequency, a note that can't be parsed gives a rest.
+    pub fn chord_with_semitones(
+        &mut self,
+        duration: f64,
+        root_sound: T,
+        note: &str,
+        semitones: &[i32],
+    ) where
+        T: HasTone + Clone,
+    {
+        let mut root_sound = root_sound;
+        if root_sound.parse_and_set_tone(note).is_err() {
+            self.hit(-duration.abs(), root_sound);
+            return;
+        }
+        let sounds = semitones
+            .iter()
+            .map(|semitones| {
+                let mut sound = root_sound.clone();
+                sound.set_tone(root_sound.get_tone() * SEMI_TONE_FACTOR.powf(*semitones as f64));
+                (duration, sound)
+            })
+            .collect();
+        self.hit_together(duration, sounds);
+    }
+
+    pub fn chord_with_intervals(
+        &mut self,
+        duration: f64,
+        root_sound: T,
+        note: &str,
+        intervals: &[Interval],
+    ) where
+        T: HasTone + Clone,
+    {
+        let semitones: Vec<i32> = intervals
+            .iter()
+            .map(|interval| interval.to_semitones() as i32)
+            .collect();
+        self.chord_with_semitones(duration, root_sound, note, &semitones);
+    }
+
+    // Chords by name, as in lead sheets: (duration, root note, chord name)
+    // like (0.5, "a3", "m7"). Unknown chords give rests.
+    pub fn chords_with_frequency(&mut self, root_sound: T, chords: &[(f64, &str, &str)])
     where
         T: HasTone + Clone,
     {
-        for (duration, note) in notes {
+        for (duration, note, name) in chords {
+            match chord_semitones(name) {
+                Some(semitones) => {
+                    self.chord_with_semitones(*duration, root_sound.clone(), note, &semitones)
+                }
+                None => self.hit(-(*duration).abs(), root_sound.clone()),
+            }
+        }
+    }
+
+    // Notes are (duration, note) or (duration, note, articulation).
+    pub fn hits_with_frequency<'a, N>(&mut self, root_sound: T, notes: &[N])
+    where
+        T: HasTone + Clone,
+        N: Into<NoteHit<'a>> + Clone,
+    {
+        for note in notes {
+            let note: NoteHit = note.clone().into();
             let mut cloned_sound = root_sound.clone();
-            let parse_result = cloned_sound.parse_and_set_tone(note);
+            let parse_result = cloned_sound.parse_and_set_tone(note.note);
             match parse_result {
-                Ok(()) => self.hit(*duration, cloned_sound),
-                Err(()) => self.hit(-(*duration).abs(), cloned_sound),
+                Ok(()) => self.hit_articulated(note.duration, cloned_sound, note.articulation),
+                Err(()) => self.hit(-note.duration.abs(), cloned_sound),
             }
         }
     }
@@ -205,6 +426,28 @@ impl<T: Into<Audio>> Rythm<T> {
 
 // impl<T: Into<Audio>> Rythm<T> {}
 
+#[allow(dead_code)]
+impl<W: MacroTarget + Clone> Rythm<Instrument<W>> {
+    // Each hit arpeggiates its note through its own offsets, in semitones,
+    // so a single track can play a whole chord progression.
+    pub fn hits_with_arpeggio(
+        &mut self,
+        root_sound: Instrument<W>,
+        rate: f64,
+        chords: &[(f64, &str, &[i32])],
+    ) {
+        for (duration, note, offsets) in chords {
+            let mut cloned_sound = root_sound.clone();
+            cloned_sound.set_arpeggio(Some(Arpeggio::new(offsets.to_vec(), rate)));
+            let parse_result = cloned_sound.parse_and_set_tone(note);
+            match parse_result {
+                Ok(()) => self.hit(*duration, cloned_sound),
+                Err(()) => self.hit(-(*duration).abs(), cloned_sound),
+            }
+        }
+    }
+}
+
 #[allow(dead_code)]
 impl<T: Into<Audio> + Clone> Rythm<T> {
     pub fn bis(&mut self, repetitions: usize) {
@@ -215,6 +458,36 @@ impl<T: Into<Audio> + Clone> Rythm<T> {
         }
     }
 
+    // Only the elements of the pattern are played, at the tempo of this
+    // rythm.
+    pub fn play_pattern(&mut self, pattern: &Rythm<T>) {
+        self.rythm.extend(pattern.rythm.iter().cloned());
+    }
+
+    pub fn play_pattern_transposed(&mut self, pattern: &Rythm<T>, semitones: i32)
+    where
+        T: HasTone,
+    {
+        let factor = SEMI_TONE_FACTOR.powf(semitones as f64);
+        let transposed = pattern.rythm.iter().cloned().map(|element| {
+            element.map_waves(|mut wave| {
+                wave.set_tone(wave.get_tone() * factor);
+                return wave;
+            })
+        });
+        self.rythm.extend(transposed);
+    }
+}
+
+// Bar:beat:tick addressing goes through the tempo map, and positions
+// are counted from the start of the rythm, including the hits it already
+// played.
+#[allow(dead_code)]
+impl<T: Into<Audio>> Rythm<T> {
+    pub fn get_tempo_map(&self) -> &TempoMap {
+        return &self.tempo_map;
+    }
+
     pub fn len(&self) -> f64 {
         self.rythm
             .iter()
@@ -222,15 +495,118 @@ impl<T: Into<Audio> + Clone> Rythm<T> {
             .reduce(std::ops::Add::add)
             .unwrap_or(0.0)
     }
-}
 
-#[allow(dead_code)]
-impl<T: Into<Audio>> Rythm<T> {
-    fn hit_duration_ms(&self, hit: &RythmElement<T>) -> f64 {
-        let ms_per_min = 60.0 * 1000.0;
-        let whole_notes_per_minute = self.tempo_bpm * self.beat_type.duration_factor();
-        let whole_note_duration_milliseconds = ms_per_min * (1.0 / whole_notes_per_minute);
-        whole_note_duration_milliseconds * hit.relative_duration()
+    pub fn set_tempo_at(
+        &mut self,
+        at: &BarBeatTick,
+        tempo_bpm: f64,
+        ramp: TempoRamp,
+    ) -> Result<(), InvalidTiming> {
+        let position = self.tempo_map.bar_beat_tick_to_position(at)?;
+        return self.tempo_map.set_tempo_at(position, tempo_bpm, ramp);
+    }
+
+    pub fn set_time_signature_at(
+        &mut self,
+        bar: u32,
+        time_signature: TimeSignature,
+    ) -> Result<(), InvalidTiming> {
+        return self.tempo_map.set_time_signature_at(bar, time_signature);
+    }
+
+    // Replaces every tempo and time signature change.
+    pub fn set_tempo_map(&mut self, tempo_map: TempoMap) {
+        self.tempo_map = tempo_map;
+    }
+
+    // Changes the tempo from the end of the rythm on.
+    pub fn set_tempo(&mut self, tempo_bpm: f64) -> Result<(), InvalidTiming> {
+        let position = self.end_position();
+        return self
+            .tempo_map
+            .set_tempo_at(position, tempo_bpm, TempoRamp::Instant);
+    }
+
+    fn end_position(&self) -> f64 {
+        let length: f64 = self
+            .rythm
+            .iter()
+            .map(|element| element.relative_duration())
+            .sum();
+        return self.hit_start_position + length;
+    }
+
+    // Rests up to the given position before hitting, which can't be before
+    // the end of the rythm.
+    pub fn hit_at(
+        &mut self,
+        at: &BarBeatTick,
+        duration: f64,
+        sound: T,
+    ) -> Result<(), InvalidTiming> {
+        let position = self.tempo_map.bar_beat_tick_to_position(at)?;
+        let gap = position - self.end_position();
+        if gap < -POSITION_TOLERANCE {
+            return Err(InvalidTiming {
+                kind: InvalidTimingKind::PositionInThePast,
+            });
+        }
+        if gap > POSITION_TOLERANCE {
+            self.rythm.push_back(RythmElement::Rest(Rest {
+                relative_duration: gap,
+            }));
+        }
+        self.hit(duration.abs(), sound);
+        return Ok(());
+    }
+
+    pub fn hit_positions(&self) -> Vec<BarBeatTick> {
+        let mut position = self.hit_start_position;
+        let mut positions = vec![];
+        for element in self.rythm.iter() {
+            if element.first_wave().is_some() {
+                positions.push(self.tempo_map.position_to_bar_beat_tick(position));
+            }
+            position += element.relative_duration();
+        }
+        return positions;
+    }
+
+    // Every hit left to play, chords note by note, without the groove.
+    pub fn scheduled_hits(&self) -> Vec<ScheduledHit<'_, T>> {
+        let mut position = self.hit_start_position;
+        let mut hits = vec![];
+        for element in self.rythm.iter() {
+            let notes = match element {
+                RythmElement::Rest(_rest) => vec![],
+                RythmElement::Hit(hit) => vec![hit],
+                RythmElement::Chord(chord) => chord.notes.iter().collect(),
+            };
+            for note in notes {
+                hits.push(ScheduledHit {
+                    position,
+                    held_duration: note.held_duration(),
+                    velocity: *note.articulation.get_velocity(),
+                    wave: &note.wave,
+                });
+            }
+            position += element.relative_duration();
+        }
+        return hits;
+    }
+
+    // The hit sounding at the given position, if any.
+    pub fn get_hit_at(&self, at: &BarBeatTick) -> Option<&T> {
+        let target = self.tempo_map.bar_beat_tick_to_position(at).ok()? + POSITION_TOLERANCE;
+        let mut position = self.hit_start_position;
+        for element in self.rythm.iter() {
+            let end = position + element.relative_duration();
+            if target >= position && target < end {
+                return element.first_wave();
+            }
+            position = end;
+        }
+        return None;
     }
 }
 
@@ -239,22 +615,48 @@ impl<T: Into<Audio>> Iterator for Rythm<T> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let hit = self.rythm.pop_front()?;
-        let duration = Self::hit_duration_ms(self, &hit);
-        let hit_start_time_ms = self.hit_start_time_ms;
-        self.hit_start_time_ms += duration;
-        let wave = hit.wave();
-        match wave {
-            Some(wave) => {
-                let mut audio = wave.into();
-                if let Some(decay) = &self.decay {
-                    audio = audio.filter_audio(decay.clone());
-                }
-                audio.set_duration_ms(duration);
-                audio.milliseconds_left_pad(hit_start_time_ms);
-                Some(audio)
-            }
-            None => self.next(),
+        let hit_start_position = self.hit_start_position;
+        self.hit_start_position += hit.relative_duration();
+        let sounds = hit.sounds();
+        if sounds.is_empty() {
+            return self.next();
         }
+        // Swing moves the whole hit, while groove templates only move its
+        // start and humanization shifts it as a block.
+        let (timing_offset, timing_offset_ms, gain) =
+            self.groove.hit_offsets(hit_start_position, &mut self.rng);
+        let time_ms = |position: f64| {
+            let position = self.groove.swing_position(position);
+            return self.tempo_map.position_to_ms(position) + timing_offset_ms;
+        };
+        let start_position = self.groove.swing_position(hit_start_position) + timing_offset;
+        let hit_start_time_ms =
+            (self.tempo_map.position_to_ms(start_position) + timing_offset_ms).max(0.0);
+        // A filter of the hit replaces the decay and is applied once the
+        // hit is cut to the time it is held, so envelopes release in time.
+        let audios = sounds.into_iter().map(|hit| {
+            let held_duration = hit.held_duration();
+            let articulation = hit.articulation;
+            let mut audio = hit.wave.into();
+            if articulation.get_filter().is_none()
+                && let Some(decay) = &self.decay
+            {
+                audio = audio.filter_audio(decay.clone());
+            }
+            let end_time_ms = time_ms(hit_start_position + held_duration);
+            audio.set_duration_ms((end_time_ms - hit_start_time_ms).max(0.0));
+            if let Some(filter) = articulation.get_filter() {
+                audio = audio.filter_audio(filter.clone());
+            }
+            let gain = gain * articulation.get_velocity();
+            if gain != 1.0 {
+                audio = audio * gain;
+            }
+            audio
+        });
+        let mut audio = audios.reduce(|acc, audio| acc / audio)?;
+        audio.milliseconds_left_pad(hit_start_time_ms);
+        Some(audio)
     }
 }
 
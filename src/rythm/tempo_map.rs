@@ -0,0 +1,334 @@
+use super::Beat;
+
+pub const TICKS_PER_BEAT: u32 = 960;
+// Keeps positions that are sums of note lengths from landing just before
+// the bar or tick they should fall on.
+pub(super) const POSITION_TOLERANCE: f64 = 1e-6;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidTimingKind {
+    NonPositiveTempo,
+    InvalidTimeSignature,
+    InvalidBarBeatTick,
+    PositionInThePast,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidTiming {
+    pub(super) kind: InvalidTimingKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeSignature {
+    pub beats_per_bar: u32,
+    pub beat_value: u32,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        return Self {
+            beats_per_bar: 4,
+            beat_value: 4,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl TimeSignature {
+    pub fn new(beats_per_bar: u32, beat_value: u32) -> Self {
+        return Self {
+            beats_per_bar,
+            beat_value,
+        };
+    }
+
+    pub fn is_valid(&self) -> bool {
+        return self.beats_per_bar > 0 && self.beat_value > 0;
+    }
+
+    // Lengths are in whole notes, like the relative durations of hits.
+    fn beat_length(&self) -> f64 {
+        return 1.0 / self.beat_value as f64;
+    }
+
+    fn bar_length(&self) -> f64 {
+        return self.beats_per_bar as f64 * self.beat_length();
+    }
+}
+
+// Bars and beats count from 1, as sequencers show them, and ticks from 0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BarBeatTick {
+    pub bar: u32,
+    pub beat: u32,
+    pub tick: u32,
+}
+
+#[allow(dead_code)]
+impl BarBeatTick {
+    pub fn new(bar: u32, beat: u32, tick: u32) -> Self {
+        return Self { bar, beat, tick };
+    }
+
+    // Reads "bar:beat:tick", where the tick can be left out.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().split(':');
+        let bar = parts.next()?.trim().parse().ok()?;
+        let beat = parts.next()?.trim().parse().ok()?;
+        let tick = match parts.next() {
+            Some(tick) => tick.trim().parse().ok()?,
+            None => 0,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        return Some(Self::new(bar, beat, tick));
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum TempoRamp {
+    // The tempo jumps to the new one at the change.
+    #[default]
+    Instant,
+    // The tempo goes linearly from the previous change to this one, for
+    // accelerandos and ritardandos.
+    Linear,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct TempoChange {
+    position: f64,
+    tempo_bpm: f64,
+    ramp: TempoRamp,
+}
+
+// Positions are in whole notes from the start and tempos in beats of
+// beat_type per minute. There is always a tempo at position 0 and a time
+// signature at bar 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TempoMap {
+    beat_type: Beat,
+    tempo_changes: Vec<TempoChange>,
+    time_signatures: Vec<(u32, TimeSignature)>,
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        return Self::new(60.0, Beat::default(), TimeSignature::default());
+    }
+}
+
+#[allow(dead_code)]
+impl TempoMap {
+    pub fn new(tempo_bpm: f64, beat_type: Beat, time_signature: TimeSignature) -> Self {
+        return Self {
+            beat_type,
+            tempo_changes: vec![TempoChange {
+                position: 0.0,
+                tempo_bpm,
+                ramp: TempoRamp::Instant,
+            }],
+            time_signatures: vec![(1, time_signature)],
+        };
+    }
+
+    // A change at the position of another one replaces it.
+    pub fn set_tempo_at(
+        &mut self,
+        position: f64,
+        tempo_bpm: f64,
+        ramp: TempoRamp,
+    ) -> Result<(), InvalidTiming> {
+        if tempo_bpm <= 0.0 || tempo_bpm.is_nan() || tempo_bpm.is_infinite() {
+            return Err(InvalidTiming {
+                kind: InvalidTimingKind::NonPositiveTempo,
+            });
+        }
+        if position < 0.0 || position.is_nan() {
+            return Err(InvalidTiming {
+                kind: InvalidTimingKind::PositionInThePast,
+            });
+        }
+        let change = TempoChange {
+            position,
+            tempo_bpm,
+            ramp,
+        };
+        let index = self
+            .tempo_changes
+            .partition_point(|change| change.position < position);
+        match self.tempo_changes.get(index) {
+            Some(existing) if existing.position == position => self.tempo_changes[index] = change,
+            _ => self.tempo_changes.insert(index, change),
+        }
+        return Ok(());
+    }
+
+    pub fn set_time_signature_at(
+        &mut self,
+        bar: u32,
+        time_signature: TimeSignature,
+    ) -> Result<(), InvalidTiming> {
+        if bar == 0 {
+            return Err(InvalidTiming {
+                kind: InvalidTimingKind::InvalidBarBeatTick,
+            });
+        }
+        if !time_signature.is_valid() {
+            return Err(InvalidTiming {
+                kind: InvalidTimingKind::InvalidTimeSignature,
+            });
+        }
+        let index = self
+            .time_signatures
+            .partition_point(|(start_bar, _)| *start_bar < bar);
+        match self.time_signatures.get(index) {
+            Some((start_bar, _)) if *start_bar == bar => {
+                self.time_signatures[index] = (bar, time_signature)
+            }
+            _ => self.time_signatures.insert(index, (bar, time_signature)),
+        }
+        return Ok(());
+    }
+
+    pub fn time_signature_at_bar(&self, bar: u32) -> &TimeSignature {
+        let index = self
+            .time_signatures
+            .partition_point(|(start_bar, _)| *start_bar <= bar);
+        return &self.time_signatures[index.max(1) - 1].1;
+    }
+
+    fn whole_note_ms(&self, tempo_bpm: f64) -> f64 {
+        let ms_per_min = 60.0 * 1000.0;
+        return ms_per_min / (tempo_bpm * self.beat_type.duration_factor());
+    }
+
+    // The tempo at the start of the change at index and how fast it moves
+    // until the next change, in beats per minute per whole note.
+    fn segment(&self, index: usize) -> (f64, f64) {
+        let change = &self.tempo_changes[index];
+        let slope = match self.tempo_changes.get(index + 1) {
+            Some(next) if next.ramp == TempoRamp::Linear => {
+                (next.tempo_bpm - change.tempo_bpm) / (next.position - change.position)
+            }
+            _ => 0.0,
+        };
+        return (change.tempo_bpm, slope);
+    }
+
+    pub fn tempo_at(&self, position: f64) -> f64 {
+        let index = self
+            .tempo_changes
+            .partition_point(|change| change.position <= position)
+            .max(1)
+            - 1;
+        let (tempo_bpm, slope) = self.segment(index);
+        return tempo_bpm + slope * (position - self.tempo_changes[index].position);
+    }
+
+    // MIDI and most sequencers count tempos in quarter notes per minute,
+    // whatever the beat type.
+    pub fn quarter_note_tempo_at(&self, position: f64) -> f64 {
+        let quarter_notes_per_beat = self.beat_type.duration_factor() / 0.25;
+        return self.tempo_at(position) * quarter_notes_per_beat;
+    }
+
+    // Where each tempo change is and how the tempo gets there.
+    pub fn tempo_changes(&self) -> Vec<(f64, TempoRamp)> {
+        return self
+            .tempo_changes
+            .iter()
+            .map(|change| (change.position, change.ramp.clone()))
+            .collect();
+    }
+
+    pub fn time_signatures(&self) -> &[(u32, TimeSignature)] {
+        return &self.time_signatures;
+    }
+
+    // Integrates the length of a whole note over the tempo changes, which
+    // gives a logarithm over ramps.
+    pub fn position_to_ms(&self, position: f64) -> f64 {
+        let mut time_ms = 0.0;
+        for (index, change) in self.tempo_changes.iter().enumerate() {
+            if change.position >= position {
+                break;
+            }
+            let end = self
+                .tempo_changes
+                .get(index + 1)
+                .map_or(position, |next| next.position.min(position));
+            let (start_tempo_bpm, slope) = self.segment(index);
+            let length = end - change.position;
+            if slope == 0.0 {
+                time_ms += length * self.whole_note_ms(start_tempo_bpm);
+            } else {
+                let end_tempo_bpm = start_tempo_bpm + slope * length;
+                time_ms += self.whole_note_ms(slope) * (end_tempo_bpm / start_tempo_bpm).ln();
+            }
+        }
+        return time_ms;
+    }
+
+    pub fn bar_beat_tick_to_position(&self, at: &BarBeatTick) -> Result<f64, InvalidTiming> {
+        let time_signature = self.time_signature_at_bar(at.bar);
+        if at.bar == 0 || at.beat == 0 || at.beat > time_signature.beats_per_bar {
+            return Err(InvalidTiming {
+                kind: InvalidTimingKind::InvalidBarBeatTick,
+            });
+        }
+        if at.tick >= TICKS_PER_BEAT {
+            return Err(InvalidTiming {
+                kind: InvalidTimingKind::InvalidBarBeatTick,
+            });
+        }
+        let mut position = 0.0;
+        for (index, (start_bar, signature)) in self.time_signatures.iter().enumerate() {
+            if *start_bar > at.bar {
+                break;
+            }
+            let end_bar = self
+                .time_signatures
+                .get(index + 1)
+                .map_or(at.bar, |(next_bar, _)| (*next_bar).min(at.bar));
+            position += (end_bar - start_bar) as f64 * signature.bar_length();
+        }
+        let beat_length = time_signature.beat_length();
+        position += (at.beat - 1) as f64 * beat_length;
+        position += at.tick as f64 / TICKS_PER_BEAT as f64 * beat_length;
+        return Ok(position);
+    }
+
+    // Positions between ticks are rounded down to a tick.
+    pub fn position_to_bar_beat_tick(&self, position: f64) -> BarBeatTick {
+        let mut bar_start = 0.0;
+        let mut bar = 1;
+        for (index, (start_bar, signature)) in self.time_signatures.iter().enumerate() {
+            let bar_length = signature.bar_length();
+            let bars_left = ((position - bar_start) / bar_length + POSITION_TOLERANCE)
+                .floor()
+                .max(0.0) as u32;
+            let bars = match self.time_signatures.get(index + 1) {
+                Some((next_bar, _)) => bars_left.min(next_bar - start_bar),
+                None => bars_left,
+            };
+            bar_start += bars as f64 * bar_length;
+            bar = start_bar + bars;
+            let is_last = self
+                .time_signatures
+                .get(index + 1)
+                .is_none_or(|(next_bar, _)| bar < *next_bar);
+            if is_last {
+                break;
+            }
+        }
+        let beat_length = self.time_signature_at_bar(bar).beat_length();
+        let ticks = ((position - bar_start) / beat_length * TICKS_PER_BEAT as f64
+            + POSITION_TOLERANCE)
+            .floor()
+            .max(0.0) as u32;
+        return BarBeatTick::new(bar, ticks / TICKS_PER_BEAT + 1, ticks % TICKS_PER_BEAT);
+    }
+}
@@ -17,10 +17,200 @@ mod tests {
         let x: RythmBuilder<waves::Sine> = RythmBuilder {
             tempo_bpm: 60.0,
             beat_type: Beat::QuarterNote,
+            time_signature: TimeSignature::default(),
             rythm: vec![].into(),
+            groove: Groove::default(),
         }
         .with_tempo_bpm(45.0);
         // .with_rythm(vec![1.0]);
         assert_eq!(x.get_tempo_bpm(), &45.0);
     }
+
+    #[test]
+    fn test_tempo_ramp() {
+        let mut tempo_map = TempoMap::default();
+        tempo_map
+            .set_tempo_at(1.0, 120.0, TempoRamp::Linear)
+            .unwrap();
+        assert_eq!(tempo_map.tempo_at(0.5), 90.0);
+        // A whole note at 60 quarter notes per minute lasts 4 seconds, so
+        // speeding up to 120 takes 4 * ln(2) seconds.
+        let ramp_ms = 4000.0 * 2.0_f64.ln();
+        assert!((tempo_map.position_to_ms(1.0) - ramp_ms).abs() < 1e-9);
+        assert!((tempo_map.position_to_ms(2.0) - ramp_ms - 2000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bar_beat_tick() {
+        let mut rythm = RythmBuilder::default()
+            .with_time_signature(TimeSignature::new(3, 4))
+            .finalize()
+            .unwrap();
+        rythm
+            .set_time_signature_at(3, TimeSignature::new(6, 8))
+            .unwrap();
+        let sine = waves::SineBuilder::default().finalize().unwrap();
+        let at = BarBeatTick::parse("3:4:480").unwrap();
+        assert_eq!(
+            rythm.get_tempo_map().bar_beat_tick_to_position(&at),
+            Ok(1.5 + 3.5 / 8.0)
+        );
+        rythm.hit(0.25, sine.clone());
+        rythm.hit_at(&at, 0.125, sine.clone()).unwrap();
+        assert_eq!(
+            rythm.hit_positions(),
+            vec![BarBeatTick::new(1, 1, 0), at.clone()]
+        );
+        assert!(rythm.get_hit_at(&BarBeatTick::new(1, 2, 0)).is_none());
+        assert!(rythm.get_hit_at(&BarBeatTick::new(3, 5, 0)).is_some());
+        assert_eq!(
+            rythm.hit_at(&BarBeatTick::new(2, 1, 0), 0.25, sine),
+            Err(InvalidTiming {
+                kind: InvalidTimingKind::PositionInThePast
+            })
+        );
+    }
+
+    #[test]
+    fn test_chords() {
+        let mut rythm = RythmBuilder::default().finalize().unwrap();
+        let sine = waves::SineBuilder::default().finalize().unwrap();
+        rythm.chords_with_frequency(sine.clone(), &[(0.5, "a3", "m"), (0.25, "a3", "h13")]);
+        rythm.chord_with_intervals(
+            0.25,
+            sine,
+            "a3",
+            &[Interval::Unison, Interval::PerfectFifth],
+        );
+        assert_eq!(rythm.rythm.len(), 3);
+        let tones: Vec<f64> = rythm.rythm[0]
+            .clone()
+            .sounds()
+            .iter()
+            .map(|hit| hit.wave.get_tone())
+            .collect();
+        assert!((tones[0] - 220.0).abs() < 1e-6);
+        assert!((tones[1] - 261.6256).abs() < 1e-3);
+        assert!((tones[2] - 329.6276).abs() < 1e-3);
+        assert!(rythm.rythm[1].first_wave().is_none());
+        assert_eq!(rythm.rythm[2].clone().sounds().len(), 2);
+    }
+
+    #[test]
+    fn test_legato() {
+        let mut rythm = RythmBuilder::default().finalize().unwrap();
+        let sine = waves::SineBuilder::default().finalize().unwrap();
+        rythm.hit_held(0.25, 0.75, sine.clone());
+        rythm.hit_tied(&[0.125, 0.125], sine);
+        // The first hit rings for three seconds, past the end of the second
+        // one at two seconds.
+        let audio: Audio = rythm.into();
+        assert_eq!(audio.get_samples().len(), 3 * 44100);
+    }
+
+    #[test]
+    fn test_articulation() {
+        let mut rythm = RythmBuilder::default().finalize().unwrap();
+        let sine = waves::SineBuilder::default()
+            .with_duration(std::time::Duration::from_secs(2))
+            .finalize()
+            .unwrap();
+        let staccato = Articulation::default().with_velocity(0.5).with_gate(0.5);
+        rythm.hits_with_frequency(
+            sine,
+            &[
+                (0.25, "a4", staccato),
+                (0.25, "a4", Articulation::default()),
+            ],
+        );
+        // The first hit is held for half its beat, at half the amplitude.
+        let peak = |samples: &[f64]| samples.iter().map(|sample| sample.abs()).reduce(f64::max);
+        let first = rythm.next().unwrap().get_samples();
+        let second = rythm.next().unwrap().get_samples();
+        assert_eq!(first.len(), 22050);
+        assert_eq!(second.len(), 2 * 44100);
+        let ratio = peak(&first).unwrap() / peak(&second[44100..]).unwrap();
+        assert!((ratio - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_arrangement() {
+        let sine = waves::SineBuilder::default().finalize().unwrap();
+        let mut arrangement = Arrangement::default();
+        arrangement
+            .new_pattern("verse")
+            .unwrap()
+            .hits_with_frequency(sine.clone(), &[(0.25, "a3")]);
+        arrangement
+            .new_pattern("ending")
+            .unwrap()
+            .hits_with_frequency(sine.clone(), &[(0.25, "e4")]);
+        assert!(arrangement.new_pattern("verse").is_err());
+        let arrangement = arrangement.with_order(Order::repeat_with_endings(
+            vec![Order::pattern("verse")],
+            3,
+            vec![
+                vec![Order::pattern("ending")],
+                vec![Order::transposed("ending", -7)],
+            ],
+        ));
+        let mut rythm = RythmBuilder::default().finalize().unwrap();
+        arrangement.arrange_into(&mut rythm).unwrap();
+        let tones: Vec<f64> = rythm
+            .rythm
+            .iter()
+            .map(|element| element.first_wave().unwrap().get_tone())
+            .collect();
+        let expected = [220.0, 329.6276, 220.0, 220.0, 220.0, 220.0];
+        assert_eq!(tones.len(), expected.len());
+        for (tone, expected) in tones.iter().zip(expected) {
+            assert!((tone - expected).abs() < 1e-3);
+        }
+        let unknown = Arrangement::default().with_order(Order::pattern("chorus"));
+        assert!(unknown.arrange_into(&mut rythm).is_err());
+        assert_eq!(rythm.rythm.len(), 6);
+    }
+
+    fn eighth_notes(builder: RythmBuilder<waves::Sine>) -> Rythm<waves::Sine> {
+        let mut rythm = builder.finalize().unwrap();
+        let sine = waves::SineBuilder::default()
+            .with_duration(std::time::Duration::from_secs(1))
+            .finalize()
+            .unwrap();
+        rythm.hits_with_frequency(sine, &[(0.125, "a4"), (0.125, "a4")]);
+        return rythm;
+    }
+
+    #[test]
+    fn test_swing() {
+        let builder =
+            RythmBuilder::default().with_swing(Swing::from_percentage(75.0, SwingNote::EighthNote));
+        let mut rythm = eighth_notes(builder);
+        // The first eighth note takes three quarters of the beat.
+        assert_eq!(rythm.next().unwrap().get_samples().len(), 33075);
+        assert_eq!(rythm.next().unwrap().get_samples().len(), 44100);
+    }
+
+    #[test]
+    fn test_groove_and_humanize() {
+        let peak = |rythm: Rythm<waves::Sine>| {
+            let audio: Audio = rythm.into();
+            return audio.get_samples()[..22050]
+                .iter()
+                .map(|sample| sample.abs())
+                .reduce(f64::max)
+                .unwrap();
+        };
+        let template = GrooveTemplate::new(0.125, vec![], vec![0.5, -0.5]);
+        let accented = eighth_notes(RythmBuilder::default().with_groove_template(template));
+        let plain = eighth_notes(RythmBuilder::default());
+        assert!((peak(accented) - 1.5 * peak(plain)).abs() < 1e-9);
+        let humanized = |seed| {
+            let builder = RythmBuilder::default().with_humanize(Humanize::new(20.0, 0.2, seed));
+            let audio: Audio = eighth_notes(builder).into();
+            return audio;
+        };
+        assert_eq!(humanized(1), humanized(1));
+        assert_ne!(humanized(1), humanized(2));
+    }
 }
@@ -0,0 +1,424 @@
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use builder_derive_macro::Setters;
+
+use crate::audio::basic_filters::{HighPass, LowPass};
+use crate::audio::envelope::Envelope;
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::{milliseconds_to_samples, samples_to_milliseconds};
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_duty_cycle::HasDutyCycle;
+use crate::waves::traits::has_phase::retune;
+use crate::waves::{
+    Noise, NoiseBuilder, Pulse, PulseBuilder, Sawtooth, SawtoothBuilder, Sine, SineBuilder,
+    Triangle, TriangleBuilder,
+};
+use crate::{impl_has_amplitude, impl_has_sampling_frequency};
+
+mod presets;
+#[allow(unused_imports)]
+pub use presets::*;
+mod tests;
+
+// Like sfxr, the noise holds each random value for a 32nd of a period, so
+// it has a pitch.
+const NOISE_VALUES_PER_PERIOD: f64 = 32.0;
+const MINIMUM_DUTY_CYCLE: f64 = 0.01;
+const MAXIMUM_DUTY_CYCLE: f64 = 0.99;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SfxWave {
+    #[default]
+    Square,
+    Sawtooth,
+    Sine,
+    Triangle,
+    Noise,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidSfxKind {
+    NonPositiveFrequency,
+    NegativeTime,
+    InvalidDutyCycle,
+    InvalidResonance,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidSfx {
+    kind: InvalidSfxKind,
+}
+
+// The waves play for the whole sound and are retuned at every sample to
+// follow the slides and the vibrato.
+#[derive(Clone, Debug, PartialEq)]
+enum SfxOscillator {
+    Square(Pulse),
+    Sawtooth(Sawtooth),
+    Sine(Sine),
+    Triangle(Triangle),
+    Noise {
+        noise: Noise,
+        phase: f64,
+        value: f64,
+    },
+}
+
+impl SfxOscillator {
+    fn new(parameters: &SfxBuilder) -> Self {
+        let duration_ms = parameters.duration_ms();
+        let sampling_frequency = parameters.sampling_frequency;
+        match parameters.wave {
+            SfxWave::Square => Self::Square(
+                PulseBuilder::default()
+                    .with_duration_ms(duration_ms)
+                    .with_sampling_frequency(sampling_frequency)
+                    .finalize()
+                    .expect("a valid pulse"),
+            ),
+            SfxWave::Sawtooth => Self::Sawtooth(
+                SawtoothBuilder::default()
+                    .with_duration_ms(duration_ms)
+                    .with_sampling_frequency(sampling_frequency)
+                    .finalize()
+                    .expect("a valid sawtooth"),
+            ),
+            SfxWave::Sine => {
+                let mut sine = SineBuilder::default()
+                    .with_duration(Duration::from_secs_f64(duration_ms / 1000.0))
+                    .finalize()
+                    .expect("a valid sine");
+                sine.set_sampling_frequency(sampling_frequency);
+                Self::Sine(sine)
+            }
+            SfxWave::Triangle => Self::Triangle(
+                TriangleBuilder::default()
+                    .with_duration_ms(duration_ms)
+                    .with_sampling_frequency(sampling_frequency)
+                    .finalize()
+                    .expect("a valid triangle"),
+            ),
+            SfxWave::Noise => {
+                let mut noise = NoiseBuilder::default()
+                    .with_seed(parameters.seed)
+                    .with_duration_ms(duration_ms)
+                    .with_sampling_frequency(sampling_frequency)
+                    .finalize()
+                    .expect("a valid noise");
+                let value = noise.next().unwrap_or(0.0);
+                Self::Noise {
+                    noise,
+                    phase: 0.0,
+                    value,
+                }
+            }
+        }
+    }
+
+    fn next_sample(&mut self, sample_index: usize, frequency: f64, duty_cycle: f64) -> f64 {
+        let sample = match self {
+            Self::Square(pulse) => {
+                pulse.set_duty_cycle(duty_cycle);
+                retune(pulse, sample_index, frequency);
+                pulse.next()
+            }
+            Self::Sawtooth(sawtooth) => {
+                retune(sawtooth, sample_index, frequency);
+                sawtooth.next()
+            }
+            Self::Sine(sine) => {
+                retune(sine, sample_index, frequency);
+                sine.next()
+            }
+            Self::Triangle(triangle) => {
+                retune(triangle, sample_index, frequency);
+                triangle.next()
+            }
+            // A new value at most once per sample is as fast as the noise
+            // can change anyway.
+            Self::Noise {
+                noise,
+                phase,
+                value,
+            } => {
+                let sampling_frequency = noise.get_sampling_frequency();
+                let steps = NOISE_VALUES_PER_PERIOD * frequency / sampling_frequency;
+                let next_phase = *phase + steps;
+                if next_phase.floor() > phase.floor() {
+                    *value = noise.next().unwrap_or(0.0);
+                }
+                *phase = next_phase.fract();
+                Some(*value)
+            }
+        };
+        return sample.unwrap_or(0.0);
+    }
+}
+
+// The parameters of sfxr, in physical units. Slides and sweeps are in
+// octaves per second, delta_slide in octaves per second squared and the
+// vibrato depth is a fraction of the frequency. The sound stops for good
+// when sliding under minimum_frequency, and everything but the envelope
+// starts over every repeat_ms. The punch is how far the envelope goes
+// above the sustain right after the attack, as a fraction of it. A zero
+// time turns the arpeggio and the repeat off.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct SfxBuilder {
+    wave: SfxWave,
+    amplitude: f64,
+    base_frequency: f64,
+    minimum_frequency: f64,
+    slide: f64,
+    delta_slide: f64,
+    vibrato_depth: f64,
+    vibrato_frequency: f64,
+    arpeggio_ratio: f64,
+    arpeggio_time_ms: f64,
+    duty_cycle: f64,
+    duty_sweep: f64,
+    repeat_ms: f64,
+    attack_ms: f64,
+    sustain_ms: f64,
+    punch: f64,
+    decay_ms: f64,
+    low_pass_cutoff: Option<f64>,
+    low_pass_sweep: f64,
+    low_pass_resonance: f64,
+    high_pass_cutoff: Option<f64>,
+    high_pass_sweep: f64,
+    seed: u64,
+    sampling_frequency: f64,
+}
+
+impl Default for SfxBuilder {
+    fn default() -> Self {
+        return Self {
+            wave: SfxWave::default(),
+            amplitude: 1.0,
+            base_frequency: 440.0,
+            minimum_frequency: 0.0,
+            slide: 0.0,
+            delta_slide: 0.0,
+            vibrato_depth: 0.0,
+            vibrato_frequency: 0.0,
+            arpeggio_ratio: 1.0,
+            arpeggio_time_ms: 0.0,
+            duty_cycle: 0.5,
+            duty_sweep: 0.0,
+            repeat_ms: 0.0,
+            attack_ms: 0.0,
+            sustain_ms: 100.0,
+            punch: 0.0,
+            decay_ms: 200.0,
+            low_pass_cutoff: None,
+            low_pass_sweep: 0.0,
+            low_pass_resonance: 0.0,
+            high_pass_cutoff: None,
+            high_pass_sweep: 0.0,
+            seed: 1,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl SfxBuilder {
+    pub fn duration_ms(&self) -> f64 {
+        return self.attack_ms + self.sustain_ms + self.decay_ms;
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<InvalidSfx>> {
+        let mut possible_errors: Vec<InvalidSfx> = vec![];
+        let cutoffs = [self.low_pass_cutoff, self.high_pass_cutoff];
+        if self.base_frequency <= 0.0
+            || self.minimum_frequency < 0.0
+            || self.arpeggio_ratio <= 0.0
+            || cutoffs.iter().flatten().any(|cutoff| *cutoff <= 0.0)
+        {
+            possible_errors.push(InvalidSfx {
+                kind: InvalidSfxKind::NonPositiveFrequency,
+            });
+        }
+        let times = [
+            self.arpeggio_time_ms,
+            self.repeat_ms,
+            self.attack_ms,
+            self.sustain_ms,
+            self.decay_ms,
+        ];
+        if times.iter().any(|time| *time < 0.0) {
+            possible_errors.push(InvalidSfx {
+                kind: InvalidSfxKind::NegativeTime,
+            });
+        }
+        if !(0.0..=1.0).contains(&self.duty_cycle) {
+            possible_errors.push(InvalidSfx {
+                kind: InvalidSfxKind::InvalidDutyCycle,
+            });
+        }
+        if !(0.0..=1.0).contains(&self.low_pass_resonance) {
+            possible_errors.push(InvalidSfx {
+                kind: InvalidSfxKind::InvalidResonance,
+            });
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    pub fn finalize(self) -> Result<Sfx, InvalidSfx> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        let sustain_level = 1.0 / (1.0 + self.punch.max(0.0));
+        let envelope = Envelope::new(
+            self.attack_ms,
+            self.sustain_ms,
+            sustain_level,
+            self.decay_ms,
+        );
+        let low_pass = self
+            .low_pass_cutoff
+            .map(|cutoff| LowPass::new(cutoff, self.low_pass_resonance, self.low_pass_sweep));
+        let high_pass = self
+            .high_pass_cutoff
+            .map(|cutoff| HighPass::new(cutoff, self.high_pass_sweep));
+        let mut sfx = Sfx {
+            amplitude: self.amplitude,
+            sampling_frequency: self.sampling_frequency,
+            envelope,
+            oscillator: SfxOscillator::new(&self),
+            parameters: self,
+            sample_index: 0,
+            restart_index: 0,
+            frequency: 0.0,
+            slide: 0.0,
+            duty_cycle: 0.0,
+            arpeggio_done: false,
+            stopped: false,
+            vibrato_phase: 0.0,
+            low_pass,
+            high_pass,
+        };
+        sfx.restart();
+        return Ok(sfx);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sfx {
+    amplitude: f64,
+    sampling_frequency: f64,
+    envelope: Envelope,
+    oscillator: SfxOscillator,
+    parameters: SfxBuilder,
+    sample_index: usize,
+    restart_index: usize,
+    frequency: f64,
+    slide: f64,
+    duty_cycle: f64,
+    arpeggio_done: bool,
+    stopped: bool,
+    vibrato_phase: f64,
+    low_pass: Option<LowPass>,
+    high_pass: Option<HighPass>,
+}
+
+impl_has_amplitude!(Sfx);
+impl_has_sampling_frequency!(Sfx);
+
+#[allow(dead_code)]
+impl Sfx {
+    pub fn get_duration_ms(&self) -> f64 {
+        return self.parameters.duration_ms();
+    }
+
+    fn number_of_samples(&self) -> usize {
+        return milliseconds_to_samples(self.sampling_frequency, self.get_duration_ms());
+    }
+
+    fn milliseconds_since_restart(&self) -> f64 {
+        let samples = self.sample_index - self.restart_index;
+        return samples_to_milliseconds(self.sampling_frequency, samples);
+    }
+
+    fn restart(&mut self) {
+        self.restart_index = self.sample_index;
+        self.frequency = self.parameters.base_frequency;
+        self.slide = self.parameters.slide;
+        self.duty_cycle = self.parameters.duty_cycle;
+        self.arpeggio_done = false;
+    }
+
+    // The low pass filter comes before the high pass one, as in sfxr.
+    fn filter(&mut self, input: f64) -> f64 {
+        let mut output = input;
+        if let Some(low_pass) = self.low_pass.as_mut() {
+            output = low_pass.next_sample(output, self.sampling_frequency);
+        }
+        if let Some(high_pass) = self.high_pass.as_mut() {
+            output = high_pass.next_sample(output, self.sampling_frequency);
+        }
+        return output;
+    }
+}
+
+impl Iterator for Sfx {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_index >= self.number_of_samples() {
+            return None;
+        }
+        let time_step = 1.0 / self.sampling_frequency;
+        let time_ms = samples_to_milliseconds(self.sampling_frequency, self.sample_index);
+        if self.parameters.repeat_ms > 0.0
+            && self.milliseconds_since_restart() >= self.parameters.repeat_ms
+        {
+            self.restart();
+        }
+        if !self.arpeggio_done
+            && self.parameters.arpeggio_time_ms > 0.0
+            && self.milliseconds_since_restart() >= self.parameters.arpeggio_time_ms
+        {
+            self.frequency *= self.parameters.arpeggio_ratio;
+            self.arpeggio_done = true;
+        }
+        self.slide += self.parameters.delta_slide * time_step;
+        self.frequency = (self.frequency * 2.0_f64.powf(self.slide * time_step))
+            .min(self.sampling_frequency / 2.0);
+        if self.frequency < self.parameters.minimum_frequency {
+            self.stopped = true;
+        }
+        self.duty_cycle = (self.duty_cycle + self.parameters.duty_sweep * time_step)
+            .clamp(MINIMUM_DUTY_CYCLE, MAXIMUM_DUTY_CYCLE);
+        let vibrato = 1.0 + self.parameters.vibrato_depth * f64::sin(2.0 * PI * self.vibrato_phase);
+        self.vibrato_phase =
+            (self.vibrato_phase + self.parameters.vibrato_frequency * time_step).fract();
+        let sample = self.oscillator.next_sample(
+            self.sample_index,
+            self.frequency * vibrato,
+            self.duty_cycle,
+        );
+        let filtered = self.filter(sample);
+        let level = self.envelope.level_at(time_ms, self.get_duration_ms());
+        self.sample_index = self.sample_index + 1;
+        if self.stopped {
+            return Some(0.0);
+        }
+        return Some(self.amplitude * level * filtered);
+    }
+}
+
+impl Into<Audio> for Sfx {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.sampling_frequency;
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
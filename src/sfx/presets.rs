@@ -0,0 +1,203 @@
+use rand::rngs::SmallRng;
+use rand::{RngCore, SeedableRng};
+
+use super::{SfxBuilder, SfxWave};
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum SfxCategory {
+    Coin,
+    Laser,
+    Explosion,
+    Powerup,
+    Hit,
+    Jump,
+    Blip,
+}
+
+struct Dice(SmallRng);
+
+impl Dice {
+    fn uniform(&mut self) -> f64 {
+        return self.0.next_u32() as f64 / u32::MAX as f64;
+    }
+
+    fn between(&mut self, low: f64, high: f64) -> f64 {
+        return low + (high - low) * self.uniform();
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        return self.uniform() < probability;
+    }
+
+    fn pick(&mut self, waves: &[SfxWave]) -> SfxWave {
+        let index = (self.uniform() * waves.len() as f64) as usize;
+        return waves[index.min(waves.len() - 1)].clone();
+    }
+}
+
+// The randomizers of sfxr, with the same seed always giving the same
+// sound. The seed also seeds the noise of the sound.
+#[allow(dead_code)]
+impl SfxBuilder {
+    pub fn from_category(category: &SfxCategory, seed: u64) -> Self {
+        let mut dice = Dice(SmallRng::seed_from_u64(seed));
+        let sfx = Self::default().with_seed(seed).with_sustain_ms(0.0);
+        match category {
+            SfxCategory::Coin => {
+                let mut sfx = sfx
+                    .with_base_frequency(dice.between(600.0, 2800.0))
+                    .with_sustain_ms(dice.between(10.0, 60.0))
+                    .with_punch(dice.between(0.3, 0.6))
+                    .with_decay_ms(dice.between(100.0, 500.0));
+                if dice.chance(0.5) {
+                    sfx = sfx
+                        .with_arpeggio_ratio(dice.between(1.2, 1.8))
+                        .with_arpeggio_time_ms(dice.between(40.0, 120.0));
+                }
+                return sfx;
+            }
+            SfxCategory::Laser => {
+                let base_frequency = dice.between(500.0, 3000.0);
+                let mut sfx = sfx
+                    .with_wave(dice.pick(&[SfxWave::Square, SfxWave::Sawtooth, SfxWave::Sine]))
+                    .with_base_frequency(base_frequency)
+                    .with_minimum_frequency(base_frequency * dice.between(0.05, 0.3))
+                    .with_slide(-dice.between(2.0, 8.0))
+                    .with_duty_cycle(dice.between(0.2, 0.5))
+                    .with_sustain_ms(dice.between(30.0, 150.0))
+                    .with_punch(dice.between(0.0, 0.3))
+                    .with_decay_ms(dice.between(40.0, 300.0));
+                if dice.chance(0.33) {
+                    sfx = sfx.with_duty_sweep(dice.between(-1.0, 1.0));
+                }
+                if dice.chance(0.5) {
+                    sfx = sfx.with_high_pass_cutoff(Some(dice.between(100.0, 800.0)));
+                }
+                return sfx;
+            }
+            SfxCategory::Explosion => {
+                let mut sfx = sfx
+                    .with_wave(SfxWave::Noise)
+                    .with_base_frequency(dice.between(40.0, 400.0))
+                    .with_slide(dice.between(-1.5, 0.3))
+                    .with_sustain_ms(dice.between(50.0, 300.0))
+                    .with_punch(dice.between(0.2, 0.8))
+                    .with_decay_ms(dice.between(300.0, 1000.0));
+                if dice.chance(0.2) {
+                    sfx = sfx
+                        .with_vibrato_depth(dice.between(0.0, 0.7))
+                        .with_vibrato_frequency(dice.between(5.0, 15.0));
+                }
+                if dice.chance(0.33) {
+                    sfx = sfx.with_repeat_ms(dice.between(100.0, 400.0));
+                }
+                return sfx;
+            }
+            SfxCategory::Powerup => {
+                let mut sfx = sfx
+                    .with_wave(dice.pick(&[SfxWave::Square, SfxWave::Sawtooth]))
+                    .with_base_frequency(dice.between(300.0, 900.0))
+                    .with_duty_cycle(dice.between(0.2, 0.5))
+                    .with_sustain_ms(dice.between(50.0, 300.0))
+                    .with_decay_ms(dice.between(100.0, 400.0));
+                if dice.chance(0.5) {
+                    sfx = sfx
+                        .with_slide(dice.between(1.0, 3.0))
+                        .with_repeat_ms(dice.between(80.0, 200.0));
+                } else {
+                    sfx = sfx
+                        .with_slide(dice.between(0.5, 3.0))
+                        .with_vibrato_depth(dice.between(0.1, 0.5))
+                        .with_vibrato_frequency(dice.between(8.0, 20.0));
+                }
+                return sfx;
+            }
+            SfxCategory::Hit => {
+                let mut sfx = sfx
+                    .with_wave(dice.pick(&[SfxWave::Square, SfxWave::Sawtooth, SfxWave::Noise]))
+                    .with_base_frequency(dice.between(100.0, 900.0))
+                    .with_slide(-dice.between(3.0, 8.0))
+                    .with_duty_cycle(dice.between(0.2, 0.5))
+                    .with_sustain_ms(dice.between(10.0, 60.0))
+                    .with_decay_ms(dice.between(50.0, 200.0));
+                if dice.chance(0.5) {
+                    sfx = sfx.with_high_pass_cutoff(Some(dice.between(100.0, 600.0)));
+                }
+                return sfx;
+            }
+            SfxCategory::Jump => {
+                let mut sfx = sfx
+                    .with_base_frequency(dice.between(250.0, 700.0))
+                    .with_slide(dice.between(1.0, 4.0))
+                    .with_duty_cycle(dice.between(0.2, 0.5))
+                    .with_sustain_ms(dice.between(30.0, 150.0))
+                    .with_decay_ms(dice.between(50.0, 200.0));
+                if dice.chance(0.5) {
+                    sfx = sfx.with_high_pass_cutoff(Some(dice.between(100.0, 500.0)));
+                }
+                if dice.chance(0.5) {
+                    sfx = sfx.with_low_pass_cutoff(Some(dice.between(1000.0, 5000.0)));
+                }
+                return sfx;
+            }
+            SfxCategory::Blip => {
+                return sfx
+                    .with_wave(dice.pick(&[SfxWave::Square, SfxWave::Sine]))
+                    .with_base_frequency(dice.between(300.0, 1500.0))
+                    .with_duty_cycle(dice.between(0.2, 0.5))
+                    .with_sustain_ms(dice.between(10.0, 50.0))
+                    .with_decay_ms(dice.between(20.0, 100.0))
+                    .with_high_pass_cutoff(Some(100.0));
+            }
+        }
+    }
+
+    // Anything goes, within ranges that still give an audible sound.
+    pub fn random(seed: u64) -> Self {
+        let mut dice = Dice(SmallRng::seed_from_u64(seed));
+        let mut sfx = Self::default()
+            .with_seed(seed)
+            .with_wave(dice.pick(&[
+                SfxWave::Square,
+                SfxWave::Sawtooth,
+                SfxWave::Sine,
+                SfxWave::Triangle,
+                SfxWave::Noise,
+            ]))
+            .with_base_frequency(dice.between(50.0, 3000.0))
+            .with_slide(dice.between(-4.0, 4.0))
+            .with_delta_slide(dice.between(-2.0, 2.0))
+            .with_duty_cycle(dice.between(0.1, 0.9))
+            .with_duty_sweep(dice.between(-0.5, 0.5))
+            .with_attack_ms(dice.between(0.0, 100.0))
+            .with_sustain_ms(dice.between(20.0, 400.0))
+            .with_punch(dice.between(0.0, 1.0))
+            .with_decay_ms(dice.between(50.0, 600.0));
+        if dice.chance(0.5) {
+            sfx = sfx
+                .with_vibrato_depth(dice.between(0.0, 0.5))
+                .with_vibrato_frequency(dice.between(2.0, 20.0));
+        }
+        if dice.chance(0.5) {
+            sfx = sfx
+                .with_arpeggio_ratio(dice.between(0.5, 2.0))
+                .with_arpeggio_time_ms(dice.between(20.0, 300.0));
+        }
+        if dice.chance(0.3) {
+            sfx = sfx.with_repeat_ms(dice.between(50.0, 400.0));
+        }
+        if dice.chance(0.5) {
+            sfx = sfx
+                .with_low_pass_cutoff(Some(dice.between(300.0, 6000.0)))
+                .with_low_pass_sweep(dice.between(-2.0, 2.0))
+                .with_low_pass_resonance(dice.between(0.0, 1.0));
+        }
+        if dice.chance(0.5) {
+            sfx = sfx
+                .with_high_pass_cutoff(Some(dice.between(20.0, 1000.0)))
+                .with_high_pass_sweep(dice.between(-2.0, 2.0));
+        }
+        return sfx;
+    }
+}
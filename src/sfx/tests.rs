@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    const CATEGORIES: [SfxCategory; 7] = [
+        SfxCategory::Coin,
+        SfxCategory::Laser,
+        SfxCategory::Explosion,
+        SfxCategory::Powerup,
+        SfxCategory::Hit,
+        SfxCategory::Jump,
+        SfxCategory::Blip,
+    ];
+
+    #[test]
+    fn test_presets_are_seeded() {
+        for category in CATEGORIES.iter() {
+            for seed in 0..20 {
+                let builder = SfxBuilder::from_category(category, seed);
+                assert_eq!(builder, SfxBuilder::from_category(category, seed));
+                let samples: Vec<f64> = builder.clone().finalize().unwrap().collect();
+                let again: Vec<f64> = builder.finalize().unwrap().collect();
+                assert_eq!(samples, again);
+                assert!(samples.iter().all(|sample| sample.is_finite()));
+                assert!(samples.iter().any(|sample| *sample != 0.0));
+            }
+        }
+        assert_ne!(SfxBuilder::random(1), SfxBuilder::random(2));
+    }
+
+    #[test]
+    fn test_slide_under_minimum_frequency_stops() {
+        let samples: Vec<f64> = SfxBuilder::default()
+            .with_base_frequency(1000.0)
+            .with_minimum_frequency(500.0)
+            .with_slide(-10.0)
+            .finalize()
+            .unwrap()
+            .collect();
+        // One octave down takes a tenth of a second.
+        assert_eq!(samples.len(), 13230);
+        assert!(samples[..4000].iter().any(|sample| *sample != 0.0));
+        assert!(samples[4500..].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn test_invalid_sfx() {
+        assert_eq!(
+            SfxBuilder::default().with_decay_ms(-1.0).finalize(),
+            Err(InvalidSfx {
+                kind: InvalidSfxKind::NegativeTime
+            })
+        );
+        assert_eq!(
+            SfxBuilder::default()
+                .with_low_pass_cutoff(Some(0.0))
+                .finalize(),
+            Err(InvalidSfx {
+                kind: InvalidSfxKind::NonPositiveFrequency
+            })
+        );
+    }
+}
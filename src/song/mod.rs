@@ -0,0 +1,222 @@
+use std::f64::consts::FRAC_PI_4;
+
+use builder_derive_macro::Setters;
+
+use crate::audio::Audio;
+use crate::audio::effect::Effect;
+use crate::audio::stereo::StereoAudio;
+
+mod tests;
+
+fn apply_effects(audio: Audio, effects: &[Effect]) -> Audio {
+    return effects
+        .iter()
+        .fold(audio, |audio, effect| audio.filter_audio(effect.clone()));
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidSongKind {
+    DuplicateTrackName,
+    NegativeVolume,
+    InvalidPan,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidSong {
+    kind: InvalidSongKind,
+}
+
+fn volume_error(volume: f64) -> Option<InvalidSong> {
+    if volume < 0.0 {
+        return Some(InvalidSong {
+            kind: InvalidSongKind::NegativeVolume,
+        });
+    }
+    return None;
+}
+
+fn pan_error(pan: f64) -> Option<InvalidSong> {
+    if !(-1.0..=1.0).contains(&pan) {
+        return Some(InvalidSong {
+            kind: InvalidSongKind::InvalidPan,
+        });
+    }
+    return None;
+}
+
+// A track holds its rythm already rendered, so a song can mix rythms of
+// any wave type. The pan goes from -1.0, hard left, to 1.0, hard right.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct Track {
+    name: String,
+    audio: Audio,
+    volume: f64,
+    pan: f64,
+    muted: bool,
+    soloed: bool,
+    effects: Vec<Effect>,
+}
+
+impl Default for Track {
+    fn default() -> Self {
+        return Self {
+            name: String::new(),
+            audio: Audio::default(),
+            volume: 1.0,
+            pan: 0.0,
+            muted: false,
+            soloed: false,
+            effects: vec![],
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl Track {
+    pub fn from_rythm<T: Into<Audio>>(name: &str, rythm: T) -> Self {
+        return Self::default()
+            .with_name(name.to_string())
+            .with_audio(rythm.into());
+    }
+
+    pub fn with_effect(mut self, effect: Effect) -> Self {
+        self.effects.push(effect);
+        return self;
+    }
+
+    pub fn set_volume(&mut self, volume: f64) -> Result<(), InvalidSong> {
+        if let Some(error) = volume_error(volume) {
+            return Err(error);
+        }
+        self.volume = volume;
+        return Ok(());
+    }
+
+    pub fn set_pan(&mut self, pan: f64) -> Result<(), InvalidSong> {
+        if let Some(error) = pan_error(pan) {
+            return Err(error);
+        }
+        self.pan = pan;
+        return Ok(());
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn set_soloed(&mut self, soloed: bool) {
+        self.soloed = soloed;
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<InvalidSong>> {
+        let mut possible_errors: Vec<InvalidSong> = vec![];
+        possible_errors.extend(volume_error(self.volume));
+        possible_errors.extend(pan_error(self.pan));
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    // Constant power panning, so a sound keeps its loudness wherever it
+    // is placed.
+    fn render(&self) -> StereoAudio {
+        let audio = apply_effects(self.audio.clone(), &self.effects) * self.volume;
+        let angle = (self.pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+        return StereoAudio::new(audio.clone() * angle.cos(), audio * angle.sin());
+    }
+}
+
+// Tracks and the master volume are only changed through checked methods,
+// so there are no setters to bypass them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Song {
+    tracks: Vec<Track>,
+    master_volume: f64,
+    master_effects: Vec<Effect>,
+}
+
+impl Default for Song {
+    fn default() -> Self {
+        return Self {
+            tracks: vec![],
+            master_volume: 1.0,
+            master_effects: vec![],
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl Song {
+    pub fn add_track(&mut self, track: Track) -> Result<(), InvalidSong> {
+        if let Result::Err(error) = track.validate() {
+            return Err(error[0].clone());
+        }
+        if self.get_track(&track.name).is_some() {
+            return Err(InvalidSong {
+                kind: InvalidSongKind::DuplicateTrackName,
+            });
+        }
+        self.tracks.push(track);
+        return Ok(());
+    }
+
+    pub fn set_master_volume(&mut self, master_volume: f64) -> Result<(), InvalidSong> {
+        if let Some(error) = volume_error(master_volume) {
+            return Err(error);
+        }
+        self.master_volume = master_volume;
+        return Ok(());
+    }
+
+    pub fn get_track(&self, name: &str) -> Option<&Track> {
+        return self.tracks.iter().find(|track| track.name == name);
+    }
+
+    pub fn get_track_mut(&mut self, name: &str) -> Option<&mut Track> {
+        return self.tracks.iter_mut().find(|track| track.name == name);
+    }
+
+    pub fn with_master_effect(mut self, effect: Effect) -> Self {
+        self.master_effects.push(effect);
+        return self;
+    }
+
+    // As on a mixing desk, soloing a track silences every track that is
+    // not soloed, and muting wins over soloing.
+    fn audible_tracks(&self) -> impl Iterator<Item = &Track> {
+        let any_soloed = self.tracks.iter().any(|track| track.soloed);
+        return self
+            .tracks
+            .iter()
+            .filter(move |track| !track.muted && (track.soloed || !any_soloed));
+    }
+
+    // Every audible track after its own volume, pan and effects, before
+    // the master bus.
+    pub fn render_stems(&self) -> Vec<(String, StereoAudio)> {
+        return self
+            .audible_tracks()
+            .map(|track| (track.name.clone(), track.render()))
+            .collect();
+    }
+
+    pub fn render(&self) -> StereoAudio {
+        let mix = self
+            .render_stems()
+            .into_iter()
+            .map(|(_, stem)| stem)
+            .reduce(StereoAudio::overlap)
+            .unwrap_or_default();
+        let (left, right) = mix.into_channels();
+        let left = apply_effects(left, &self.master_effects) * self.master_volume;
+        let right = apply_effects(right, &self.master_effects) * self.master_volume;
+        return StereoAudio::new(left, right);
+    }
+}
+
+impl Into<Audio> for Song {
+    fn into(self) -> Audio {
+        return self.render().into();
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::audio::AudioBuilder;
+    use crate::utils::build::Build;
+
+    fn constant_track(name: &str, value: f64) -> Track {
+        let audio = AudioBuilder::new(vec![value; 100], 44100.0)
+            .finalize()
+            .unwrap();
+        return Track::default()
+            .with_name(name.to_string())
+            .with_audio(audio);
+    }
+
+    fn first_samples(stereo: StereoAudio) -> (f64, f64) {
+        let (left, right) = stereo.into_channels();
+        return (left.get_samples()[0], right.get_samples()[0]);
+    }
+
+    #[test]
+    fn test_pan() {
+        let (left, right) = first_samples(constant_track("a", 1.0).with_pan(-1.0).render());
+        assert!((left - 1.0).abs() < 1e-9 && right.abs() < 1e-9);
+        let (left, right) = first_samples(constant_track("a", 1.0).render());
+        assert!((left - right).abs() < 1e-9);
+        assert!((left * left + right * right - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mute_and_solo() {
+        let mut song = Song::default();
+        song.set_master_volume(2.0).unwrap();
+        song.add_track(constant_track("bass", 1.0).with_pan(-1.0))
+            .unwrap();
+        song.add_track(constant_track("lead", 0.5).with_pan(-1.0))
+            .unwrap();
+        assert_eq!(first_samples(song.render()).0, 3.0);
+        song.get_track_mut("lead").unwrap().set_soloed(true);
+        assert_eq!(first_samples(song.render()).0, 1.0);
+        song.get_track_mut("lead").unwrap().set_muted(true);
+        assert_eq!(song.render_stems().len(), 0);
+        assert!(song.render().into_channels().0.get_samples().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_tracks() {
+        let mut song = Song::default();
+        song.add_track(constant_track("bass", 1.0)).unwrap();
+        assert_eq!(
+            song.add_track(constant_track("bass", 1.0)),
+            Err(InvalidSong {
+                kind: InvalidSongKind::DuplicateTrackName
+            })
+        );
+        assert_eq!(
+            song.add_track(constant_track("lead", 1.0).with_pan(2.0)),
+            Err(InvalidSong {
+                kind: InvalidSongKind::InvalidPan
+            })
+        );
+        // Tracks already in the song are checked the same way.
+        let bass = song.get_track_mut("bass").unwrap();
+        assert_eq!(
+            bass.set_volume(-0.5),
+            Err(InvalidSong {
+                kind: InvalidSongKind::NegativeVolume
+            })
+        );
+        assert_eq!(
+            bass.set_pan(-1.5),
+            Err(InvalidSong {
+                kind: InvalidSongKind::InvalidPan
+            })
+        );
+        assert_eq!(bass.get_volume(), &1.0);
+        assert_eq!(bass.get_pan(), &0.0);
+        assert_eq!(bass.set_pan(-1.0), Ok(()));
+        assert_eq!(
+            song.set_master_volume(-1.0),
+            Err(InvalidSong {
+                kind: InvalidSongKind::NegativeVolume
+            })
+        );
+    }
+}
@@ -0,0 +1,10 @@
+// Input clocks of the sound chips, which both the chip emulations and the
+// waves modelled after them derive their frequencies from.
+pub const NES_NTSC_CPU_CLOCK: f64 = 1_789_773.0;
+pub const GAME_BOY_CPU_CLOCK: f64 = 4_194_304.0;
+pub const SEGA_MASTER_SYSTEM_PSG_CLOCK: f64 = 3_579_545.0;
+pub const ZX_SPECTRUM_AY_CLOCK: f64 = 1_773_400.0;
+#[allow(dead_code)]
+pub const MSX_PSG_CLOCK: f64 = 1_789_772.5;
+#[allow(dead_code)]
+pub const ATARI_ST_PSG_CLOCK: f64 = 2_000_000.0;
@@ -4,6 +4,7 @@ pub mod has_duration;
 use has_duration::HasDuration;
 pub mod has_sampling_frequency;
 use has_sampling_frequency::HasSamplingFrequency;
+pub mod clocks;
 pub mod frequency;
 // use sampling_frequency;
 
@@ -0,0 +1,23 @@
+mod module;
+#[allow(unused_imports)]
+pub use module::*;
+mod note;
+#[allow(unused_imports)]
+pub use note::*;
+mod player;
+#[allow(unused_imports)]
+pub use player::*;
+mod tests;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidModuleKind {
+    NotAModule,
+    TruncatedFile,
+    UnreadableFile,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidModule {
+    kind: InvalidModuleKind,
+}
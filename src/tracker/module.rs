@@ -0,0 +1,204 @@
+use std::rc::Rc;
+
+use super::{InvalidModule, InvalidModuleKind};
+
+const TITLE_LENGTH: usize = 20;
+const SAMPLE_COUNT: usize = 31;
+const SAMPLE_HEADER_LENGTH: usize = 30;
+const SAMPLE_NAME_LENGTH: usize = 22;
+const ORDER_TABLE_LENGTH: usize = 128;
+const SIGNATURE_OFFSET: usize = 1080;
+const PATTERN_OFFSET: usize = 1084;
+const CELL_LENGTH: usize = 4;
+pub const ROWS_PER_PATTERN: usize = 64;
+pub const MAXIMUM_VOLUME: u8 = 64;
+
+fn read_u16(bytes: &[u8], offset: usize) -> usize {
+    return u16::from_be_bytes([bytes[offset], bytes[offset + 1]]) as usize;
+}
+
+// Names are padded with zeros, and often hold whatever the tracker left
+// in memory after them.
+fn read_name(bytes: &[u8]) -> String {
+    return bytes
+        .iter()
+        .take_while(|byte| **byte != 0)
+        .map(|byte| *byte as char)
+        .collect();
+}
+
+// The four bytes after the order table tell how many channels there are,
+// or that the file isn't a 31 sample module at all.
+fn channel_count(signature: &[u8]) -> Option<usize> {
+    let channels = match signature {
+        b"M.K." | b"M!K!" | b"FLT4" | b"4CHN" => 4,
+        b"FLT8" => 8,
+        [digit, b'C', b'H', b'N'] if digit.is_ascii_digit() => (digit - b'0') as usize,
+        [tens, units, b'C', b'H'] if tens.is_ascii_digit() && units.is_ascii_digit() => {
+            ((tens - b'0') * 10 + (units - b'0')) as usize
+        }
+        _ => return None,
+    };
+    return match channels {
+        0 => None,
+        channels => Some(channels),
+    };
+}
+
+// Signed 8 bit samples, played at a rate set by the period of the note.
+// Lengths and loop points are in bytes, although the file counts them in
+// words. The finetune is in eighths of a semitone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrackerSample {
+    pub name: String,
+    pub data: Vec<f64>,
+    pub finetune: i8,
+    pub volume: u8,
+    pub loop_start: usize,
+    pub loop_length: usize,
+}
+
+#[allow(dead_code)]
+impl TrackerSample {
+    // A loop of a single word is how trackers write that there is none.
+    pub fn loop_points(&self) -> Option<(usize, usize)> {
+        let loop_end = (self.loop_start + self.loop_length).min(self.data.len());
+        if self.loop_length <= 2 || loop_end <= self.loop_start {
+            return None;
+        }
+        return Some((self.loop_start, loop_end));
+    }
+}
+
+// The period is the one of the Amiga, 428 for C-2 and lower for higher
+// notes, or 0 when the cell has no note. The sample counts from 1, with 0
+// for none.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PatternCell {
+    pub sample: u8,
+    pub period: u16,
+    pub effect: u8,
+    pub parameter: u8,
+}
+
+impl PatternCell {
+    fn parse(bytes: &[u8]) -> Self {
+        return Self {
+            sample: (bytes[0] & 0xf0) | (bytes[2] >> 4),
+            period: (((bytes[0] & 0x0f) as u16) << 8) | bytes[1] as u16,
+            effect: bytes[2] & 0x0f,
+            parameter: bytes[3],
+        };
+    }
+}
+
+// Rows of cells, one for each channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub rows: Vec<Vec<PatternCell>>,
+}
+
+// A ProTracker module, or one of the trackers that share its format with
+// more channels. The 15 sample modules of the original Soundtracker and
+// the formats that came after, like XM, are not supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackerModule {
+    pub title: String,
+    pub samples: Vec<Rc<TrackerSample>>,
+    pub orders: Vec<u8>,
+    pub channel_count: usize,
+    pub patterns: Vec<Pattern>,
+}
+
+#[allow(dead_code)]
+impl TrackerModule {
+    // Sample data cut short by the end of the file is kept as far as it
+    // goes, as many modules in the wild are.
+    pub fn parse(bytes: &[u8]) -> Result<Self, InvalidModule> {
+        let truncated = InvalidModule {
+            kind: InvalidModuleKind::TruncatedFile,
+        };
+        if bytes.len() < PATTERN_OFFSET {
+            return match bytes.len() < SIGNATURE_OFFSET {
+                true => Err(truncated),
+                false => Err(InvalidModule {
+                    kind: InvalidModuleKind::NotAModule,
+                }),
+            };
+        }
+        let channel_count =
+            channel_count(&bytes[SIGNATURE_OFFSET..PATTERN_OFFSET]).ok_or(InvalidModule {
+                kind: InvalidModuleKind::NotAModule,
+            })?;
+        let mut samples = vec![];
+        let mut sample_lengths = vec![];
+        for index in 0..SAMPLE_COUNT {
+            let header = &bytes[TITLE_LENGTH + index * SAMPLE_HEADER_LENGTH..];
+            let finetune = (header[SAMPLE_NAME_LENGTH + 2] & 0x0f) as i8;
+            samples.push(TrackerSample {
+                name: read_name(&header[..SAMPLE_NAME_LENGTH]),
+                data: vec![],
+                finetune: match finetune > 7 {
+                    true => finetune - 16,
+                    false => finetune,
+                },
+                volume: header[SAMPLE_NAME_LENGTH + 3].min(MAXIMUM_VOLUME),
+                loop_start: 2 * read_u16(header, SAMPLE_NAME_LENGTH + 4),
+                loop_length: 2 * read_u16(header, SAMPLE_NAME_LENGTH + 6),
+            });
+            sample_lengths.push(2 * read_u16(header, SAMPLE_NAME_LENGTH));
+        }
+        let order_offset = TITLE_LENGTH + SAMPLE_COUNT * SAMPLE_HEADER_LENGTH;
+        let song_length = (bytes[order_offset] as usize).clamp(1, ORDER_TABLE_LENGTH);
+        let order_table = &bytes[order_offset + 2..order_offset + 2 + ORDER_TABLE_LENGTH];
+        // Patterns that only unused entries of the table point to are
+        // still stored.
+        let pattern_count = *order_table.iter().max().expect("not empty") as usize + 1;
+        let row_length = channel_count * CELL_LENGTH;
+        let pattern_length = ROWS_PER_PATTERN * row_length;
+        let sample_offset = PATTERN_OFFSET + pattern_count * pattern_length;
+        if bytes.len() < sample_offset {
+            return Err(truncated);
+        }
+        let patterns = (0..pattern_count)
+            .map(|index| {
+                let pattern = &bytes[PATTERN_OFFSET + index * pattern_length..];
+                let rows = (0..ROWS_PER_PATTERN)
+                    .map(|row| {
+                        (0..channel_count)
+                            .map(|channel| {
+                                PatternCell::parse(
+                                    &pattern[row * row_length + channel * CELL_LENGTH..],
+                                )
+                            })
+                            .collect()
+                    })
+                    .collect();
+                Pattern { rows }
+            })
+            .collect();
+        let mut offset = sample_offset;
+        for (sample, length) in samples.iter_mut().zip(sample_lengths) {
+            let end = (offset + length).min(bytes.len());
+            sample.data = bytes[offset..end]
+                .iter()
+                .map(|byte| *byte as i8 as f64 / 128.0)
+                .collect();
+            offset = end;
+        }
+        return Ok(Self {
+            title: read_name(&bytes[..TITLE_LENGTH]),
+            samples: samples.into_iter().map(Rc::new).collect(),
+            orders: order_table[..song_length].to_vec(),
+            channel_count,
+            patterns,
+        });
+    }
+
+    pub fn read(path: &str) -> Result<Self, InvalidModule> {
+        let bytes = std::fs::read(path).map_err(|_error| InvalidModule {
+            kind: InvalidModuleKind::UnreadableFile,
+        })?;
+        return Self::parse(&bytes);
+    }
+}
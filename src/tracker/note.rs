@@ -0,0 +1,86 @@
+use std::rc::Rc;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::milliseconds_to_samples;
+use crate::utils::build::Build;
+
+use super::TrackerSample;
+
+// A tick of a note, at a rate in samples of the sample per second and a
+// volume from 0.0 to 1.0, which effects change from one tick to the next.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackerTick {
+    pub duration_ms: f64,
+    pub rate: f64,
+    pub volume: f64,
+}
+
+// A sample played from its offset, tick after tick, until the next note of
+// the channel. The sample is shared with every other note playing it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackerNote {
+    sample: Rc<TrackerSample>,
+    offset: usize,
+    ticks: Vec<TrackerTick>,
+    sampling_frequency: f64,
+}
+
+#[allow(dead_code)]
+impl TrackerNote {
+    pub fn new(sample: Rc<TrackerSample>, offset: usize, sampling_frequency: f64) -> Self {
+        return Self {
+            sample,
+            offset,
+            ticks: vec![],
+            sampling_frequency,
+        };
+    }
+
+    pub fn get_sample(&self) -> &TrackerSample {
+        return &self.sample;
+    }
+
+    pub fn push_tick(&mut self, tick: TrackerTick) {
+        self.ticks.push(tick);
+    }
+
+    pub fn get_ticks(&self) -> &[TrackerTick] {
+        return &self.ticks;
+    }
+
+    pub fn duration_ms(&self) -> f64 {
+        return self.ticks.iter().map(|tick| tick.duration_ms).sum();
+    }
+}
+
+// Like Paula, every sample is held until the next one, without any
+// interpolation.
+impl Into<Audio> for TrackerNote {
+    fn into(self) -> Audio {
+        let loop_points = self.sample.loop_points();
+        let mut samples = vec![];
+        let mut position = self.offset as f64;
+        let mut elapsed_ms = 0.0;
+        for tick in &self.ticks {
+            elapsed_ms += tick.duration_ms;
+            let tick_end = milliseconds_to_samples(self.sampling_frequency, elapsed_ms);
+            while samples.len() < tick_end {
+                if let Some((loop_start, loop_end)) = loop_points {
+                    while position >= loop_end as f64 {
+                        position -= (loop_end - loop_start) as f64;
+                    }
+                }
+                let sample = self
+                    .sample
+                    .data
+                    .get(position as usize)
+                    .copied()
+                    .unwrap_or(0.0);
+                samples.push(tick.volume * sample);
+                position += tick.rate / self.sampling_frequency;
+            }
+        }
+        let builder = AudioBuilder::new(samples, self.sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
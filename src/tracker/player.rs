@@ -0,0 +1,344 @@
+use std::collections::HashSet;
+
+use crate::rythm::{Beat, Rythm, RythmBuilder, TempoMap, TempoRamp, TimeSignature};
+use crate::song::{Song, Track};
+use crate::utils::build::Build;
+use crate::waves::traits::has_tone::SEMI_TONE_FACTOR;
+
+use super::{
+    MAXIMUM_VOLUME, PatternCell, ROWS_PER_PATTERN, TrackerModule, TrackerNote, TrackerTick,
+};
+
+// Twice the rate Paula reads samples at on a PAL Amiga, for a period of 1.
+const PAL_CLOCK: f64 = 7_093_789.2;
+const DEFAULT_SPEED: u32 = 6;
+const DEFAULT_BPM: u32 = 125;
+// A tick lasts 2.5 seconds divided by the BPM, as on the Amiga timers.
+const TICK_MS_PER_BPM: f64 = 2500.0;
+// At the default speed and BPM, four rows make a beat of 125 BPM, so rows
+// are placed as sixteenth notes.
+const ROWS_PER_WHOLE_NOTE: f64 = 16.0;
+const MS_PER_MINUTE: f64 = 60_000.0;
+const MINIMUM_PERIOD: f64 = 113.0;
+const MAXIMUM_PERIOD: f64 = 856.0;
+const FINETUNE_STEPS_PER_SEMITONE: f64 = 8.0;
+const SAMPLE_OFFSET_STEP: usize = 256;
+// Set speed takes ticks per row below this, and the BPM from it up.
+const FIRST_BPM: u8 = 32;
+// Half a sine over 32 steps, as ProTracker has it.
+const VIBRATO_TABLE: [f64; 32] = [
+    0.0, 24.0, 49.0, 74.0, 97.0, 120.0, 141.0, 161.0, 180.0, 197.0, 212.0, 224.0, 235.0, 244.0,
+    250.0, 253.0, 255.0, 253.0, 250.0, 244.0, 235.0, 224.0, 212.0, 197.0, 180.0, 161.0, 141.0,
+    120.0, 97.0, 74.0, 49.0, 24.0,
+];
+const VIBRATO_DEPTH_SCALE: f64 = 128.0;
+// Left, right, right, left, as the Amiga wires its four channels.
+const AMIGA_PANNING: [f64; 4] = [-1.0, 1.0, 1.0, -1.0];
+
+const ARPEGGIO: u8 = 0x0;
+const PORTAMENTO_UP: u8 = 0x1;
+const PORTAMENTO_DOWN: u8 = 0x2;
+const TONE_PORTAMENTO: u8 = 0x3;
+const VIBRATO: u8 = 0x4;
+const TONE_PORTAMENTO_VOLUME_SLIDE: u8 = 0x5;
+const VIBRATO_VOLUME_SLIDE: u8 = 0x6;
+const SAMPLE_OFFSET: u8 = 0x9;
+const VOLUME_SLIDE: u8 = 0xa;
+const POSITION_JUMP: u8 = 0xb;
+const SET_VOLUME: u8 = 0xc;
+const PATTERN_BREAK: u8 = 0xd;
+const SET_SPEED: u8 = 0xf;
+
+pub fn period_to_frequency(period: f64) -> f64 {
+    return PAL_CLOCK / (2.0 * period);
+}
+
+// In quarter notes, of four rows each, per minute.
+fn tempo_bpm(speed: u32, bpm: u32) -> f64 {
+    let row_ms = speed as f64 * TICK_MS_PER_BPM / bpm as f64;
+    return MS_PER_MINUTE / (ROWS_PER_WHOLE_NOTE / 4.0 * row_ms);
+}
+
+// What a channel remembers from one row to the next. The volume goes from
+// 0 to 64, and the sample counts from 1, with 0 for none yet.
+#[derive(Default)]
+struct Channel {
+    sample: usize,
+    period: f64,
+    volume: f64,
+    target_period: f64,
+    portamento_speed: f64,
+    vibrato_speed: usize,
+    vibrato_depth: f64,
+    vibrato_position: usize,
+    note: Option<(usize, TrackerNote)>,
+    notes: Vec<(usize, TrackerNote)>,
+}
+
+impl Channel {
+    fn end_note(&mut self) {
+        if let Some(note) = self.note.take() {
+            self.notes.push(note);
+        }
+    }
+
+    // Tick 0 of the row: a note with a tone portamento slides to its
+    // period rather than starting over.
+    fn start_row(
+        &mut self,
+        cell: &PatternCell,
+        module: &TrackerModule,
+        row: usize,
+        sampling_frequency: f64,
+    ) {
+        let sample = cell.sample as usize;
+        if sample > 0 && sample <= module.samples.len() {
+            self.sample = sample;
+            self.volume = module.samples[sample - 1].volume as f64;
+        }
+        let sliding = matches!(cell.effect, TONE_PORTAMENTO | TONE_PORTAMENTO_VOLUME_SLIDE);
+        if cell.period > 0 && sliding {
+            self.target_period = cell.period as f64;
+        } else if cell.period > 0 && self.sample > 0 {
+            self.period = cell.period as f64;
+            self.target_period = self.period;
+            self.vibrato_position = 0;
+            let offset = match cell.effect {
+                SAMPLE_OFFSET => cell.parameter as usize * SAMPLE_OFFSET_STEP,
+                _ => 0,
+            };
+            let sample = module.samples[self.sample - 1].clone();
+            self.end_note();
+            self.note = Some((row, TrackerNote::new(sample, offset, sampling_frequency)));
+        }
+        let (x, y) = (cell.parameter >> 4, cell.parameter & 0x0f);
+        match cell.effect {
+            TONE_PORTAMENTO if cell.parameter > 0 => {
+                self.portamento_speed = cell.parameter as f64;
+            }
+            VIBRATO => {
+                if x > 0 {
+                    self.vibrato_speed = x as usize;
+                }
+                if y > 0 {
+                    self.vibrato_depth = y as f64;
+                }
+            }
+            SET_VOLUME => self.volume = cell.parameter.min(MAXIMUM_VOLUME) as f64,
+            _ => {}
+        }
+    }
+
+    fn slide_volume(&mut self, parameter: u8) {
+        let (up, down) = (parameter >> 4, parameter & 0x0f);
+        let step = match up > 0 {
+            true => up as f64,
+            false => -(down as f64),
+        };
+        self.volume = (self.volume + step).clamp(0.0, MAXIMUM_VOLUME as f64);
+    }
+
+    fn slide_to_target(&mut self) {
+        self.period = match self.period < self.target_period {
+            true => (self.period + self.portamento_speed).min(self.target_period),
+            false => (self.period - self.portamento_speed).max(self.target_period),
+        };
+    }
+
+    // The vibrato at its current position, before moving on.
+    fn vibrate(&mut self) -> f64 {
+        let position = self.vibrato_position % (2 * VIBRATO_TABLE.len());
+        let offset = VIBRATO_TABLE[position % VIBRATO_TABLE.len()] * self.vibrato_depth
+            / VIBRATO_DEPTH_SCALE;
+        self.vibrato_position += self.vibrato_speed;
+        return match position < VIBRATO_TABLE.len() {
+            true => offset,
+            false => -offset,
+        };
+    }
+
+    // Slides and vibratos move on every tick but the first of the row,
+    // while an arpeggio plays its three notes in turn from the first.
+    fn play_tick(&mut self, cell: &PatternCell, tick: u32, tick_ms: f64) {
+        let mut vibrato = 0.0;
+        if tick > 0 {
+            match cell.effect {
+                PORTAMENTO_UP => {
+                    self.period = (self.period - cell.parameter as f64).max(MINIMUM_PERIOD);
+                }
+                PORTAMENTO_DOWN => {
+                    self.period = (self.period + cell.parameter as f64).min(MAXIMUM_PERIOD);
+                }
+                TONE_PORTAMENTO => self.slide_to_target(),
+                VIBRATO => vibrato = self.vibrate(),
+                TONE_PORTAMENTO_VOLUME_SLIDE => {
+                    self.slide_to_target();
+                    self.slide_volume(cell.parameter);
+                }
+                VIBRATO_VOLUME_SLIDE => {
+                    vibrato = self.vibrate();
+                    self.slide_volume(cell.parameter);
+                }
+                VOLUME_SLIDE => self.slide_volume(cell.parameter),
+                _ => {}
+            }
+        }
+        let volume = self.volume / MAXIMUM_VOLUME as f64;
+        let period = (self.period + vibrato).max(1.0);
+        let Some((_, note)) = self.note.as_mut() else {
+            return;
+        };
+        let finetune = note.get_sample().finetune as f64 / FINETUNE_STEPS_PER_SEMITONE;
+        let semitones = match (cell.effect, cell.parameter) {
+            (ARPEGGIO, parameter) if parameter > 0 => match tick % 3 {
+                0 => 0,
+                1 => parameter >> 4,
+                _ => parameter & 0x0f,
+            },
+            _ => 0,
+        };
+        let rate = period_to_frequency(period)
+            * SEMI_TONE_FACTOR.powf(finetune)
+            * SEMI_TONE_FACTOR.powi(semitones as i32);
+        note.push_tick(TrackerTick {
+            duration_ms: tick_ms,
+            rate,
+            volume,
+        });
+    }
+}
+
+// The notes of every channel, each with the row it starts on counted from
+// the start of the song, and the tempo from each row where it changes.
+pub(super) struct Playback {
+    pub(super) notes: Vec<Vec<(usize, TrackerNote)>>,
+    pub(super) tempo_changes: Vec<(usize, f64)>,
+    pub(super) row_count: usize,
+}
+
+#[allow(dead_code)]
+impl TrackerModule {
+    // Plays the order list row by row, until its end or until a position
+    // jump goes back to a row already played, where the song would loop.
+    pub(super) fn play(&self, sampling_frequency: f64) -> Playback {
+        let mut channels: Vec<Channel> = (0..self.channel_count)
+            .map(|_| Channel::default())
+            .collect();
+        let (mut speed, mut bpm) = (DEFAULT_SPEED, DEFAULT_BPM);
+        let mut tempo_changes = vec![];
+        let mut played = HashSet::new();
+        let (mut order, mut row) = (0, 0);
+        let mut row_count = 0;
+        while order < self.orders.len() && played.insert((order, row)) {
+            let Some(pattern) = self.patterns.get(self.orders[order] as usize) else {
+                break;
+            };
+            let cells = &pattern.rows[row];
+            let (mut jump, mut break_row) = (None, None);
+            let tempo = tempo_bpm(speed, bpm);
+            for (channel, cell) in channels.iter_mut().zip(cells) {
+                channel.start_row(cell, self, row_count, sampling_frequency);
+                let parameter = cell.parameter;
+                match cell.effect {
+                    POSITION_JUMP => jump = Some(parameter as usize),
+                    // The row is written in decimal, one digit a nibble.
+                    PATTERN_BREAK => {
+                        let target = (parameter >> 4) as usize * 10 + (parameter & 0x0f) as usize;
+                        break_row = Some(match target < ROWS_PER_PATTERN {
+                            true => target,
+                            false => 0,
+                        });
+                    }
+                    SET_SPEED if parameter == 0 => {}
+                    SET_SPEED if parameter < FIRST_BPM => speed = parameter as u32,
+                    SET_SPEED => bpm = parameter as u32,
+                    _ => {}
+                }
+            }
+            if tempo_bpm(speed, bpm) != tempo {
+                tempo_changes.push((row_count, tempo_bpm(speed, bpm)));
+            }
+            let tick_ms = TICK_MS_PER_BPM / bpm as f64;
+            for tick in 0..speed {
+                for (channel, cell) in channels.iter_mut().zip(cells) {
+                    channel.play_tick(cell, tick, tick_ms);
+                }
+            }
+            row_count += 1;
+            (order, row) = match (jump, break_row) {
+                (None, None) if row + 1 < ROWS_PER_PATTERN => (order, row + 1),
+                (jump, break_row) => (jump.unwrap_or(order + 1), break_row.unwrap_or(0)),
+            };
+        }
+        let notes = channels
+            .into_iter()
+            .map(|mut channel| {
+                channel.end_note();
+                channel.notes
+            })
+            .collect();
+        return Playback {
+            notes,
+            tempo_changes,
+            row_count,
+        };
+    }
+
+    // Rows are sixteenth notes on the tempo map of the song, whose tempo
+    // changes with the speed and the BPM, so that every note of a channel
+    // lasts until the next one.
+    pub fn channel_rythms(&self, sampling_frequency: f64) -> Vec<Rythm<TrackerNote>> {
+        let playback = self.play(sampling_frequency);
+        let mut tempo_map = TempoMap::new(
+            tempo_bpm(DEFAULT_SPEED, DEFAULT_BPM),
+            Beat::QuarterNote,
+            TimeSignature::default(),
+        );
+        for (row, tempo) in playback.tempo_changes {
+            tempo_map
+                .set_tempo_at(row as f64 / ROWS_PER_WHOLE_NOTE, tempo, TempoRamp::Instant)
+                .expect("speeds and BPMs are positive");
+        }
+        let to_position = |rows: usize| rows as f64 / ROWS_PER_WHOLE_NOTE;
+        return playback
+            .notes
+            .into_iter()
+            .map(|notes| {
+                let mut rythm = RythmBuilder::default().finalize().expect("TODO");
+                rythm.set_tempo_map(tempo_map.clone());
+                let mut end = 0;
+                for (index, (start, note)) in notes.iter().enumerate() {
+                    if *start > end {
+                        rythm.hit(-to_position(start - end), note.clone());
+                    }
+                    end = notes
+                        .get(index + 1)
+                        .map_or(playback.row_count, |(next_start, _)| *next_start);
+                    rythm.hit(to_position(end - start), note.clone());
+                }
+                rythm
+            })
+            .collect();
+    }
+
+    // Each channel that plays at least one note makes a track, named
+    // "channel 1" for the first one and so on, panned as on the Amiga.
+    pub fn to_song(&self, sampling_frequency: f64) -> Song {
+        let mut song = Song::default();
+        for (index, rythm) in self
+            .channel_rythms(sampling_frequency)
+            .into_iter()
+            .enumerate()
+        {
+            if rythm.hit_positions().is_empty() {
+                continue;
+            }
+            let mut track = Track::from_rythm(&format!("channel {}", index + 1), rythm);
+            track
+                .set_pan(AMIGA_PANNING[index % AMIGA_PANNING.len()])
+                .expect("Amiga pans are in range");
+            song.add_track(track).expect("channel names are unique");
+        }
+        return song;
+    }
+}
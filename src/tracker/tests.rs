@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::audio::Audio;
+    use crate::waves::traits::has_tone::SEMI_TONE_FACTOR;
+
+    const C_2: u16 = 428;
+    const C_3: u16 = 214;
+
+    fn cell(sample: u8, period: u16, effect: u8, parameter: u8) -> [u8; 4] {
+        return [
+            (sample & 0xf0) | (period >> 8) as u8,
+            period as u8,
+            (sample << 4) | effect,
+            parameter,
+        ];
+    }
+
+    // A four channel module with a single pattern and a single sample, a
+    // looped square wave at full volume.
+    fn module_bytes(cells: &[(usize, usize, [u8; 4])]) -> Vec<u8> {
+        let mut bytes = b"test".to_vec();
+        bytes.resize(20, 0);
+        let mut sample = b"square".to_vec();
+        sample.resize(22, 0);
+        sample.extend([0, 16, 0, 64, 0, 0, 0, 16]);
+        bytes.extend(sample);
+        bytes.resize(20 + 31 * 30, 0);
+        bytes.extend([1, 127]);
+        bytes.resize(bytes.len() + 128, 0);
+        bytes.extend(b"M.K.");
+        let mut pattern = vec![0; 64 * 4 * 4];
+        for (row, channel, cell) in cells {
+            let offset = (row * 4 + channel) * 4;
+            pattern[offset..offset + 4].copy_from_slice(cell);
+        }
+        bytes.extend(pattern);
+        bytes.extend([64; 16]);
+        bytes.extend([-64_i8 as u8; 16]);
+        return bytes;
+    }
+
+    #[test]
+    fn test_parse() {
+        let bytes = module_bytes(&[(1, 2, cell(1, C_2, 0xc, 32))]);
+        let module = TrackerModule::parse(&bytes).unwrap();
+        assert_eq!(module.title, "test");
+        assert_eq!(module.channel_count, 4);
+        assert_eq!(module.orders, vec![0]);
+        assert_eq!(module.patterns.len(), 1);
+        assert_eq!(
+            module.patterns[0].rows[1][2],
+            PatternCell {
+                sample: 1,
+                period: C_2,
+                effect: 0xc,
+                parameter: 32
+            }
+        );
+        let sample = &module.samples[0];
+        assert_eq!(sample.name, "square");
+        assert_eq!(sample.volume, 64);
+        assert_eq!(sample.data.len(), 32);
+        assert_eq!(sample.data[0], 0.5);
+        assert_eq!(sample.loop_points(), Some((0, 32)));
+        assert_eq!(module.samples[1].loop_points(), None);
+        let mut not_a_module = bytes.clone();
+        not_a_module[1080..1084].copy_from_slice(b"RIFF");
+        assert_eq!(
+            TrackerModule::parse(&not_a_module),
+            Err(InvalidModule {
+                kind: InvalidModuleKind::NotAModule
+            })
+        );
+        assert_eq!(
+            TrackerModule::parse(&bytes[..2000]),
+            Err(InvalidModule {
+                kind: InvalidModuleKind::TruncatedFile
+            })
+        );
+    }
+
+    #[test]
+    fn test_effects() {
+        let bytes = module_bytes(&[
+            (0, 0, cell(1, C_2, 0xf, 3)),
+            (1, 0, cell(0, 0, 0xa, 0x02)),
+            (2, 0, cell(0, 0, 0x0, 0x47)),
+            (3, 1, cell(1, C_3, 0x1, 4)),
+            (3, 2, cell(0, 0, 0xd, 0)),
+        ]);
+        let module = TrackerModule::parse(&bytes).unwrap();
+        let playback = module.play(44100.0);
+        // The break on the last row leaves the order list, so the song ends.
+        assert_eq!(playback.row_count, 4);
+        assert_eq!(playback.tempo_changes, vec![(0, 250.0)]);
+        let (start, note) = &playback.notes[0][0];
+        assert_eq!(*start, 0);
+        let ticks = note.get_ticks();
+        assert_eq!(ticks.len(), 12);
+        assert_eq!(ticks[0].duration_ms, 20.0);
+        let volumes: Vec<f64> = ticks[2..6].iter().map(|tick| tick.volume).collect();
+        assert_eq!(volumes, vec![1.0, 1.0, 62.0 / 64.0, 60.0 / 64.0]);
+        let base_rate = period_to_frequency(C_2 as f64);
+        assert_eq!(ticks[6].rate, base_rate);
+        assert!((ticks[7].rate / base_rate - SEMI_TONE_FACTOR.powi(4)).abs() < 1e-12);
+        assert!((ticks[8].rate / base_rate - SEMI_TONE_FACTOR.powi(7)).abs() < 1e-12);
+        let slide: Vec<f64> = playback.notes[1][0].1.get_ticks()[..3]
+            .iter()
+            .map(|tick| tick.rate)
+            .collect();
+        assert_eq!(slide[0], period_to_frequency(C_3 as f64));
+        assert_eq!(slide[2], period_to_frequency((C_3 - 8) as f64));
+        assert!(playback.notes[2].is_empty());
+    }
+
+    #[test]
+    fn test_position_jump() {
+        let bytes = module_bytes(&[(0, 0, cell(1, C_2, 0x3, 8)), (1, 3, cell(0, 0, 0xb, 0))]);
+        let module = TrackerModule::parse(&bytes).unwrap();
+        // Going back to the first row would loop forever.
+        let playback = module.play(44100.0);
+        assert_eq!(playback.row_count, 2);
+        // A tone portamento without a note playing has nothing to slide.
+        assert!(playback.notes[0].is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let bytes = module_bytes(&[
+            (0, 0, cell(1, C_2, 0xf, 3)),
+            (3, 1, cell(1, C_3, 0, 0)),
+            (3, 2, cell(0, 0, 0xd, 0)),
+        ]);
+        let module = TrackerModule::parse(&bytes).unwrap();
+        let rythms = module.channel_rythms(44100.0);
+        assert_eq!(rythms.len(), 4);
+        let mut audios = rythms.into_iter().map(Into::<Audio>::into);
+        // Four rows of three ticks of 20 ms.
+        let first = audios.next().unwrap().get_samples();
+        assert_eq!(first.len(), 10584);
+        assert_eq!(first[0], 0.5);
+        let second = audios.next().unwrap().get_samples();
+        assert_eq!(second.len(), 10584);
+        assert!(second[..7938].iter().all(|sample| *sample == 0.0));
+        assert!(audios.next().unwrap().get_samples().is_empty());
+        let song = module.to_song(44100.0);
+        assert_eq!(song.get_track("channel 1").unwrap().get_pan(), &-1.0);
+        assert_eq!(song.get_track("channel 2").unwrap().get_pan(), &1.0);
+        assert!(song.get_track("channel 3").is_none());
+        let (left, right) = song.render().into_channels();
+        assert_eq!(left.get_samples()[0], 0.5);
+        assert!(right.get_samples()[0].abs() < 1e-12);
+    }
+}
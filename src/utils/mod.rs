@@ -1,2 +1,5 @@
 pub mod build;
+#[allow(dead_code)]
 pub mod fft;
+#[cfg(test)]
+pub mod testing;
@@ -0,0 +1,39 @@
+use crate::apu::{InvalidApu, InvalidApuKind, ScheduleNotes};
+
+// Helpers shared by the tests of several modules.
+
+pub fn count_rising_crossings(samples: &[f64]) -> usize {
+    return samples
+        .windows(2)
+        .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+        .count();
+}
+
+// Plays a second of a4 on each channel of a fresh chip, which should rise
+// through zero 440 times give or take the tolerance.
+pub fn assert_note_pitch<A: ScheduleNotes>(
+    channels: &[A::Channel],
+    new_chip: impl Fn() -> A,
+    render_ms: impl Fn(&mut A, f64) -> Vec<f64>,
+    tolerance: usize,
+) {
+    for channel in channels {
+        let mut chip = new_chip();
+        chip.schedule_note(*channel, 0.0, 1000.0, "a4").unwrap();
+        let crossings = count_rising_crossings(&render_ms(&mut chip, 1000.0));
+        assert!((440 - tolerance..=440 + tolerance).contains(&crossings));
+    }
+}
+
+// Each note, scheduled at a start time, must fail with its error.
+pub fn assert_note_errors<A: ScheduleNotes>(
+    chip: &mut A,
+    notes: &[(A::Channel, f64, &str, InvalidApuKind)],
+) {
+    for (channel, start_ms, note, kind) in notes {
+        assert_eq!(
+            chip.schedule_note(*channel, *start_ms, 100.0, note),
+            Err(InvalidApu::new(kind.clone()))
+        );
+    }
+}
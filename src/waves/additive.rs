@@ -0,0 +1,255 @@
+use std::f64::consts::PI;
+
+use builder_derive_macro::Setters;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::has_duration::HasDuration;
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::{milliseconds_to_samples, samples_to_seconds};
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_phase::HasPhase;
+use crate::waves::traits::has_tone::HasTone;
+use crate::{
+    impl_has_amplitude, impl_has_duration, impl_has_phase, impl_has_sampling_frequency,
+    impl_has_tone,
+};
+
+use super::{InvalidWaveForm, InvalidWaveFormKind};
+
+const PRESET_HARMONICS: usize = 32;
+
+// Hammond drawbar footages (16', 5 1/3', 8', 4', 2 2/3', 2', 1 3/5', 1 1/3', 1')
+// as ratios of the 8' fundamental.
+const DRAWBAR_RATIOS: [f64; 9] = [0.5, 1.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0];
+
+// Partials of Risset's bell, relative to its nominal pitch.
+const BELL_PARTIALS: [(f64, f64); 9] = [
+    (0.56, 0.37),
+    (0.92, 0.25),
+    (1.19, 0.37),
+    (1.71, 0.67),
+    (2.00, 1.00),
+    (2.74, 0.63),
+    (3.00, 0.55),
+    (3.76, 0.50),
+    (4.07, 0.50),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Partial {
+    pub ratio: f64,
+    pub amplitude: f64,
+    pub phase_rad: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum AdditivePreset {
+    Organ([u8; 9]),
+    Bell,
+    Square,
+    Sawtooth,
+    Triangle,
+}
+
+impl AdditivePreset {
+    pub fn partials(&self) -> Vec<Partial> {
+        match self {
+            Self::Organ(drawbars) => DRAWBAR_RATIOS
+                .iter()
+                .zip(drawbars.iter())
+                .filter(|(_ratio, drawbar)| **drawbar > 0)
+                .map(|(ratio, drawbar)| Partial {
+                    ratio: *ratio,
+                    amplitude: (*drawbar).min(8) as f64 / 8.0,
+                    phase_rad: 0.0,
+                })
+                .collect(),
+            Self::Bell => BELL_PARTIALS
+                .iter()
+                .map(|(ratio, amplitude)| Partial {
+                    ratio: *ratio,
+                    amplitude: *amplitude,
+                    phase_rad: 0.0,
+                })
+                .collect(),
+            Self::Square => (1..=PRESET_HARMONICS)
+                .step_by(2)
+                .map(|harmonic| Partial {
+                    ratio: harmonic as f64,
+                    amplitude: 4.0 / (PI * harmonic as f64),
+                    phase_rad: 0.0,
+                })
+                .collect(),
+            Self::Sawtooth => (1..=PRESET_HARMONICS)
+                .map(|harmonic| Partial {
+                    ratio: harmonic as f64,
+                    amplitude: 2.0 / (PI * harmonic as f64),
+                    phase_rad: if harmonic % 2 == 0 { PI } else { 0.0 },
+                })
+                .collect(),
+            Self::Triangle => (1..=PRESET_HARMONICS)
+                .step_by(2)
+                .map(|harmonic| Partial {
+                    ratio: harmonic as f64,
+                    amplitude: 8.0 / (PI * harmonic as f64).powi(2),
+                    phase_rad: if harmonic % 4 == 3 { PI } else { 0.0 },
+                })
+                .collect(),
+        }
+    }
+}
+
+// Without any partial added, the wave plays its fundamental alone.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct AdditiveBuilder {
+    tone: f64,
+    amplitude: f64,
+    phase_rad: f64,
+    partials: Vec<Partial>,
+    truncate_above_nyquist: bool,
+    duration_ms: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for AdditiveBuilder {
+    fn default() -> Self {
+        return Self {
+            tone: 0.0,
+            amplitude: 1.0,
+            phase_rad: 0.0,
+            partials: vec![],
+            truncate_above_nyquist: true,
+            duration_ms: 0.0,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl AdditiveBuilder {
+    pub fn with_partial(mut self, ratio: f64, amplitude: f64, phase_rad: f64) -> Self {
+        self.partials.push(Partial {
+            ratio,
+            amplitude,
+            phase_rad,
+        });
+        return self;
+    }
+
+    pub fn with_preset(mut self, preset: AdditivePreset) -> Self {
+        self.partials = preset.partials();
+        return self;
+    }
+}
+
+#[allow(dead_code)]
+impl Build for AdditiveBuilder {
+    type Output = Additive;
+    type Error = InvalidWaveForm;
+
+    fn validate(&self) -> Result<(), Vec<InvalidWaveForm>> {
+        let mut possible_errors: Vec<InvalidWaveForm> = vec![];
+        if self.duration_ms < 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativeDuration,
+            });
+        }
+        if self.partials.iter().any(|partial| partial.ratio < 0.0) {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativePartialRatio,
+            });
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    fn finalize(self) -> Result<Additive, InvalidWaveForm> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        let partials = match self.partials.is_empty() {
+            true => vec![Partial {
+                ratio: 1.0,
+                amplitude: 1.0,
+                phase_rad: 0.0,
+            }],
+            false => self.partials,
+        };
+        return Ok(Additive {
+            tone: self.tone,
+            amplitude: self.amplitude,
+            phase_rad: self.phase_rad,
+            partials,
+            truncate_above_nyquist: self.truncate_above_nyquist,
+            duration_ms: self.duration_ms,
+            sampling_frequency: self.sampling_frequency,
+            sample_index: 0,
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Additive {
+    tone: f64,
+    amplitude: f64,
+    phase_rad: f64,
+    partials: Vec<Partial>,
+    truncate_above_nyquist: bool,
+    duration_ms: f64,
+    sampling_frequency: f64,
+    sample_index: usize,
+}
+
+impl_has_tone!(Additive);
+impl_has_amplitude!(Additive);
+impl_has_phase!(Additive);
+impl_has_duration!(Additive);
+impl_has_sampling_frequency!(Additive);
+
+impl Additive {
+    fn number_of_samples(&self) -> usize {
+        return milliseconds_to_samples(self.sampling_frequency, self.duration_ms);
+    }
+
+    fn is_audible(&self, partial: &Partial) -> bool {
+        if !self.truncate_above_nyquist {
+            return true;
+        }
+        return self.tone * partial.ratio < self.sampling_frequency / 2.0;
+    }
+}
+
+impl Iterator for Additive {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_index >= self.number_of_samples() {
+            return None;
+        }
+        let time = samples_to_seconds(self.sampling_frequency, self.sample_index);
+        let sample: f64 = self
+            .partials
+            .iter()
+            .filter(|partial| self.is_audible(partial))
+            .map(|partial| {
+                let angle = 2.0 * PI * self.tone * partial.ratio * time;
+                partial.amplitude
+                    * (angle + partial.ratio * self.phase_rad + partial.phase_rad).sin()
+            })
+            .sum();
+        self.sample_index = self.sample_index + 1;
+        return Some(self.amplitude * sample);
+    }
+}
+
+impl Into<Audio> for Additive {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.sampling_frequency;
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
@@ -0,0 +1,243 @@
+use std::f64::consts::PI;
+
+use builder_derive_macro::Setters;
+
+use crate::audio::envelope::Envelope;
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::has_duration::HasDuration;
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::{milliseconds_to_samples, samples_to_milliseconds};
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_tone::HasTone;
+use crate::{impl_has_amplitude, impl_has_duration, impl_has_sampling_frequency, impl_has_tone};
+
+use super::{InvalidWaveForm, InvalidWaveFormKind};
+
+const MAX_OPERATORS: usize = 4;
+
+// Operators are numbered as in the YM2612 (OPN) manual, so operator 0 is
+// the one with feedback and operator 3 is always a carrier.
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum FmAlgorithm {
+    #[default]
+    Algorithm0,
+    Algorithm1,
+    Algorithm2,
+    Algorithm3,
+    Algorithm4,
+    Algorithm5,
+    Algorithm6,
+    Algorithm7,
+}
+
+impl FmAlgorithm {
+    fn modulations(&self) -> &'static [(usize, usize)] {
+        match self {
+            Self::Algorithm0 => &[(0, 1), (1, 2), (2, 3)],
+            Self::Algorithm1 => &[(0, 2), (1, 2), (2, 3)],
+            Self::Algorithm2 => &[(0, 3), (1, 2), (2, 3)],
+            Self::Algorithm3 => &[(0, 1), (1, 3), (2, 3)],
+            Self::Algorithm4 => &[(0, 1), (2, 3)],
+            Self::Algorithm5 => &[(0, 1), (0, 2), (0, 3)],
+            Self::Algorithm6 => &[(0, 1)],
+            Self::Algorithm7 => &[],
+        }
+    }
+
+    fn carriers(&self) -> &'static [usize] {
+        match self {
+            Self::Algorithm0 | Self::Algorithm1 | Self::Algorithm2 | Self::Algorithm3 => &[3],
+            Self::Algorithm4 => &[1, 3],
+            Self::Algorithm5 | Self::Algorithm6 => &[1, 2, 3],
+            Self::Algorithm7 => &[0, 1, 2, 3],
+        }
+    }
+}
+
+// For carriers the modulation index works as the output level of the
+// operator, for modulators it is the peak phase deviation in radians.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct FmOperator {
+    ratio: f64,
+    detune_cents: f64,
+    modulation_index: f64,
+    envelope: Envelope,
+}
+
+impl Default for FmOperator {
+    fn default() -> Self {
+        return Self {
+            ratio: 1.0,
+            detune_cents: 0.0,
+            modulation_index: 1.0,
+            envelope: Envelope::default(),
+        };
+    }
+}
+
+impl FmOperator {
+    fn frequency(&self, tone: f64) -> f64 {
+        return tone * self.ratio * 2.0_f64.powf(self.detune_cents / 1200.0);
+    }
+}
+
+// Without any operator added, the wave plays two default ones.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct FmBuilder {
+    tone: f64,
+    amplitude: f64,
+    operators: Vec<FmOperator>,
+    algorithm: FmAlgorithm,
+    feedback: f64,
+    duration_ms: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for FmBuilder {
+    fn default() -> Self {
+        return Self {
+            tone: 0.0,
+            amplitude: 1.0,
+            operators: vec![],
+            algorithm: FmAlgorithm::default(),
+            feedback: 0.0,
+            duration_ms: 0.0,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl FmBuilder {
+    pub fn with_operator(mut self, operator: FmOperator) -> Self {
+        self.operators.push(operator);
+        return self;
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<InvalidWaveForm>> {
+        let mut possible_errors: Vec<InvalidWaveForm> = vec![];
+        if self.duration_ms < 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativeDuration,
+            });
+        }
+        if self.operators.len() == 1 || self.operators.len() > MAX_OPERATORS {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidOperatorCount,
+            });
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    pub fn finalize(self) -> Result<Fm, InvalidWaveForm> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        let operators = match self.operators.is_empty() {
+            true => vec![FmOperator::default(); 2],
+            false => self.operators,
+        };
+        let number_of_operators = operators.len();
+        return Ok(Fm {
+            tone: self.tone,
+            amplitude: self.amplitude,
+            operators,
+            algorithm: self.algorithm,
+            feedback: self.feedback,
+            duration_ms: self.duration_ms,
+            sampling_frequency: self.sampling_frequency,
+            sample_index: 0,
+            phases: vec![0.0; number_of_operators],
+            feedback_history: [0.0; 2],
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fm {
+    tone: f64,
+    amplitude: f64,
+    operators: Vec<FmOperator>,
+    algorithm: FmAlgorithm,
+    feedback: f64,
+    duration_ms: f64,
+    sampling_frequency: f64,
+    sample_index: usize,
+    phases: Vec<f64>,
+    feedback_history: [f64; 2],
+}
+
+impl_has_tone!(Fm);
+impl_has_amplitude!(Fm);
+impl_has_duration!(Fm);
+impl_has_sampling_frequency!(Fm);
+
+impl Fm {
+    fn number_of_samples(&self) -> usize {
+        return milliseconds_to_samples(self.sampling_frequency, self.duration_ms);
+    }
+
+    // With less than four operators, the given ones take the last slots of
+    // the algorithm, so that every algorithm keeps at least one carrier.
+    fn first_slot(&self) -> usize {
+        return MAX_OPERATORS - self.operators.len();
+    }
+}
+
+impl Iterator for Fm {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_index >= self.number_of_samples() {
+            return None;
+        }
+        let time_ms = samples_to_milliseconds(self.sampling_frequency, self.sample_index);
+        let first_slot = self.first_slot();
+        let mut outputs = [0.0; MAX_OPERATORS];
+        for (index, operator) in self.operators.iter().enumerate() {
+            let slot = first_slot + index;
+            let mut modulation: f64 = self
+                .algorithm
+                .modulations()
+                .iter()
+                .filter(|(_modulator, target)| *target == slot)
+                .map(|(modulator, _target)| outputs[*modulator])
+                .sum();
+            if index == 0 {
+                let history = self.feedback_history;
+                modulation += self.feedback * (history[0] + history[1]) / 2.0;
+            }
+            let level = operator.envelope.level_at(time_ms, self.duration_ms);
+            outputs[slot] =
+                operator.modulation_index * level * (self.phases[index] + modulation).sin();
+        }
+        self.feedback_history = [self.feedback_history[1], outputs[first_slot]];
+        for (index, operator) in self.operators.iter().enumerate() {
+            let increment = 2.0 * PI * operator.frequency(self.tone) / self.sampling_frequency;
+            self.phases[index] = (self.phases[index] + increment) % (2.0 * PI);
+        }
+        let carriers: Vec<usize> = self
+            .algorithm
+            .carriers()
+            .iter()
+            .copied()
+            .filter(|slot| *slot >= first_slot)
+            .collect();
+        let output: f64 = carriers.iter().map(|slot| outputs[*slot]).sum();
+        self.sample_index = self.sample_index + 1;
+        return Some(self.amplitude * output / carriers.len() as f64);
+    }
+}
+
+impl Into<Audio> for Fm {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.sampling_frequency;
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
@@ -0,0 +1,261 @@
+use builder_derive_macro::Setters;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::clocks::NES_NTSC_CPU_CLOCK;
+use crate::time::has_duration::HasDuration;
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::milliseconds_to_samples;
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_tone::HasTone;
+use crate::{impl_has_amplitude, impl_has_duration, impl_has_sampling_frequency};
+
+use super::{InvalidWaveForm, InvalidWaveFormKind};
+
+// Noise timer periods of the 2A03 (NTSC), in CPU cycles.
+pub const NES_NOISE_PERIODS: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+const MAX_LFSR_WIDTH: u8 = 32;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum LfsrMode {
+    #[default]
+    Long,
+    Short,
+    GameBoyShort,
+}
+
+impl LfsrMode {
+    pub fn width(&self) -> u8 {
+        match self {
+            Self::Long | Self::Short => 15,
+            Self::GameBoyShort => 7,
+        }
+    }
+
+    pub fn taps(&self) -> Vec<u8> {
+        match self {
+            Self::Long | Self::GameBoyShort => vec![0, 1],
+            Self::Short => vec![0, 6],
+        }
+    }
+}
+
+// Registers are from 2 to 32 bits wide, and taps are bits of the register.
+fn lfsr_errors(width: u8, taps: &[u8]) -> Vec<InvalidWaveForm> {
+    let mut possible_errors: Vec<InvalidWaveForm> = vec![];
+    if !(2..=MAX_LFSR_WIDTH).contains(&width) {
+        possible_errors.push(InvalidWaveForm {
+            kind: InvalidWaveFormKind::InvalidLfsrWidth,
+        });
+    }
+    if taps.is_empty() || taps.iter().any(|tap| *tap >= width) {
+        possible_errors.push(InvalidWaveForm {
+            kind: InvalidWaveFormKind::InvalidLfsrTaps,
+        });
+    }
+    return possible_errors;
+}
+
+// Fibonacci style register: the taps are xor'ed into the top bit while the
+// register shifts right, and the lowest bit is the output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lfsr {
+    register: u32,
+    width: u8,
+    taps: Vec<u8>,
+}
+
+#[allow(dead_code)]
+impl Lfsr {
+    pub fn new(width: u8, taps: Vec<u8>) -> Result<Self, InvalidWaveForm> {
+        if let Some(error) = lfsr_errors(width, &taps).first() {
+            return Err(error.clone());
+        }
+        return Ok(Self {
+            register: 1,
+            width,
+            taps,
+        });
+    }
+
+    pub fn from_mode(mode: &LfsrMode) -> Self {
+        return Self::new(mode.width(), mode.taps()).expect("modes are valid registers");
+    }
+
+    pub fn set_mode(&mut self, mode: &LfsrMode) {
+        self.width = mode.width();
+        self.taps = mode.taps();
+        self.register &= self.mask();
+        if self.register == 0 {
+            self.register = 1;
+        }
+    }
+
+    pub fn fill(&mut self) {
+        self.register = self.mask();
+    }
+
+    pub fn get_register(&self) -> u32 {
+        return self.register;
+    }
+
+    pub fn output(&self) -> bool {
+        return self.register & 1 == 1;
+    }
+
+    pub fn step(&mut self) -> bool {
+        let feedback = self.taps.iter().fold(0, |accumulator, tap| {
+            accumulator ^ ((self.register >> tap) & 1)
+        });
+        self.register = ((self.register >> 1) | (feedback << (self.width - 1))) & self.mask();
+        return self.output();
+    }
+
+    fn mask(&self) -> u32 {
+        return (((1_u64) << self.width) - 1) as u32;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct LfsrNoiseBuilder {
+    amplitude: f64,
+    clock_frequency: f64,
+    width: u8,
+    taps: Vec<u8>,
+    duration_ms: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for LfsrNoiseBuilder {
+    fn default() -> Self {
+        let mode = LfsrMode::default();
+        return Self {
+            amplitude: 1.0,
+            clock_frequency: NES_NTSC_CPU_CLOCK / NES_NOISE_PERIODS[8] as f64,
+            width: mode.width(),
+            taps: mode.taps(),
+            duration_ms: 0.0,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl LfsrNoiseBuilder {
+    pub fn with_mode(mut self, mode: LfsrMode) -> Self {
+        self.width = mode.width();
+        self.taps = mode.taps();
+        return self;
+    }
+
+    pub fn with_nes_period_index(mut self, index: usize) -> Self {
+        let period = NES_NOISE_PERIODS[index.min(NES_NOISE_PERIODS.len() - 1)];
+        self.clock_frequency = NES_NTSC_CPU_CLOCK / period as f64;
+        return self;
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<InvalidWaveForm>> {
+        let mut possible_errors: Vec<InvalidWaveForm> = vec![];
+        if self.duration_ms < 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativeDuration,
+            });
+        }
+        if !(self.clock_frequency.is_finite() && self.clock_frequency > 0.0) {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NonPositiveClockFrequency,
+            });
+        }
+        possible_errors.extend(lfsr_errors(self.width, &self.taps));
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    pub fn finalize(self) -> Result<LfsrNoise, InvalidWaveForm> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        return Ok(LfsrNoise {
+            amplitude: self.amplitude,
+            clock_frequency: self.clock_frequency,
+            lfsr: Lfsr::new(self.width, self.taps).expect("validated"),
+            duration_ms: self.duration_ms,
+            sampling_frequency: self.sampling_frequency,
+            sample_index: 0,
+            clock_phase: 0.0,
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LfsrNoise {
+    amplitude: f64,
+    clock_frequency: f64,
+    lfsr: Lfsr,
+    duration_ms: f64,
+    sampling_frequency: f64,
+    sample_index: usize,
+    clock_phase: f64,
+}
+
+impl_has_amplitude!(LfsrNoise);
+impl_has_duration!(LfsrNoise);
+impl_has_sampling_frequency!(LfsrNoise);
+
+// The tone of an LFSR is the rate at which its register is clocked, which
+// is what the period registers of the sound chips control.
+impl HasTone for LfsrNoise {
+    fn get_tone(&self) -> f64 {
+        self.clock_frequency
+    }
+
+    fn set_tone(&mut self, tone: f64) {
+        self.clock_frequency = tone;
+    }
+}
+
+impl LfsrNoise {
+    fn number_of_samples(&self) -> usize {
+        return milliseconds_to_samples(self.sampling_frequency, self.duration_ms);
+    }
+}
+
+impl Iterator for LfsrNoise {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_index >= self.number_of_samples() {
+            return None;
+        }
+        // Averaging the output over every clock inside the sample keeps high
+        // clock rates from aliasing into a tone of their own.
+        self.clock_phase += self.clock_frequency / self.sampling_frequency;
+        let clocks = self.clock_phase.floor() as usize;
+        self.clock_phase -= clocks as f64;
+        let level = |bit: bool| if bit { -1.0 } else { 1.0 };
+        let sample = if clocks == 0 {
+            level(self.lfsr.output())
+        } else {
+            (0..clocks)
+                .map(|_clock| level(self.lfsr.step()))
+                .sum::<f64>()
+                / clocks as f64
+        };
+        self.sample_index = self.sample_index + 1;
+        return Some(self.amplitude * sample);
+    }
+}
+
+impl Into<Audio> for LfsrNoise {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.sampling_frequency;
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
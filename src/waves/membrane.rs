@@ -0,0 +1,143 @@
+use std::f64::consts::PI;
+
+use builder_derive_macro::Setters;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::has_duration::HasDuration;
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::{milliseconds_to_samples, samples_to_seconds};
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_tone::HasTone;
+use crate::{impl_has_amplitude, impl_has_duration, impl_has_sampling_frequency, impl_has_tone};
+
+use super::{InvalidWaveForm, InvalidWaveFormKind};
+
+// Frequencies of the first modes of an ideal circular membrane, relative
+// to its fundamental (ratios between zeros of the Bessel functions).
+const MEMBRANE_MODES: [f64; 8] = [1.0, 1.594, 2.136, 2.296, 2.653, 2.918, 3.156, 3.501];
+
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct MembraneBuilder {
+    tone: f64,
+    amplitude: f64,
+    decay_ms: f64,
+    brightness: f64,
+    pitch_drop: f64,
+    duration_ms: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for MembraneBuilder {
+    fn default() -> Self {
+        return Self {
+            tone: 0.0,
+            amplitude: 1.0,
+            decay_ms: 150.0,
+            brightness: 0.5,
+            pitch_drop: 0.0,
+            duration_ms: 0.0,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl MembraneBuilder {
+    pub fn validate(&self) -> Result<(), Vec<InvalidWaveForm>> {
+        let mut possible_errors: Vec<InvalidWaveForm> = vec![];
+        if self.duration_ms < 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativeDuration,
+            });
+        }
+        if self.decay_ms <= 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::DecayOutOfBounds,
+            });
+        }
+        if !(0.0..=1.0).contains(&self.brightness) {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::BrightnessOutOfBounds,
+            });
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    pub fn finalize(self) -> Result<Membrane, InvalidWaveForm> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        return Ok(Membrane {
+            tone: self.tone,
+            amplitude: self.amplitude,
+            decay_ms: self.decay_ms,
+            brightness: self.brightness,
+            pitch_drop: self.pitch_drop,
+            duration_ms: self.duration_ms,
+            sampling_frequency: self.sampling_frequency,
+            sample_index: 0,
+            phases: [0.0; MEMBRANE_MODES.len()],
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Membrane {
+    tone: f64,
+    amplitude: f64,
+    decay_ms: f64,
+    brightness: f64,
+    pitch_drop: f64,
+    duration_ms: f64,
+    sampling_frequency: f64,
+    sample_index: usize,
+    phases: [f64; MEMBRANE_MODES.len()],
+}
+
+impl_has_tone!(Membrane);
+impl_has_amplitude!(Membrane);
+impl_has_duration!(Membrane);
+impl_has_sampling_frequency!(Membrane);
+
+impl Membrane {
+    fn number_of_samples(&self) -> usize {
+        return milliseconds_to_samples(self.sampling_frequency, self.duration_ms);
+    }
+}
+
+impl Iterator for Membrane {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_index >= self.number_of_samples() {
+            return None;
+        }
+        let time = samples_to_seconds(self.sampling_frequency, self.sample_index);
+        let decay_seconds = self.decay_ms / 1000.0;
+        // The pitch starts higher and settles on the tone, like a struck
+        // tom whose skin tension relaxes after the hit.
+        let bend = 1.0 + self.pitch_drop * f64::exp(-time / (0.25 * decay_seconds));
+        let mut sample = 0.0;
+        for (mode, ratio) in MEMBRANE_MODES.iter().enumerate() {
+            let weight = self.brightness.powi(mode as i32);
+            let envelope = f64::exp(-time * ratio / decay_seconds);
+            sample += weight * envelope * self.phases[mode].sin();
+            let increment = 2.0 * PI * self.tone * ratio * bend / self.sampling_frequency;
+            self.phases[mode] = (self.phases[mode] + increment) % (2.0 * PI);
+        }
+        self.sample_index = self.sample_index + 1;
+        return Some(self.amplitude * sample);
+    }
+}
+
+impl Into<Audio> for Membrane {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.sampling_frequency;
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
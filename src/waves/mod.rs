@@ -11,13 +11,50 @@ pub use sawtooth::*;
 mod noise;
 #[allow(unused_imports)]
 pub use noise::*;
+mod fm;
+#[allow(unused_imports)]
+pub use fm::*;
+mod additive;
+#[allow(unused_imports)]
+pub use additive::*;
+mod plucked_string;
+#[allow(unused_imports)]
+pub use plucked_string::*;
+mod membrane;
+#[allow(unused_imports)]
+pub use membrane::*;
+mod lfsr_noise;
+#[allow(unused_imports)]
+pub use lfsr_noise::*;
+mod pitch_quantizer;
+#[allow(unused_imports)]
+pub use pitch_quantizer::*;
+mod sample_playback;
+#[allow(unused_imports)]
+pub use sample_playback::*;
+mod sid;
+#[allow(unused_imports)]
+pub use sid::*;
 pub mod traits;
+mod tests;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum InvalidWaveFormKind {
     NegativeDuration,
     NegativeDutyCycle,
     DutyCycleBiggerThanOne,
+    InvalidOperatorCount,
+    NegativePartialRatio,
+    DecayOutOfBounds,
+    BrightnessOutOfBounds,
+    InvalidLfsrWidth,
+    InvalidLfsrTaps,
+    InvalidVoiceCount,
+    NonPositiveEncodingRate,
+    NonPositiveClockFrequency,
+    NonPositiveTone,
+    InvalidLoopPoints,
+    InvalidBitDepth,
 }
 
 #[derive(Clone, Debug, PartialEq)]
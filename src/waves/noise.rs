@@ -1,8 +1,7 @@
-use std::f64::consts::PI;
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
 use rand::rngs::SmallRng;
 use rand::{RngCore, SeedableRng};
-use rustfft::num_complex::Complex;
 
 use builder_derive_macro::{Finalize, Setters};
 
@@ -11,12 +10,32 @@ use crate::time::has_duration::HasDuration;
 use crate::time::has_sampling_frequency::HasSamplingFrequency;
 use crate::time::milliseconds_to_samples;
 use crate::utils::build::Build;
-use crate::utils::fft::{irfft, rfft, rfft_freq_bins};
 use crate::waves::traits::has_amplitude::HasAmplitude;
 use crate::{impl_has_amplitude, impl_has_duration, impl_has_sampling_frequency};
 
 use super::{InvalidWaveForm, InvalidWaveFormKind};
 
+// Paul Kellet's refined pink noise filter: a bank of one pole filters
+// whose sum follows the -3 dB/octave slope within 0.05 dB above 9 Hz.
+const PINK_FILTER_POLES: usize = 6;
+const PINK_FILTER_COEFFICIENTS: [(f64, f64); PINK_FILTER_POLES] = [
+    (0.99886, 0.0555179),
+    (0.99332, 0.0750759),
+    (0.96900, 0.1538520),
+    (0.86650, 0.3104856),
+    (0.55000, 0.5329522),
+    (-0.7616, -0.0168980),
+];
+const PINK_FILTER_DIRECT_GAIN: f64 = 0.5362;
+const PINK_FILTER_DELAYED_GAIN: f64 = 0.115926;
+const BROWN_NOISE_LEAK: f64 = 0.02;
+// Gains that bring every color back to roughly the unit variance of the
+// white noise they are made from.
+const PINK_NOISE_GAIN: f64 = 0.33;
+const BROWN_NOISE_GAIN: f64 = 10.0;
+const BLUE_NOISE_GAIN: f64 = 1.67;
+const VIOLET_NOISE_GAIN: f64 = FRAC_1_SQRT_2;
+
 #[allow(dead_code)]
 #[derive(Clone, Debug, Default, PartialEq)]
 pub enum NoiseVariant {
@@ -77,6 +96,9 @@ impl NoiseBuilder {
             sampling_frequency: self.sampling_frequency,
             rng: SmallRng::seed_from_u64(self.seed),
             sample_index: 0,
+            filter_state: [0.0; PINK_FILTER_POLES],
+            delayed_white_sample: 0.0,
+            last_sample: 0.0,
         });
     }
 }
@@ -90,12 +112,66 @@ pub struct Noise {
     sampling_frequency: f64,
     rng: SmallRng,
     sample_index: usize,
+    filter_state: [f64; PINK_FILTER_POLES],
+    delayed_white_sample: f64,
+    last_sample: f64,
 }
 
 impl Noise {
     fn number_of_samples(&self) -> usize {
         return milliseconds_to_samples(self.sampling_frequency, self.duration_ms);
     }
+
+    // Box-Muller transform of two uniform samples in [0, 1). The first one
+    // is flipped to (0, 1], as its logarithm is infinite at 0.
+    fn normal_sample(&mut self) -> f64 {
+        let uniform_sample_1 = self.rng.next_u32() as f64 / (u32::MAX as f64 + 1.0);
+        let uniform_sample_2 = self.rng.next_u32() as f64 / (u32::MAX as f64 + 1.0);
+        return f64::sqrt(-2.0 * f64::ln(1.0 - uniform_sample_1))
+            * f64::cos(2.0 * PI * uniform_sample_2);
+    }
+
+    fn pink_sample(&mut self, white_sample: f64) -> f64 {
+        let mut pink_sample = PINK_FILTER_DIRECT_GAIN * white_sample
+            + PINK_FILTER_DELAYED_GAIN * self.delayed_white_sample;
+        self.delayed_white_sample = white_sample;
+        for (state, (pole, gain)) in self
+            .filter_state
+            .iter_mut()
+            .zip(PINK_FILTER_COEFFICIENTS.iter())
+        {
+            *state = pole * *state + gain * white_sample;
+            pink_sample += *state;
+        }
+        return PINK_NOISE_GAIN * pink_sample;
+    }
+
+    // Blue and violet noise are the pink and white noises differentiated,
+    // and brown noise is white noise through a leaky integrator, so every
+    // color only needs the previous sample of the stream.
+    fn colored_sample(&mut self, white_sample: f64) -> f64 {
+        match self.variant {
+            NoiseVariant::White => white_sample,
+            NoiseVariant::Pink => self.pink_sample(white_sample),
+            NoiseVariant::Brown => {
+                let integrated =
+                    (self.last_sample + BROWN_NOISE_LEAK * white_sample) / (1.0 + BROWN_NOISE_LEAK);
+                self.last_sample = integrated;
+                BROWN_NOISE_GAIN * integrated
+            }
+            NoiseVariant::Blue => {
+                let pink_sample = self.pink_sample(white_sample);
+                let differentiated = pink_sample - self.last_sample;
+                self.last_sample = pink_sample;
+                BLUE_NOISE_GAIN * differentiated
+            }
+            NoiseVariant::Violet => {
+                let differentiated = white_sample - self.last_sample;
+                self.last_sample = white_sample;
+                VIOLET_NOISE_GAIN * differentiated
+            }
+        }
+    }
 }
 
 impl_has_amplitude!(Noise);
@@ -110,111 +186,15 @@ impl Iterator for Noise {
             return None;
         }
         self.sample_index = self.sample_index + 1;
-        let uniform_sample_1 = self.rng.next_u32() as f64 / u32::MAX as f64;
-        let uniform_sample_2 = self.rng.next_u32() as f64 / u32::MAX as f64;
-        let normal_sample = self.amplitude
-            * f64::sqrt(-2.0 * f64::ln(uniform_sample_1))
-            * f64::cos(2.0 * PI * uniform_sample_2);
-        return Some(normal_sample);
+        let white_sample = self.normal_sample();
+        return Some(self.amplitude * self.colored_sample(white_sample));
     }
 }
 
-fn get_noise(
-    sampling_frequency: f64,
-    number_of_samples: usize,
-    normal_samples: Vec<f64>,
-    noise_closure: impl Fn(f64) -> f64,
-) -> Audio {
-    let normal_samples_rfft = rfft(&normal_samples);
-    let spectral_density =
-        generate_power_spectral_density(number_of_samples, sampling_frequency, noise_closure);
-    assert_eq!(normal_samples_rfft.len(), spectral_density.len());
-    let rfft_len = normal_samples_rfft.len();
-    let mut noise_samples_rfft: Vec<Complex<f64>> = vec![Complex { re: 0.0, im: 0.0 }; rfft_len];
-    for i in 0..rfft_len {
-        noise_samples_rfft[i] = normal_samples_rfft[i] * spectral_density[i];
-    }
-    let noise_samples = irfft(noise_samples_rfft, number_of_samples);
-    let builder = AudioBuilder::new(noise_samples, sampling_frequency);
-    builder.finalize().expect("TODO")
-}
-
 impl Into<Audio> for Noise {
     fn into(self) -> Audio {
-        let number_of_samples = self.number_of_samples();
         let sampling_frequency = self.sampling_frequency;
-        let variant = self.variant.clone();
-        let normal_samples: Vec<f64> = self.collect();
-        return match variant {
-            NoiseVariant::Violet => {
-                let blue_noise_function = |freq: f64| freq;
-                get_noise(
-                    sampling_frequency,
-                    number_of_samples,
-                    normal_samples,
-                    blue_noise_function,
-                )
-            }
-            NoiseVariant::Blue => {
-                let blue_noise_function = |freq: f64| f64::sqrt(freq);
-                get_noise(
-                    sampling_frequency,
-                    number_of_samples,
-                    normal_samples,
-                    blue_noise_function,
-                )
-            }
-            NoiseVariant::White => {
-                let builder = AudioBuilder::new(normal_samples, sampling_frequency);
-                builder.finalize().expect("TODO")
-            }
-            NoiseVariant::Pink => {
-                let pink_noise_function = |freq: f64| {
-                    if freq != 0.0 {
-                        1.0 / f64::sqrt(freq)
-                    } else {
-                        0.0
-                    }
-                };
-                get_noise(
-                    sampling_frequency,
-                    number_of_samples,
-                    normal_samples,
-                    pink_noise_function,
-                )
-            }
-            NoiseVariant::Brown => {
-                let brown_noise_function = |freq: f64| if freq != 0.0 { 1.0 / freq } else { 0.0 };
-                get_noise(
-                    sampling_frequency,
-                    number_of_samples,
-                    normal_samples,
-                    brown_noise_function,
-                )
-            }
-        };
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
     }
 }
-
-fn generate_power_spectral_density<F: Fn(f64) -> f64>(
-    length: usize,
-    sampling_frequency: f64,
-    power_spectral_density_closure: F,
-) -> Vec<f64> {
-    let frequency_bins = rfft_freq_bins(length, sampling_frequency);
-    let spectrum_density: Vec<f64> = frequency_bins
-        .into_iter()
-        .map(power_spectral_density_closure)
-        .collect();
-    let power = spectrum_density
-        .iter()
-        .map(|density| density * density)
-        .reduce(|accumulator, element| accumulator + element / spectrum_density.len() as f64)
-        .unwrap_or(1.0)
-        .sqrt();
-    let normalized_spectrum_density: Vec<f64> = spectrum_density
-        .into_iter()
-        .map(|density| density / power)
-        .collect();
-    return normalized_spectrum_density;
-}
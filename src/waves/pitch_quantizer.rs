@@ -0,0 +1,112 @@
+use crate::time::clocks::{
+    GAME_BOY_CPU_CLOCK, NES_NTSC_CPU_CLOCK, SEGA_MASTER_SYSTEM_PSG_CLOCK, ZX_SPECTRUM_AY_CLOCK,
+};
+use crate::waves::traits::has_tone::parse_note;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PitchQuantizerPreset {
+    NesPulse,
+    NesTriangle,
+    GameBoySquare,
+    GameBoyWave,
+    Sn76489,
+    Ay38910,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizedPitch {
+    pub period: u32,
+    pub frequency: f64,
+    pub cents_error: f64,
+}
+
+// Chips produce frequencies of the form clock / (divider * (period +
+// offset)) for an integer period register. The Game Boy counts up to 2048
+// instead of down, so its period is 2048 minus the frequency register.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PitchQuantizer {
+    clock: f64,
+    divider: f64,
+    period_offset: f64,
+    minimum_period: u32,
+    maximum_period: u32,
+}
+
+#[allow(dead_code)]
+impl PitchQuantizer {
+    pub fn new(
+        clock: f64,
+        divider: f64,
+        period_offset: f64,
+        minimum_period: u32,
+        maximum_period: u32,
+    ) -> Self {
+        return Self {
+            clock,
+            divider,
+            period_offset,
+            minimum_period,
+            maximum_period,
+        };
+    }
+
+    pub fn from_preset(preset: &PitchQuantizerPreset) -> Self {
+        match preset {
+            PitchQuantizerPreset::NesPulse => Self::new(NES_NTSC_CPU_CLOCK, 16.0, 1.0, 8, 0x7FF),
+            PitchQuantizerPreset::NesTriangle => Self::new(NES_NTSC_CPU_CLOCK, 32.0, 1.0, 2, 0x7FF),
+            PitchQuantizerPreset::GameBoySquare => {
+                Self::new(GAME_BOY_CPU_CLOCK, 32.0, 0.0, 1, 2048)
+            }
+            PitchQuantizerPreset::GameBoyWave => Self::new(GAME_BOY_CPU_CLOCK, 64.0, 0.0, 1, 2048),
+            PitchQuantizerPreset::Sn76489 => {
+                Self::new(SEGA_MASTER_SYSTEM_PSG_CLOCK, 32.0, 0.0, 1, 0x3FF)
+            }
+            PitchQuantizerPreset::Ay38910 => Self::new(ZX_SPECTRUM_AY_CLOCK, 16.0, 0.0, 1, 0xFFF),
+        }
+    }
+
+    pub fn get_clock(&self) -> f64 {
+        return self.clock;
+    }
+
+    pub fn with_clock(mut self, clock: f64) -> Self {
+        self.clock = clock;
+        return self;
+    }
+
+    pub fn frequency(&self, period: u32) -> f64 {
+        return self.clock / (self.divider * (period as f64 + self.period_offset));
+    }
+
+    // The nearest period in cents, which is not always the nearest one in
+    // hertz. Frequencies out of the range of the chip get the closest end
+    // of the range, and frequencies that aren't positive get nothing.
+    pub fn quantize(&self, frequency: f64) -> Option<QuantizedPitch> {
+        if !(frequency.is_finite() && frequency > 0.0) {
+            return None;
+        }
+        let exact_period = self.clock / (self.divider * frequency) - self.period_offset;
+        let lower = exact_period
+            .floor()
+            .clamp(self.minimum_period as f64, self.maximum_period as f64);
+        let upper = exact_period
+            .ceil()
+            .clamp(self.minimum_period as f64, self.maximum_period as f64);
+        let cents_error = |period: f64| 1200.0 * (self.frequency(period as u32) / frequency).log2();
+        let period = if cents_error(lower).abs() <= cents_error(upper).abs() {
+            lower
+        } else {
+            upper
+        };
+        return Some(QuantizedPitch {
+            period: period as u32,
+            frequency: self.frequency(period as u32),
+            cents_error: cents_error(period),
+        });
+    }
+
+    pub fn quantize_note(&self, note: &str) -> Option<QuantizedPitch> {
+        return self.quantize(parse_note(note)?);
+    }
+}
@@ -0,0 +1,169 @@
+use rand::rngs::SmallRng;
+use rand::{RngCore, SeedableRng};
+
+use builder_derive_macro::Setters;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::has_duration::HasDuration;
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::milliseconds_to_samples;
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_tone::HasTone;
+use crate::{impl_has_amplitude, impl_has_duration, impl_has_sampling_frequency, impl_has_tone};
+
+use super::{InvalidWaveForm, InvalidWaveFormKind, Noise};
+
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct PluckedStringBuilder {
+    tone: f64,
+    amplitude: f64,
+    decay: f64,
+    brightness: f64,
+    seed: u64,
+    excitation: Option<Noise>,
+    duration_ms: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for PluckedStringBuilder {
+    fn default() -> Self {
+        return Self {
+            tone: 0.0,
+            amplitude: 1.0,
+            decay: 0.996,
+            brightness: 0.0,
+            seed: 1,
+            excitation: None,
+            duration_ms: 0.0,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl PluckedStringBuilder {
+    pub fn validate(&self) -> Result<(), Vec<InvalidWaveForm>> {
+        let mut possible_errors: Vec<InvalidWaveForm> = vec![];
+        if self.duration_ms < 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativeDuration,
+            });
+        }
+        if !(0.0..=1.0).contains(&self.decay) {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::DecayOutOfBounds,
+            });
+        }
+        if !(0.0..=1.0).contains(&self.brightness) {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::BrightnessOutOfBounds,
+            });
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    pub fn finalize(self) -> Result<PluckedString, InvalidWaveForm> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        return Ok(PluckedString {
+            tone: self.tone,
+            amplitude: self.amplitude,
+            decay: self.decay,
+            brightness: self.brightness,
+            seed: self.seed,
+            excitation: self.excitation,
+            duration_ms: self.duration_ms,
+            sampling_frequency: self.sampling_frequency,
+            sample_index: 0,
+            delay_line: vec![],
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PluckedString {
+    tone: f64,
+    amplitude: f64,
+    decay: f64,
+    brightness: f64,
+    seed: u64,
+    excitation: Option<Noise>,
+    duration_ms: f64,
+    sampling_frequency: f64,
+    sample_index: usize,
+    delay_line: Vec<f64>,
+}
+
+impl_has_tone!(PluckedString);
+impl_has_amplitude!(PluckedString);
+impl_has_duration!(PluckedString);
+impl_has_sampling_frequency!(PluckedString);
+
+impl PluckedString {
+    fn number_of_samples(&self) -> usize {
+        return milliseconds_to_samples(self.sampling_frequency, self.duration_ms);
+    }
+
+    // The delay line is only filled on the first sample, since the tone
+    // usually gets set after the string is built (e.g. by a Rythm).
+    fn excite(&mut self) {
+        let length = (self.sampling_frequency / self.tone).round().max(2.0) as usize;
+        self.delay_line = match self.excitation.clone() {
+            Some(mut noise) => {
+                noise.set_sampling_frequency(self.sampling_frequency);
+                noise.set_duration_ms(1000.0 * (length + 1) as f64 / self.sampling_frequency);
+                let audio: Audio = noise.into();
+                let mut burst = audio.get_samples();
+                burst.resize(length, 0.0);
+                burst
+            }
+            None => {
+                let mut rng = SmallRng::seed_from_u64(self.seed);
+                (0..length)
+                    .map(|_index| 2.0 * (rng.next_u32() as f64 / u32::MAX as f64) - 1.0)
+                    .collect()
+            }
+        };
+    }
+}
+
+impl Iterator for PluckedString {
+    type Item = f64;
+
+    // A string without a tone can't be plucked, but stays silent for its
+    // whole duration, so that it still takes its time in a rythm.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_index >= self.number_of_samples() {
+            return None;
+        }
+        if self.tone <= 0.0 {
+            self.sample_index = self.sample_index + 1;
+            return Some(0.0);
+        }
+        if self.delay_line.is_empty() {
+            self.excite();
+        }
+        let length = self.delay_line.len();
+        let position = self.sample_index % length;
+        let current = self.delay_line[position];
+        let following = self.delay_line[(position + 1) % length];
+        let averaged = 0.5 * (current + following);
+        let filtered = self.brightness * current + (1.0 - self.brightness) * averaged;
+        self.delay_line[position] = self.decay * filtered;
+        self.sample_index = self.sample_index + 1;
+        return Some(self.amplitude * current);
+    }
+}
+
+impl Into<Audio> for PluckedString {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.sampling_frequency;
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
@@ -9,11 +9,12 @@ use crate::time::has_sampling_frequency::HasSamplingFrequency;
 use crate::time::{infer_number_of_samples_1, samples_to_seconds};
 use crate::utils::build::Build;
 use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_duty_cycle::HasDutyCycle;
 use crate::waves::traits::has_phase::HasPhase;
 use crate::waves::traits::has_tone::HasTone;
 use crate::{
-    impl_has_amplitude, impl_has_duration, impl_has_phase, impl_has_sampling_frequency,
-    impl_has_tone,
+    impl_has_amplitude, impl_has_duration, impl_has_duty_cycle, impl_has_phase,
+    impl_has_sampling_frequency, impl_has_tone,
 };
 
 use super::{InvalidWaveForm, InvalidWaveFormKind};
@@ -101,6 +102,7 @@ pub struct Pulse {
 impl_has_tone!(Pulse);
 impl_has_amplitude!(Pulse);
 impl_has_phase!(Pulse);
+impl_has_duty_cycle!(Pulse);
 impl_has_duration!(Pulse);
 impl_has_sampling_frequency!(Pulse);
 
@@ -0,0 +1,322 @@
+use builder_derive_macro::Setters;
+
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::clocks::NES_NTSC_CPU_CLOCK;
+use crate::time::has_duration::HasDuration;
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::milliseconds_to_samples;
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_tone::HasTone;
+use crate::{impl_has_amplitude, impl_has_duration, impl_has_sampling_frequency, impl_has_tone};
+
+use super::{InvalidWaveForm, InvalidWaveFormKind};
+
+// Output rates of the delta modulation channel (NTSC), in CPU cycles per bit.
+pub const DMC_RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+// The DMC output is a 7 bit counter that every bit moves up or down by 2.
+const DPCM_LEVELS: i32 = 128;
+const DPCM_STEP: i32 = 2;
+const IMA_STEPS: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+const IMA_INDEX_CHANGES: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+const IMA_FULL_SCALE: f64 = 32767.0;
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SampleEncoding {
+    // 1 bit delta modulation, as played by the NES DMC.
+    #[default]
+    Dpcm,
+    // 4 bit IMA ADPCM.
+    Adpcm,
+    // Plain PCM with the given number of bits.
+    Pcm(u8),
+}
+
+impl SampleEncoding {
+    // Encodes the samples and decodes them right back, so the result keeps
+    // every artifact of the encoding.
+    fn degrade(&self, samples: &[f64]) -> Vec<f64> {
+        match self {
+            Self::Dpcm => degrade_dpcm(samples),
+            Self::Adpcm => degrade_adpcm(samples),
+            Self::Pcm(bits) => {
+                let levels = ((1_u64 << (*bits).min(32)) - 1) as f64;
+                samples
+                    .iter()
+                    .map(|sample| {
+                        let level = ((sample.clamp(-1.0, 1.0) + 1.0) / 2.0 * levels).round();
+                        2.0 * level / levels - 1.0
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn degrade_dpcm(samples: &[f64]) -> Vec<f64> {
+    let mut counter = DPCM_LEVELS / 2;
+    let mut output = Vec::with_capacity(samples.len());
+    for sample in samples {
+        let target = (sample + 1.0) / 2.0 * (DPCM_LEVELS - 1) as f64;
+        // The counter stays put instead of wrapping at either end.
+        if target > counter as f64 {
+            if counter + DPCM_STEP < DPCM_LEVELS {
+                counter += DPCM_STEP;
+            }
+        } else if counter - DPCM_STEP >= 0 {
+            counter -= DPCM_STEP;
+        }
+        output.push(2.0 * counter as f64 / (DPCM_LEVELS - 1) as f64 - 1.0);
+    }
+    return output;
+}
+
+fn degrade_adpcm(samples: &[f64]) -> Vec<f64> {
+    let mut predicted: i32 = 0;
+    let mut index: i32 = 0;
+    let mut output = Vec::with_capacity(samples.len());
+    for sample in samples {
+        let target = (sample.clamp(-1.0, 1.0) * IMA_FULL_SCALE) as i32;
+        let step = IMA_STEPS[index as usize];
+        let mut difference = target - predicted;
+        let mut nibble = 0;
+        if difference < 0 {
+            nibble = 8;
+            difference = -difference;
+        }
+        let mut delta = step >> 3;
+        for (bit, fraction) in [(4, step), (2, step >> 1), (1, step >> 2)] {
+            if difference >= fraction {
+                nibble |= bit;
+                difference -= fraction;
+                delta += fraction;
+            }
+        }
+        predicted += if nibble & 8 != 0 { -delta } else { delta };
+        predicted = predicted.clamp(-IMA_FULL_SCALE as i32, IMA_FULL_SCALE as i32);
+        index = (index + IMA_INDEX_CHANGES[(nibble & 7) as usize]).clamp(0, 88);
+        output.push(predicted as f64 / IMA_FULL_SCALE);
+    }
+    return output;
+}
+
+// Resamples with linear interpolation, which is good enough before the
+// much cruder reduction of the encoding.
+fn resample(samples: &[f64], from: f64, to: f64) -> Vec<f64> {
+    if samples.is_empty() || from <= 0.0 {
+        return vec![];
+    }
+    let length = (samples.len() as f64 * to / from) as usize;
+    return (0..length)
+        .map(|index| {
+            let position = index as f64 * from / to;
+            let before = position.floor() as usize;
+            let after = (before + 1).min(samples.len() - 1);
+            let fraction = position - before as f64;
+            samples[before] * (1.0 - fraction) + samples[after] * fraction
+        })
+        .collect();
+}
+
+// The sample plays at its original speed when the tone equals the root
+// tone, and faster or slower otherwise, as hardware does by changing the
+// playback rate. Loop points are in milliseconds of the original sample.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct SamplePlaybackBuilder {
+    tone: f64,
+    root_tone: f64,
+    amplitude: f64,
+    sample: Audio,
+    encoding: SampleEncoding,
+    encoding_rate: f64,
+    loop_start_ms: Option<f64>,
+    loop_end_ms: Option<f64>,
+    duration_ms: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for SamplePlaybackBuilder {
+    fn default() -> Self {
+        return Self {
+            tone: 440.0,
+            root_tone: 440.0,
+            amplitude: 1.0,
+            sample: Audio::default(),
+            encoding: SampleEncoding::default(),
+            encoding_rate: NES_NTSC_CPU_CLOCK / DMC_RATES[15] as f64,
+            loop_start_ms: None,
+            loop_end_ms: None,
+            duration_ms: 0.0,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl SamplePlaybackBuilder {
+    pub fn with_dmc_rate_index(mut self, index: usize) -> Self {
+        self.encoding_rate = NES_NTSC_CPU_CLOCK / DMC_RATES[index.min(15)] as f64;
+        return self;
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<InvalidWaveForm>> {
+        let mut possible_errors: Vec<InvalidWaveForm> = vec![];
+        if self.duration_ms < 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativeDuration,
+            });
+        }
+        if self.tone <= 0.0 || self.root_tone <= 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NonPositiveTone,
+            });
+        }
+        if self.encoding_rate <= 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NonPositiveEncodingRate,
+            });
+        }
+        if let SampleEncoding::Pcm(bits) = self.encoding
+            && (bits == 0 || bits > 32)
+        {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidBitDepth,
+            });
+        }
+        // A missing loop point defaults to its end of the sample.
+        let sample_ms = self.sample.get_duration_ms();
+        let loop_start_ms = self.loop_start_ms.unwrap_or(0.0);
+        let loop_end_ms = self.loop_end_ms.unwrap_or(sample_ms);
+        let is_looping = self.loop_start_ms.is_some() || self.loop_end_ms.is_some();
+        if is_looping
+            && (loop_start_ms < 0.0 || loop_end_ms > sample_ms || loop_start_ms >= loop_end_ms)
+        {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidLoopPoints,
+            });
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    pub fn finalize(self) -> Result<SamplePlayback, InvalidWaveForm> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        let source_frequency = self.sample.get_sampling_frequency();
+        let samples = self.sample.get_samples();
+        let peak = samples
+            .iter()
+            .map(|sample| sample.abs())
+            .reduce(f64::max)
+            .unwrap_or(0.0);
+        let normalized: Vec<f64> = samples
+            .iter()
+            .map(|sample| if peak > 0.0 { sample / peak } else { 0.0 })
+            .collect();
+        let resampled = resample(&normalized, source_frequency, self.encoding_rate);
+        let encoded_samples = self.encoding.degrade(&resampled);
+        let to_position = |time_ms: f64| time_ms * self.encoding_rate / 1000.0;
+        let loop_points = match (self.loop_start_ms, self.loop_end_ms) {
+            (None, None) => None,
+            (start, end) => Some((
+                to_position(start.unwrap_or(0.0)),
+                end.map(to_position)
+                    .unwrap_or(encoded_samples.len() as f64)
+                    .min(encoded_samples.len() as f64),
+            )),
+        };
+        // The encoded sample can end before the original one does, which
+        // leaves a loop starting right before its end nothing to play.
+        if let Some((loop_start, loop_end)) = loop_points
+            && loop_start >= loop_end
+        {
+            return Err(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidLoopPoints,
+            });
+        }
+        return Ok(SamplePlayback {
+            tone: self.tone,
+            root_tone: self.root_tone,
+            amplitude: self.amplitude,
+            encoded_samples,
+            encoding_rate: self.encoding_rate,
+            loop_points,
+            duration_ms: self.duration_ms,
+            sampling_frequency: self.sampling_frequency,
+            sample_index: 0,
+            position: 0.0,
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SamplePlayback {
+    tone: f64,
+    root_tone: f64,
+    amplitude: f64,
+    encoded_samples: Vec<f64>,
+    encoding_rate: f64,
+    loop_points: Option<(f64, f64)>,
+    duration_ms: f64,
+    sampling_frequency: f64,
+    sample_index: usize,
+    position: f64,
+}
+
+impl_has_tone!(SamplePlayback);
+impl_has_amplitude!(SamplePlayback);
+impl_has_duration!(SamplePlayback);
+impl_has_sampling_frequency!(SamplePlayback);
+
+impl SamplePlayback {
+    fn number_of_samples(&self) -> usize {
+        return milliseconds_to_samples(self.sampling_frequency, self.duration_ms);
+    }
+}
+
+impl Iterator for SamplePlayback {
+    type Item = f64;
+
+    // Like the hardware, every encoded sample is held until the next one,
+    // without any interpolation.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_index >= self.number_of_samples() {
+            return None;
+        }
+        if let Some((loop_start, loop_end)) = self.loop_points {
+            while self.position >= loop_end {
+                self.position -= loop_end - loop_start;
+            }
+        }
+        let sample = self
+            .encoded_samples
+            .get(self.position as usize)
+            .copied()
+            .unwrap_or(0.0);
+        let speed = self.tone / self.root_tone;
+        self.position += speed * self.encoding_rate / self.sampling_frequency;
+        self.sample_index = self.sample_index + 1;
+        return Some(self.amplitude * sample);
+    }
+}
+
+impl Into<Audio> for SamplePlayback {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.sampling_frequency;
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
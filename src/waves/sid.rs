@@ -0,0 +1,436 @@
+use builder_derive_macro::Setters;
+
+use crate::audio::basic_filters::LowPass;
+use crate::audio::{Audio, AudioBuilder};
+use crate::time::has_duration::HasDuration;
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::{milliseconds_to_samples, samples_to_milliseconds};
+use crate::utils::build::Build;
+use crate::waves::traits::has_amplitude::HasAmplitude;
+use crate::waves::traits::has_tone::HasTone;
+use crate::{impl_has_amplitude, impl_has_duration, impl_has_sampling_frequency, impl_has_tone};
+
+use super::{InvalidWaveForm, InvalidWaveFormKind, Lfsr};
+
+pub const SID_PAL_CLOCK: f64 = 985_248.0;
+#[allow(dead_code)]
+pub const SID_NTSC_CLOCK: f64 = 1_022_727.0;
+
+const MAX_VOICES: usize = 3;
+const ACCUMULATOR_RANGE: f64 = 16_777_216.0;
+const WAVEFORM_MAX: f64 = 4095.0;
+// Clock cycles between envelope steps for each of the 16 rate settings.
+// Decay and release use the same table, slowed down further by the
+// exponential counter.
+const ENVELOPE_RATE_PERIODS: [f64; 16] = [
+    9.0, 32.0, 63.0, 95.0, 149.0, 220.0, 267.0, 313.0, 392.0, 977.0, 1954.0, 3126.0, 3907.0,
+    11720.0, 19532.0, 31251.0,
+];
+// Nominal release times in milliseconds from the datasheet.
+const RELEASE_TIMES_MS: [f64; 16] = [
+    6.0, 24.0, 48.0, 72.0, 114.0, 168.0, 204.0, 240.0, 300.0, 750.0, 1500.0, 2400.0, 3000.0,
+    9000.0, 15000.0, 24000.0,
+];
+// The noise register of the SID shifts left and taps bits 22 and 17. This
+// one shifts right, so every bit index is mirrored.
+const NOISE_WIDTH: u8 = 23;
+const NOISE_TAPS: [u8; 2] = [0, 5];
+const NOISE_OUTPUT_BITS: [u8; 8] = [2, 4, 8, 11, 13, 17, 20, 22];
+
+// Waveforms can be combined like on the chip, where the selected outputs
+// are approximately and'ed together. The envelope rates are indices into
+// the SID rate tables, from 0 to 15, and the sustain is a level from 0 to
+// 15. Ring modulation and sync use the previous voice as their source,
+// with the first voice taking the last one, as voice 1 takes voice 3.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct SidVoice {
+    ratio: f64,
+    detune_cents: f64,
+    triangle: bool,
+    sawtooth: bool,
+    pulse: bool,
+    noise: bool,
+    duty_cycle: f64,
+    ring_modulation: bool,
+    sync: bool,
+    attack: u8,
+    decay: u8,
+    sustain: u8,
+    release: u8,
+    filtered: bool,
+}
+
+impl Default for SidVoice {
+    fn default() -> Self {
+        return Self {
+            ratio: 1.0,
+            detune_cents: 0.0,
+            triangle: false,
+            sawtooth: false,
+            pulse: true,
+            noise: false,
+            duty_cycle: 0.5,
+            ring_modulation: false,
+            sync: false,
+            attack: 0,
+            decay: 0,
+            sustain: 15,
+            release: 0,
+            filtered: false,
+        };
+    }
+}
+
+impl SidVoice {
+    fn frequency(&self, tone: f64) -> f64 {
+        return tone * self.ratio * 2.0_f64.powf(self.detune_cents / 1200.0);
+    }
+
+    fn has_waveform(&self) -> bool {
+        return self.triangle || self.sawtooth || self.pulse || self.noise;
+    }
+
+    // The 12 bit output of the waveform generator.
+    fn waveform(&self, accumulator: u32, ring_source_high: bool, lfsr: &Lfsr) -> u32 {
+        let mut output = 0xFFF;
+        if self.triangle {
+            let mut high = accumulator & 0x800000 != 0;
+            if self.ring_modulation {
+                high ^= ring_source_high;
+            }
+            let folded = if high { !accumulator } else { accumulator };
+            output &= (folded >> 11) & 0xFFF;
+        }
+        if self.sawtooth {
+            output &= accumulator >> 12;
+        }
+        if self.pulse {
+            let threshold = (self.duty_cycle * ACCUMULATOR_RANGE) as u32;
+            output &= if accumulator < threshold {
+                0xFFF
+            } else {
+                0x000
+            };
+        }
+        if self.noise {
+            let register = lfsr.get_register();
+            let byte = NOISE_OUTPUT_BITS
+                .iter()
+                .fold(0, |byte, bit| (byte << 1) | ((register >> bit) & 1));
+            output &= byte << 4;
+        }
+        return output;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct SidFilter {
+    cutoff_frequency: f64,
+    resonance: u8,
+    low_pass: bool,
+    band_pass: bool,
+    high_pass: bool,
+}
+
+impl Default for SidFilter {
+    fn default() -> Self {
+        return Self {
+            cutoff_frequency: 1000.0,
+            resonance: 0,
+            low_pass: true,
+            band_pass: false,
+            high_pass: false,
+        };
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+enum EnvelopeState {
+    #[default]
+    Attack,
+    DecaySustain,
+    Release,
+}
+
+// The envelope generator of one voice: an 8 bit counter stepped at the
+// rate of the current state. Decay and release go through an extra
+// prescaler that grows as the counter gets lower, which approximates an
+// exponential curve.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct EnvelopeGenerator {
+    state: EnvelopeState,
+    counter: u8,
+    rate_cycles: f64,
+    exponential_counter: u8,
+}
+
+impl EnvelopeGenerator {
+    fn exponential_period(&self) -> u8 {
+        match self.counter {
+            94..=255 => 1,
+            55..=93 => 2,
+            27..=54 => 4,
+            15..=26 => 8,
+            7..=14 => 16,
+            1..=6 => 30,
+            0 => 1,
+        }
+    }
+
+    fn release(&mut self) {
+        self.state = EnvelopeState::Release;
+    }
+
+    fn clock(&mut self, voice: &SidVoice, cycles: f64) {
+        let rate = match self.state {
+            EnvelopeState::Attack => voice.attack,
+            EnvelopeState::DecaySustain => voice.decay,
+            EnvelopeState::Release => voice.release,
+        };
+        let period = ENVELOPE_RATE_PERIODS[rate.min(15) as usize];
+        self.rate_cycles += cycles;
+        while self.rate_cycles >= period {
+            self.rate_cycles -= period;
+            self.step(voice);
+        }
+    }
+
+    fn step(&mut self, voice: &SidVoice) {
+        if self.state == EnvelopeState::Attack {
+            self.counter = self.counter.saturating_add(1);
+            if self.counter == 0xFF {
+                self.state = EnvelopeState::DecaySustain;
+            }
+            return;
+        }
+        self.exponential_counter += 1;
+        if self.exponential_counter < self.exponential_period() {
+            return;
+        }
+        self.exponential_counter = 0;
+        let floor = match self.state {
+            EnvelopeState::DecaySustain => voice.sustain.min(15) * 17,
+            _ => 0,
+        };
+        if self.counter > floor {
+            self.counter -= 1;
+        }
+    }
+
+    fn level(&self) -> f64 {
+        return self.counter as f64 / 255.0;
+    }
+}
+
+// Without any voice added, the chip plays a single default one.
+#[derive(Clone, Debug, PartialEq, Setters)]
+pub struct SidBuilder {
+    tone: f64,
+    amplitude: f64,
+    voices: Vec<SidVoice>,
+    filter: SidFilter,
+    clock: f64,
+    duration_ms: f64,
+    sampling_frequency: f64,
+}
+
+impl Default for SidBuilder {
+    fn default() -> Self {
+        return Self {
+            tone: 0.0,
+            amplitude: 1.0,
+            voices: vec![],
+            filter: SidFilter::default(),
+            clock: SID_PAL_CLOCK,
+            duration_ms: 0.0,
+            sampling_frequency: 44100_f64,
+        };
+    }
+}
+
+#[allow(dead_code)]
+impl SidBuilder {
+    pub fn with_voice(mut self, voice: SidVoice) -> Self {
+        self.voices.push(voice);
+        return self;
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<InvalidWaveForm>> {
+        let mut possible_errors: Vec<InvalidWaveForm> = vec![];
+        if self.duration_ms < 0.0 {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativeDuration,
+            });
+        }
+        if self.voices.len() > MAX_VOICES {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidVoiceCount,
+            });
+        }
+        if self.voices.iter().any(|voice| voice.duty_cycle < 0.0) {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NegativeDutyCycle,
+            });
+        }
+        if self.voices.iter().any(|voice| voice.duty_cycle > 1.0) {
+            possible_errors.push(InvalidWaveForm {
+                kind: InvalidWaveFormKind::DutyCycleBiggerThanOne,
+            });
+        }
+        if !possible_errors.is_empty() {
+            return Err(possible_errors);
+        };
+        return Ok(());
+    }
+
+    pub fn finalize(self) -> Result<Sid, InvalidWaveForm> {
+        if let Result::Err(error) = self.validate() {
+            return Err(error[0].clone());
+        }
+        let voices = match self.voices.is_empty() {
+            true => vec![SidVoice::default()],
+            false => self.voices,
+        };
+        let number_of_voices = voices.len();
+        let mut lfsr = Lfsr::new(NOISE_WIDTH, NOISE_TAPS.to_vec()).expect("a valid register");
+        lfsr.fill();
+        let resonance = self.filter.resonance.min(15) as f64 / 15.0;
+        let low_pass = LowPass::new(self.filter.cutoff_frequency, resonance, 0.0);
+        return Ok(Sid {
+            tone: self.tone,
+            amplitude: self.amplitude,
+            voices,
+            filter: self.filter,
+            clock: self.clock,
+            duration_ms: self.duration_ms,
+            sampling_frequency: self.sampling_frequency,
+            sample_index: 0,
+            phases: vec![0.0; number_of_voices],
+            envelopes: vec![EnvelopeGenerator::default(); number_of_voices],
+            lfsrs: vec![lfsr; number_of_voices],
+            low_pass,
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sid {
+    tone: f64,
+    amplitude: f64,
+    voices: Vec<SidVoice>,
+    filter: SidFilter,
+    clock: f64,
+    duration_ms: f64,
+    sampling_frequency: f64,
+    sample_index: usize,
+    phases: Vec<f64>,
+    envelopes: Vec<EnvelopeGenerator>,
+    lfsrs: Vec<Lfsr>,
+    low_pass: LowPass,
+}
+
+impl_has_tone!(Sid);
+impl_has_amplitude!(Sid);
+impl_has_duration!(Sid);
+impl_has_sampling_frequency!(Sid);
+
+impl Sid {
+    fn number_of_samples(&self) -> usize {
+        return milliseconds_to_samples(self.sampling_frequency, self.duration_ms);
+    }
+
+    fn modulation_source(&self, index: usize) -> usize {
+        return (index + self.voices.len() - 1) % self.voices.len();
+    }
+
+    // Like the envelopes in the audio module, the release happens inside
+    // the duration of the sound, so the gate closes before its end.
+    fn gate_open(&self, voice: &SidVoice, time_ms: f64) -> bool {
+        let release_ms = RELEASE_TIMES_MS[voice.release.min(15) as usize];
+        return time_ms < self.duration_ms - release_ms;
+    }
+
+    // The state variable filter has the same 12 dB slopes as the filter of
+    // the SID, and each of its outputs can be mixed in.
+    fn filter(&mut self, input: f64) -> f64 {
+        let (low, band, high) = self.low_pass.next_outputs(input, self.sampling_frequency);
+        let mut output = 0.0;
+        if self.filter.low_pass {
+            output += low;
+        }
+        if self.filter.band_pass {
+            output += band;
+        }
+        if self.filter.high_pass {
+            output += high;
+        }
+        return output;
+    }
+}
+
+impl Iterator for Sid {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample_index >= self.number_of_samples() {
+            return None;
+        }
+        let time_ms = samples_to_milliseconds(self.sampling_frequency, self.sample_index);
+        let cycles_per_sample = self.clock / self.sampling_frequency;
+        let accumulators: Vec<u32> = self
+            .phases
+            .iter()
+            .map(|phase| (phase * ACCUMULATOR_RANGE) as u32)
+            .collect();
+        let mut filtered = 0.0;
+        let mut unfiltered = 0.0;
+        for index in 0..self.voices.len() {
+            let voice = self.voices[index].clone();
+            if !self.gate_open(&voice, time_ms) {
+                self.envelopes[index].release();
+            }
+            self.envelopes[index].clock(&voice, cycles_per_sample);
+            if !voice.has_waveform() {
+                continue;
+            }
+            let source_high = accumulators[self.modulation_source(index)] & 0x800000 != 0;
+            let waveform = voice.waveform(accumulators[index], source_high, &self.lfsrs[index]);
+            let output =
+                (2.0 * waveform as f64 / WAVEFORM_MAX - 1.0) * self.envelopes[index].level();
+            if voice.filtered {
+                filtered += output;
+            } else {
+                unfiltered += output;
+            }
+        }
+        let mut wrapped = vec![false; self.voices.len()];
+        for (index, voice) in self.voices.iter().enumerate() {
+            let increment = voice.frequency(self.tone) / self.sampling_frequency;
+            let next_phase = self.phases[index] + increment;
+            // Bit 19 of the accumulator clocks the noise register, 16 times
+            // per period.
+            let noise_clocks = (next_phase * 16.0).floor() - (self.phases[index] * 16.0).floor();
+            for _ in 0..noise_clocks as usize {
+                self.lfsrs[index].step();
+            }
+            wrapped[index] = next_phase >= 1.0;
+            self.phases[index] = next_phase % 1.0;
+        }
+        for index in 0..self.voices.len() {
+            if self.voices[index].sync && wrapped[self.modulation_source(index)] {
+                self.phases[index] = 0.0;
+            }
+        }
+        let output = self.filter(filtered) + unfiltered;
+        self.sample_index = self.sample_index + 1;
+        return Some(self.amplitude * output / self.voices.len() as f64);
+    }
+}
+
+impl Into<Audio> for Sid {
+    fn into(self) -> Audio {
+        let sampling_frequency = self.sampling_frequency;
+        let builder = AudioBuilder::new(self.collect(), sampling_frequency);
+        return builder.finalize().expect("TODO");
+    }
+}
@@ -0,0 +1,451 @@
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::super::*;
+    use crate::audio::{Audio, AudioBuilder};
+    use crate::utils::build::Build;
+    use crate::utils::fft::{rfft, rfft_freq_bins};
+    use crate::utils::testing::count_rising_crossings;
+    use crate::waves::traits::has_tone::HasTone;
+
+    fn samples<T: Into<Audio>>(wave: T) -> Vec<f64> {
+        let audio: Audio = wave.into();
+        return audio.get_samples();
+    }
+
+    // The phase of a tone at the given sample, at 44.1 kHz.
+    fn phase(tone: f64, sample: usize) -> f64 {
+        return (2.0 * PI * tone * sample as f64 / 44100.0) % (2.0 * PI);
+    }
+
+    fn fm(builder: FmBuilder) -> Vec<f64> {
+        let wave = builder
+            .with_tone(440.0)
+            .with_duration_ms(10.0)
+            .finalize()
+            .unwrap();
+        return samples(wave);
+    }
+
+    #[test]
+    fn test_fm_operators() {
+        let operator = FmOperator::default;
+        let three = FmBuilder::default()
+            .with_operator(operator())
+            .with_operator(operator())
+            .with_operator(operator())
+            .finalize();
+        assert!(three.is_ok());
+        let one = FmBuilder::default().with_operator(operator()).finalize();
+        assert_eq!(
+            one,
+            Err(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidOperatorCount
+            })
+        );
+        let five = (0..5).fold(FmBuilder::default(), |builder, _| {
+            builder.with_operator(operator())
+        });
+        assert!(five.finalize().is_err());
+        // Without operators, two default ones: the first modulates the
+        // second.
+        let default = fm(FmBuilder::default());
+        for index in [1, 57, 301] {
+            let expected = (phase(440.0, index) + phase(440.0, index).sin()).sin();
+            assert!((default[index] - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_fm_algorithms() {
+        let modulator = FmOperator::default()
+            .with_ratio(2.0)
+            .with_modulation_index(0.5);
+        let carrier = FmOperator::default();
+        let builder = FmBuilder::default()
+            .with_operator(modulator)
+            .with_operator(carrier);
+        // In series, the first operator modulates the second.
+        let series = fm(builder.clone());
+        // Side by side, both are carriers and are averaged.
+        let parallel = fm(builder.with_algorithm(FmAlgorithm::Algorithm7));
+        for index in [1, 57, 301] {
+            let (modulation, carrier_phase) = (phase(880.0, index), phase(440.0, index));
+            let expected = (carrier_phase + 0.5 * modulation.sin()).sin();
+            assert!((series[index] - expected).abs() < 1e-9);
+            let expected = (0.5 * modulation.sin() + carrier_phase.sin()) / 2.0;
+            assert!((parallel[index] - expected).abs() < 1e-9);
+        }
+    }
+
+    fn additive(builder: AdditiveBuilder, tone: f64) -> Vec<f64> {
+        let wave = builder
+            .with_tone(tone)
+            .with_duration_ms(10.0)
+            .finalize()
+            .unwrap();
+        return samples(wave);
+    }
+
+    #[test]
+    fn test_additive_partials() {
+        let builder = AdditiveBuilder::default()
+            .with_partial(1.0, 0.5, 0.0)
+            .with_partial(3.0, 0.25, 0.0);
+        let spectrum = additive(builder.clone(), 440.0);
+        for index in [1, 57, 301] {
+            let expected = 0.5 * phase(440.0, index).sin() + 0.25 * phase(1320.0, index).sin();
+            assert!((spectrum[index] - expected).abs() < 1e-9);
+        }
+        // The third partial of 10 kHz is past the Nyquist frequency.
+        let truncated = additive(builder, 10000.0);
+        for index in [1, 57, 301] {
+            let expected = 0.5 * phase(10000.0, index).sin();
+            assert!((truncated[index] - expected).abs() < 1e-9);
+        }
+        let fundamental = additive(AdditiveBuilder::default(), 440.0);
+        assert!((fundamental[57] - phase(440.0, 57).sin()).abs() < 1e-9);
+        let square = AdditivePreset::Square.partials();
+        assert_eq!(square[1].ratio, 3.0);
+        assert!((square[1].amplitude - square[0].amplitude / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_plucked_string() {
+        let string = PluckedStringBuilder::default()
+            .with_tone(220.0)
+            .with_duration_ms(1500.0)
+            .finalize()
+            .unwrap();
+        let plucked = samples(string);
+        assert_eq!(plucked.len(), 66150);
+        // The harmonics of the burst cross zero many times a period, so the
+        // period is found where the string best matches itself delayed.
+        let correlation = |lag: usize| -> f64 {
+            return plucked[22050..44100]
+                .iter()
+                .zip(&plucked[22050 + lag..])
+                .map(|(sample, delayed)| sample * delayed)
+                .sum();
+        };
+        let period = (100..400)
+            .max_by(|a, b| correlation(*a).total_cmp(&correlation(*b)))
+            .unwrap();
+        assert!((44100.0 / period as f64 - 220.0).abs() < 1.5);
+        assert!(plucked[..4410].iter().any(|sample| sample.abs() > 0.5));
+        let toneless = PluckedStringBuilder::default()
+            .with_duration_ms(100.0)
+            .finalize()
+            .unwrap();
+        let silence = samples(toneless);
+        assert_eq!(silence.len(), 4410);
+        assert!(silence.iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn test_membrane() {
+        // Without brightness only the fundamental mode sounds.
+        let drum = MembraneBuilder::default()
+            .with_tone(100.0)
+            .with_brightness(0.0)
+            .with_duration_ms(1000.0)
+            .finalize()
+            .unwrap();
+        let struck = samples(drum);
+        assert_eq!(count_rising_crossings(&struck), 99);
+        let peak = |samples: &[f64]| samples.iter().fold(0.0_f64, |peak, x| peak.max(x.abs()));
+        // The default decay is 150 ms.
+        let ratio = peak(&struck[6615..7056]) / peak(&struck[..441]);
+        assert!((ratio - (-1.0_f64).exp()).abs() < 0.05);
+    }
+
+    fn lfsr_period(mode: LfsrMode) -> usize {
+        let mut lfsr = Lfsr::from_mode(&mode);
+        let start = lfsr.get_register();
+        lfsr.step();
+        let mut period = 1;
+        while lfsr.get_register() != start {
+            lfsr.step();
+            period += 1;
+        }
+        return period;
+    }
+
+    #[test]
+    fn test_lfsr() {
+        assert_eq!(lfsr_period(LfsrMode::Long), 32767);
+        assert_eq!(lfsr_period(LfsrMode::Short), 93);
+        assert_eq!(lfsr_period(LfsrMode::GameBoyShort), 127);
+        let invalid_width = InvalidWaveForm {
+            kind: InvalidWaveFormKind::InvalidLfsrWidth,
+        };
+        assert_eq!(Lfsr::new(0, vec![0]), Err(invalid_width.clone()));
+        assert_eq!(Lfsr::new(33, vec![0]), Err(invalid_width.clone()));
+        assert_eq!(
+            Lfsr::new(7, vec![0, 7]),
+            Err(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidLfsrTaps
+            })
+        );
+        let builder = LfsrNoiseBuilder::default().with_width(0);
+        assert_eq!(builder.finalize(), Err(invalid_width));
+        for clock_frequency in [0.0, -1.0, f64::NAN] {
+            assert_eq!(
+                LfsrNoiseBuilder::default()
+                    .with_clock_frequency(clock_frequency)
+                    .finalize(),
+                Err(InvalidWaveForm {
+                    kind: InvalidWaveFormKind::NonPositiveClockFrequency
+                })
+            );
+        }
+        let noise = LfsrNoiseBuilder::default()
+            .with_duration_ms(100.0)
+            .finalize()
+            .unwrap();
+        let noise = samples(noise);
+        assert_eq!(noise.len(), 4410);
+        assert!(noise.iter().all(|sample| sample.abs() <= 1.0));
+        assert!(noise.iter().any(|sample| *sample > 0.5));
+        assert!(noise.iter().any(|sample| *sample < -0.5));
+    }
+
+    // The slope of the spectrum, in dB per octave, between the octaves
+    // starting at 250 Hz and at 2 kHz.
+    fn spectral_slope(variant: NoiseVariant) -> f64 {
+        let noise = NoiseBuilder::default()
+            .with_variant(variant)
+            .with_duration_ms(2000.0)
+            .finalize()
+            .unwrap();
+        let noise = samples(noise);
+        let spectrum = rfft(&noise);
+        let frequencies = rfft_freq_bins(noise.len(), 44100.0);
+        let band_power = |low: f64| {
+            let powers: Vec<f64> = frequencies
+                .iter()
+                .zip(&spectrum)
+                .filter(|(frequency, _)| **frequency >= low && **frequency < 2.0 * low)
+                .map(|(_, bin)| bin.norm_sqr())
+                .collect();
+            return powers.iter().sum::<f64>() / powers.len() as f64;
+        };
+        return 10.0 * (band_power(2000.0) / band_power(250.0)).log10() / 3.0;
+    }
+
+    #[test]
+    fn test_noise_colors() {
+        let slopes = [
+            (NoiseVariant::Violet, 6.0),
+            (NoiseVariant::Blue, 3.0),
+            (NoiseVariant::White, 0.0),
+            (NoiseVariant::Pink, -3.0),
+            (NoiseVariant::Brown, -6.0),
+        ];
+        for (variant, expected) in slopes {
+            let slope = spectral_slope(variant.clone());
+            assert!((slope - expected).abs() < 1.0, "{:?}: {}", variant, slope);
+        }
+    }
+
+    fn sid(builder: SidBuilder, tone: f64) -> Vec<f64> {
+        let wave = builder
+            .with_tone(tone)
+            .with_duration_ms(100.0)
+            .finalize()
+            .unwrap();
+        return samples(wave);
+    }
+
+    fn rms(samples: &[f64]) -> f64 {
+        return (samples.iter().map(|sample| sample * sample).sum::<f64>() / samples.len() as f64)
+            .sqrt();
+    }
+
+    #[test]
+    fn test_sid_voices() {
+        let voice = SidVoice::default;
+        let three = SidBuilder::default()
+            .with_voice(voice())
+            .with_voice(voice())
+            .with_voice(voice());
+        assert!(three.clone().finalize().is_ok());
+        assert_eq!(
+            three.with_voice(voice()).finalize(),
+            Err(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidVoiceCount
+            })
+        );
+        // Sawtooth and pulse together are and'ed: the sawtooth for the first
+        // half of the period, then nothing, once the attack is over.
+        let combined = voice().with_sawtooth(true).with_pulse(true);
+        let combined = sid(SidBuilder::default().with_voice(combined), 100.0);
+        let sawtooth = voice().with_sawtooth(true).with_pulse(false);
+        let sawtooth = sid(SidBuilder::default().with_voice(sawtooth), 100.0);
+        let silent = |samples: &[f64]| samples.iter().filter(|sample| **sample == -1.0).count();
+        assert!((silent(&combined[882..1764]) as f64 - 441.0).abs() < 10.0);
+        // Alone, the sawtooth only starts from nothing once per period.
+        assert!(silent(&sawtooth[882..1764]) <= 2);
+        let default = sid(SidBuilder::default(), 100.0);
+        assert!(default[882..1764].iter().all(|sample| sample.abs() == 1.0));
+    }
+
+    #[test]
+    fn test_sid_filter() {
+        let voice = SidVoice::default();
+        let filter = SidFilter::default().with_cutoff_frequency(200.0);
+        let unfiltered = SidBuilder::default().with_voice(voice.clone());
+        let unfiltered = sid(unfiltered.with_filter(filter.clone()), 2000.0);
+        let builder = SidBuilder::default().with_voice(voice.with_filtered(true));
+        let unfiltered = rms(&unfiltered[882..3528]);
+        let low_pass = sid(builder.clone().with_filter(filter.clone()), 2000.0);
+        let filter = filter.with_low_pass(false).with_high_pass(true);
+        let high_pass = sid(builder.with_filter(filter), 2000.0);
+        assert!(rms(&low_pass[882..3528]) < 0.2 * unfiltered);
+        assert!(rms(&high_pass[882..3528]) > 0.7 * unfiltered);
+    }
+
+    #[test]
+    fn test_pitch_quantizer() {
+        type Preset = PitchQuantizerPreset;
+        let presets = [
+            (Preset::NesPulse, 253, 1.561),
+            (Preset::NesTriangle, 126, 1.561),
+            (Preset::GameBoySquare, 298, -0.634),
+            (Preset::GameBoyWave, 149, -0.634),
+            (Preset::Sn76489, 254, 1.560),
+            (Preset::Ay38910, 252, -0.664),
+        ];
+        for (preset, period, cents_error) in presets {
+            let quantizer = PitchQuantizer::from_preset(&preset);
+            let pitch = quantizer.quantize(440.0).unwrap();
+            assert_eq!(pitch.period, period);
+            assert_eq!(pitch.frequency, quantizer.frequency(period));
+            assert!((pitch.cents_error - cents_error).abs() < 1e-3);
+            // Every note of the middle octaves gets the period closest in
+            // cents, whichever way the chip rounds.
+            for octave in 3..6 {
+                for note in [
+                    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+                ] {
+                    let note = format!("{}{}", note, octave);
+                    let pitch = quantizer.quantize_note(&note).unwrap();
+                    let tone = pitch.frequency / 2.0_f64.powf(pitch.cents_error / 1200.0);
+                    let cents_error =
+                        |period: u32| 1200.0 * (quantizer.frequency(period) / tone).log2();
+                    assert!((cents_error(pitch.period) - pitch.cents_error).abs() < 1e-9);
+                    assert!(pitch.cents_error.abs() <= cents_error(pitch.period - 1).abs());
+                    assert!(pitch.cents_error.abs() <= cents_error(pitch.period + 1).abs());
+                    assert!(pitch.cents_error.abs() < 25.0);
+                }
+            }
+        }
+        // Out of the range of the chip, the closest end of it.
+        let quantizer = PitchQuantizer::from_preset(&Preset::NesPulse);
+        assert_eq!(quantizer.quantize(10.0).unwrap().period, 0x7FF);
+        assert_eq!(quantizer.quantize(20000.0).unwrap().period, 8);
+        for frequency in [0.0, -440.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(quantizer.quantize(frequency), None);
+        }
+        let mut pulse = PulseBuilder::default().with_tone(440.0).finalize().unwrap();
+        assert_eq!(pulse.set_quantized_tone(0.0, &quantizer), None);
+        assert_eq!(pulse.get_tone(), 440.0);
+        assert!(pulse.set_quantized_tone(220.0, &quantizer).is_some());
+        assert_ne!(pulse.get_tone(), 440.0);
+    }
+
+    // Plays the sample back at the rate it is encoded at, so every output
+    // sample is the next encoded one.
+    fn played_back(builder: SamplePlaybackBuilder, original: Vec<f64>, rate: f64) -> Vec<f64> {
+        let duration_ms = original.len() as f64 * 1000.0 / rate;
+        let sample = AudioBuilder::new(original, rate).finalize().unwrap();
+        let playback = builder
+            .with_sample(sample)
+            .with_encoding_rate(rate)
+            .with_sampling_frequency(rate)
+            .with_duration_ms(duration_ms)
+            .finalize()
+            .unwrap();
+        return samples(playback);
+    }
+
+    #[test]
+    fn test_dpcm() {
+        let builder = SamplePlaybackBuilder::default().with_encoding(SampleEncoding::Dpcm);
+        let encoded = played_back(builder, vec![1.0; 100], 44100.0);
+        // From the middle, the counter climbs 2 of its 127 steps at a time
+        // and stays at the top.
+        assert!((encoded[0] - (2.0 * 66.0 / 127.0 - 1.0)).abs() < 1e-12);
+        for window in encoded[..31].windows(2) {
+            assert!((window[1] - window[0] - 4.0 / 127.0).abs() < 1e-12);
+        }
+        assert!(
+            encoded[31..]
+                .iter()
+                .all(|sample| (sample - (2.0 * 126.0 / 127.0 - 1.0)).abs() < 1e-12)
+        );
+    }
+
+    #[test]
+    fn test_adpcm() {
+        let sine: Vec<f64> = (0..4410).map(|index| phase(440.0, index).sin()).collect();
+        let builder = SamplePlaybackBuilder::default().with_encoding(SampleEncoding::Adpcm);
+        let decoded = played_back(builder, sine.clone(), 44100.0);
+        assert_eq!(decoded.len(), sine.len());
+        // Once the step size has adapted, the decoded wave follows the
+        // original closely.
+        let error = decoded[100..]
+            .iter()
+            .zip(&sine[100..])
+            .map(|(decoded, sample)| (decoded - sample).abs())
+            .reduce(f64::max)
+            .unwrap();
+        assert!(error < 0.05);
+    }
+
+    #[test]
+    fn test_sample_loop() {
+        let ramp: Vec<f64> = (0..10).map(|index| index as f64 / 9.0).collect();
+        let sample = AudioBuilder::new(ramp.clone(), 1000.0).finalize().unwrap();
+        let builder = SamplePlaybackBuilder::default()
+            .with_sample(sample)
+            .with_encoding(SampleEncoding::Pcm(16))
+            .with_encoding_rate(1000.0)
+            .with_sampling_frequency(1000.0)
+            .with_duration_ms(12.0);
+        let looped = builder
+            .clone()
+            .with_loop_start_ms(Some(2.0))
+            .with_loop_end_ms(Some(6.0))
+            .finalize()
+            .unwrap();
+        let positions = [0, 1, 2, 3, 4, 5, 2, 3, 4, 5, 2, 3];
+        for (sample, position) in samples(looped).iter().zip(positions) {
+            assert!((sample - ramp[position]).abs() < 1e-4);
+        }
+        let unlooped = samples(builder.clone().finalize().unwrap());
+        assert!(unlooped[10..].iter().all(|sample| *sample == 0.0));
+        assert_eq!(
+            builder.clone().with_tone(0.0).finalize(),
+            Err(InvalidWaveForm {
+                kind: InvalidWaveFormKind::NonPositiveTone
+            })
+        );
+        // 1000 samples last 22.68 ms, but only 751 are left once encoded at
+        // the default rate, so a loop starting at 22.67 ms has nothing left.
+        let sample = AudioBuilder::new(vec![0.5; 1000], 44100.0)
+            .finalize()
+            .unwrap();
+        assert_eq!(
+            SamplePlaybackBuilder::default()
+                .with_sample(sample)
+                .with_loop_start_ms(Some(22.67))
+                .with_duration_ms(100.0)
+                .finalize(),
+            Err(InvalidWaveForm {
+                kind: InvalidWaveFormKind::InvalidLoopPoints
+            })
+        );
+    }
+}
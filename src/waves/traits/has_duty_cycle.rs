@@ -0,0 +1,21 @@
+#[allow(dead_code)]
+pub trait HasDutyCycle {
+    fn get_duty_cycle(&self) -> f64;
+
+    fn set_duty_cycle(&mut self, duty_cycle: f64);
+}
+
+#[macro_export]
+macro_rules! impl_has_duty_cycle {
+    ($name: ty) => {
+        impl HasDutyCycle for $name {
+            fn get_duty_cycle(&self) -> f64 {
+                self.duty_cycle
+            }
+
+            fn set_duty_cycle(&mut self, duty_cycle: f64) {
+                self.duty_cycle = duty_cycle;
+            }
+        }
+    };
+}
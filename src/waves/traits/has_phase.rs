@@ -1,3 +1,10 @@
+use std::f64::consts::PI;
+
+use crate::time::has_sampling_frequency::HasSamplingFrequency;
+use crate::time::samples_to_seconds;
+
+use super::has_tone::HasTone;
+
 #[allow(dead_code)]
 pub trait HasPhase {
     fn get_phase_rad(&self) -> f64;
@@ -27,3 +34,17 @@ macro_rules! impl_has_phase {
         }
     };
 }
+
+// The waves compute their samples from the time since they started, so
+// the phase is moved to keep the wave where it was in its period.
+pub fn retune<W: HasPhase + HasTone + HasSamplingFrequency>(
+    wave: &mut W,
+    sample_index: usize,
+    tone: f64,
+) {
+    let time = samples_to_seconds(wave.get_sampling_frequency(), sample_index);
+    let cycles = time * wave.get_tone() + wave.get_phase_rad() / (2.0 * PI);
+    let phase = (cycles - time * tone).rem_euclid(1.0);
+    wave.set_phase_rad(2.0 * PI * phase);
+    wave.set_tone(tone);
+}
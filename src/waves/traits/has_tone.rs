@@ -1,3 +1,5 @@
+use crate::waves::PitchQuantizer;
+
 pub const SEMI_TONE_FACTOR: f64 = f64::from_bits(4607450216769616227);
 
 pub const C_0_FREQUENCY: f64 = f64::from_bits(4625295783300872534);
@@ -191,6 +193,23 @@ pub trait HasTone {
         let new_tone = self.parse_note(note).ok_or(())?;
         Ok(self.set_tone(new_tone))
     }
+
+    // Both return the cents error of the tone that was actually set, and
+    // leave the tone as it was when the chip can't play the new one.
+    fn set_quantized_tone(&mut self, tone: f64, quantizer: &PitchQuantizer) -> Option<f64> {
+        let pitch = quantizer.quantize(tone)?;
+        self.set_tone(pitch.frequency);
+        Some(pitch.cents_error)
+    }
+
+    fn parse_and_set_quantized_tone(
+        &mut self,
+        note: &str,
+        quantizer: &PitchQuantizer,
+    ) -> Result<f64, ()> {
+        let new_tone = self.parse_note(note).ok_or(())?;
+        self.set_quantized_tone(new_tone, quantizer).ok_or(())
+    }
 }
 
 #[macro_export]
@@ -1,4 +1,5 @@
 pub mod has_amplitude;
 pub mod has_duration;
+pub mod has_duty_cycle;
 pub mod has_phase;
 pub mod has_tone;